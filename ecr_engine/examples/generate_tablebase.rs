@@ -0,0 +1,47 @@
+//! Generates the tablebases for some material and everything it can turn into and writes them
+//! into a directory, e.g.
+//!
+//! ```text
+//! cargo run --release --example generate_tablebase -- tablebases KRvK KQvKR
+//! ```
+
+use std::env;
+use std::process;
+use std::time::Instant;
+
+use ecr_engine::eval::endgame::MaterialSignature;
+use ecr_engine::search::tablebase::Tablebases;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("usage: generate_tablebase <directory> <material>...");
+        process::exit(1);
+    }
+    let mut tablebases = Tablebases::load_dir(&args[0]).unwrap_or_default();
+    for material in &args[1..] {
+        let signature: MaterialSignature = match material.parse() {
+            Ok(signature) => signature,
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        };
+        let start = Instant::now();
+        if let Err(error) = tablebases.generate(signature) {
+            eprintln!("cannot generate {}: {}", signature, error);
+            process::exit(1);
+        }
+        println!("generated {} in {:.1?}", signature, start.elapsed());
+    }
+    for table in tablebases.get_tablebases() {
+        match table.get_longest_mate() {
+            Some(plies) => println!("{}: longest mate in {} plies", table.get_signature(), plies),
+            None => println!("{}: no wins", table.get_signature()),
+        }
+    }
+    if let Err(error) = tablebases.save_dir(&args[0]) {
+        eprintln!("cannot write the tablebases: {}", error);
+        process::exit(1);
+    }
+}
@@ -1,6 +1,5 @@
-use std::cell::RefCell;
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use ecr_formats::fen::{Fen, FenPiecePlacements};
 pub use ecr_shared::board::BoardCastleState;
@@ -8,14 +7,15 @@ use ecr_shared::coordinate::Coordinate;
 
 use crate::pieces::{BoardPiece, PieceColor, PieceType};
 use crate::r#move::Move;
-use crate::utils::{get_en_passant_actual, new_rc_refcell};
+use crate::score::Score;
+use crate::utils::{get_en_passant_actual, new_shared, SharedCell};
 use std::fmt::Formatter;
 
 // Just exists so we can safely
 
-/// The inner content of a square. Holds a reference-counted pointer to a [`RefCell`] that holds a
-/// [`BoardPiece`].
-pub type SquareInner = Rc<RefCell<BoardPiece>>;
+/// The inner content of a square. Holds a reference-counted pointer to a [`SharedCell`] that holds
+/// a [`BoardPiece`]. Both can be sent between threads, so the [`Board`] can be sent as well.
+pub type SquareInner = Arc<SharedCell<BoardPiece>>;
 
 /// A [`Board`] contains the current game of chess.
 #[derive(Debug)]
@@ -99,7 +99,8 @@ impl ThreatenedState {
 }
 
 impl Clone for Board {
-    /// Since we use RefCells we can't just clone a board but have to replace the references inside.
+    /// Since we use SharedCells we can't just clone a board but have to replace the references
+    /// inside.
     fn clone(&self) -> Self {
         // First we create an empty board to clone to.
         let mut board_clone = Board::empty();
@@ -107,7 +108,7 @@ impl Clone for Board {
         let mut cloned_pieces = vec![];
         for inner in &self.pieces {
             let board_piece_to_add: BoardPiece = inner.borrow().deref().clone();
-            let piece = Rc::new(RefCell::new(board_piece_to_add));
+            let piece = new_shared(board_piece_to_add);
             cloned_pieces.push(piece);
             // TODO: This doesn't clone the Piece
         }
@@ -119,6 +120,7 @@ impl Clone for Board {
         board_clone.move_number = self.get_move_number();
         board_clone.moves = self.moves.clone();
         board_clone.to_move = self.to_move;
+        board_clone.threatened_state = self.threatened_state.clone();
 
         board_clone
     }
@@ -154,7 +156,7 @@ impl Board {
         // First we get the right column of the piece
         let column = self.board.get_mut(target.get_x() as usize).unwrap();
         // Then we get the row as a range since splice() requires a range, which is totally necessary for changing one variable.
-        let column_index_range = target.get_y() as usize..target.get_y() as usize;
+        let column_index_range = target.get_y() as usize..=target.get_y() as usize;
 
         column.splice(column_index_range, vec![None]);
     }
@@ -187,7 +189,7 @@ impl Board {
         // ? -> square not found
         let square = column.get(coordinate.get_y() as usize)?;
         // If it was found, clone the BoardPiece for future access
-        square.as_ref().map(|v| Rc::clone(v))
+        square.as_ref().map(Arc::clone)
     }
 
     /// Adds a piece to the board. Since a hybrid solution for saving the board is used, the piece
@@ -195,7 +197,7 @@ impl Board {
     pub fn add_piece(&mut self, piece: BoardPiece) {
         self.add_piece_to_board(piece.clone());
 
-        let square_inner = new_rc_refcell(piece);
+        let square_inner = new_shared(piece);
         // Since we are using a hybrid approach for saving the board and its pieces, we have to add
         // the square to the list of all pieces, too
         self.pieces.push(square_inner);
@@ -213,10 +215,10 @@ impl Board {
         // a range that consists of the x coordinate
         let column_index_range = y_coordinate as usize..=y_coordinate as usize;
 
-        let square_inner: SquareInner = new_rc_refcell(piece);
+        let square_inner: SquareInner = new_shared(piece);
 
         // Replaces the square with the supplied piece
-        column.splice(column_index_range, vec![Some(Rc::clone(&square_inner))]);
+        column.splice(column_index_range, vec![Some(Arc::clone(&square_inner))]);
     }
 
     pub fn get_pieces_by_type(&self, piece_type: PieceType) -> Vec<SquareInner> {
@@ -287,7 +289,7 @@ impl Board {
 
     /// Adds a threat to the square by the given team.
     pub fn add_threat(&mut self, square: Coordinate, team: PieceColor) {
-        let mut current_state = self.get_threatened_state(square).clone();
+        let mut current_state = self.get_threatened_state(square);
         match team {
             PieceColor::Light => {
                 current_state.threatened_light += 1;
@@ -305,7 +307,7 @@ impl Board {
             threatened_light: 0,
             threatened_dark: 0,
         };
-        let range = 0_usize..7;
+        let range = 0_usize..=7;
         for i in 0..=7 {
             let column = self.threatened_state.get_mut(i).unwrap();
             column.splice(
@@ -318,14 +320,21 @@ impl Board {
         }
     }
 
-    /// This function returns a float, which returns a positive value if light is ahead and a
-    /// negative value if  dark is ahead(MiniMax Implementation).
-    pub fn eval_board(&self) -> f32 {
+    /// This function returns a score, which is positive if light is ahead and negative if dark is
+    /// ahead (MiniMax Implementation).
+    pub fn eval_board(&self) -> Score {
         self.eval()
     }
 
+    /// Passes the right to move to the opponent without moving a piece. Used by the null move
+    /// pruning of the search.
+    pub fn null_move(&mut self) {
+        self.to_move = self.to_move.get_opponent();
+        self.en_passant = None;
+    }
+
     /// This function returns the pieces of a team. Useful for the eval function as well as the move_gen function.
-    pub fn get_team_pieces(&self, team_color: PieceColor) -> Vec<&RefCell<BoardPiece>> {
+    pub fn get_team_pieces(&self, team_color: PieceColor) -> Vec<&SharedCell<BoardPiece>> {
         let mut result = vec![];
         for piece in &self.pieces {
             if piece.as_ref().borrow().deref().get_color() == team_color {
@@ -506,16 +515,16 @@ impl From<Board> for Fen {
 
 impl std::fmt::Display for Board {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Fen: {}, Eval: {}",
-            Fen::from(self.clone()).to_string(),
-            self.eval()
-        )
+        write!(f, "Fen: {}, Eval: {}", Fen::from(self.clone()), self.eval())
     }
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::unnecessary_cast,
+    clippy::get_first,
+    clippy::useless_conversion
+)]
 mod tests {
     use super::*;
 
@@ -576,6 +585,43 @@ mod tests {
             assert_eq!(None, default_board.get_at((0, 1).into()));
         }
 
+        #[test]
+        fn test_remove_piece_keeps_column() {
+            let mut default_board = Board::default();
+            default_board.remove_piece((0, 1).into());
+            assert_eq!(8, default_board.board[0].len());
+            assert!(default_board.get_at((0, 0).into()).is_some());
+            assert!(default_board.get_at((0, 2).into()).is_none());
+            assert!(default_board.get_at((0, 6).into()).is_some());
+        }
+
+        #[test]
+        fn test_clone_threatened_state() {
+            let mut board = Board::empty();
+            board.add_threat((3, 4).into(), PieceColor::Dark);
+            let cloned_board = board.clone();
+            assert_eq!(
+                board.get_threatened_state((3, 4).into()),
+                cloned_board.get_threatened_state((3, 4).into())
+            );
+        }
+
+        #[test]
+        fn test_remove_all_threats() {
+            let mut board = Board::empty();
+            board.add_threat((7, 7).into(), PieceColor::Light);
+            board.add_threat((0, 0).into(), PieceColor::Dark);
+            board.remove_all_threats();
+            let expected = ThreatenedState {
+                threatened_light: 0,
+                threatened_dark: 0,
+            };
+            for column in &board.threatened_state {
+                assert_eq!(8, column.len());
+                assert!(column.iter().all(|state| *state == expected));
+            }
+        }
+
         #[test]
         fn test_get_light_to_move() {
             let mut b = Board::empty();
@@ -593,7 +639,7 @@ mod tests {
             let mut b = Board::empty();
             let p = BoardPiece::new_from_type(PieceType::Pawn, (2, 1).into(), PieceColor::Light);
             let column = b.board.get_mut(2).unwrap();
-            column.insert(1, Some(Rc::new(RefCell::new(p.clone()))));
+            column.insert(1, Some(new_shared(p.clone())));
 
             assert_eq!(None, b.get_at((0 as u8, 0 as u8).into()));
             let square_from_board = b.get_at((2 as u8, 1 as u8).into()).unwrap();
@@ -683,7 +729,7 @@ mod tests {
         fn test_eval_board() {
             let default_board: Board = board::Board::default();
             let result = default_board.eval_board();
-            assert_eq!(0.0, result);
+            assert_eq!(Score::DRAW, result);
         }
 
         #[test]
@@ -864,6 +910,17 @@ mod tests {
             };
             assert_eq!(expected2, state);
         }
+        #[test]
+        fn test_null_move() {
+            let mut b = Board::empty();
+            b.en_passant = Some(EnPassant::new_from_target_square((3, 5).into()));
+            b.null_move();
+            assert_eq!(PieceColor::Dark, b.to_move);
+            assert_eq!(None, b.en_passant);
+            b.null_move();
+            assert_eq!(PieceColor::Light, b.to_move);
+        }
+
         #[test]
         fn test_get_team_pieces() {
             let default_board = Board::default();
@@ -0,0 +1,520 @@
+//! Knowledge about endgames with little material. The general evaluation doesn't know how to win
+//! some of them and thinks that some draws are won, so these endgames are recognized by the
+//! material on the board and either get their own evaluation or scale the general one.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::eval::king_safety::find_king;
+use crate::eval::kpk::{self, KpkResult};
+use crate::eval::params::piece_index;
+use crate::eval::pawns::relative_rank;
+use crate::score::Score;
+
+/// The score of an endgame that is won, but where the mate is too far away to be found by the
+/// search. The recognizers add bonuses to it that guide the search towards the mate.
+pub const KNOWN_WIN: Score = Score::new(10_000);
+/// The scale factor that leaves the evaluation unchanged.
+pub const SCALE_NORMAL: i32 = 64;
+/// No endgame with more knights, bishops, rooks and queens than this is recognized, so most boards
+/// of a game are skipped right away.
+const MAX_PIECES: u8 = 4;
+
+/// The result of a recognized endgame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endgame {
+    /// Replaces the evaluation with this score from the view of light.
+    Evaluation(Score),
+    /// Multiplies the evaluation with the factor divided by [`SCALE_NORMAL`].
+    Scale(i32),
+}
+
+impl Endgame {
+    /// Applies the result to the general evaluation.
+    pub fn apply(self, score: Score) -> Score {
+        match self {
+            Endgame::Evaluation(score) => score,
+            Endgame::Scale(factor) => Score::new(score.get_value() * factor / SCALE_NORMAL),
+        }
+    }
+}
+
+/// The amount of pieces of every type of both teams, which identifies an endgame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MaterialSignature {
+    light: [u8; 6],
+    dark: [u8; 6],
+}
+
+impl MaterialSignature {
+    pub fn from_board(board: &Board) -> MaterialSignature {
+        let mut signature = MaterialSignature::default();
+        for inner in board.get_pieces() {
+            let piece = inner.deref().borrow();
+            let index = piece_index(piece.get_piece().get_type());
+            match piece.get_color() {
+                PieceColor::Light => signature.light[index] += 1,
+                PieceColor::Dark => signature.dark[index] += 1,
+            }
+        }
+        signature
+    }
+
+    /// Returns the amount of pieces of the type that the team has.
+    pub fn get(&self, team: PieceColor, piece_type: PieceType) -> u8 {
+        self.team(team)[piece_index(piece_type)]
+    }
+
+    /// Returns the signature with the teams swapped.
+    pub fn flipped(&self) -> MaterialSignature {
+        MaterialSignature {
+            light: self.dark,
+            dark: self.light,
+        }
+    }
+
+    /// Returns the signature with one more piece of the type for the team.
+    pub fn with_piece(&self, team: PieceColor, piece_type: PieceType) -> MaterialSignature {
+        let mut result = *self;
+        result.team_mut(team)[piece_index(piece_type)] += 1;
+        result
+    }
+
+    /// Returns the signature with one piece of the type less for the team.
+    pub fn without_piece(&self, team: PieceColor, piece_type: PieceType) -> MaterialSignature {
+        let mut result = *self;
+        let count = &mut result.team_mut(team)[piece_index(piece_type)];
+        *count = count.saturating_sub(1);
+        result
+    }
+
+    fn team_mut(&mut self, team: PieceColor) -> &mut [u8; 6] {
+        match team {
+            PieceColor::Light => &mut self.light,
+            PieceColor::Dark => &mut self.dark,
+        }
+    }
+
+    fn team(&self, team: PieceColor) -> &[u8; 6] {
+        match team {
+            PieceColor::Light => &self.light,
+            PieceColor::Dark => &self.dark,
+        }
+    }
+
+    /// Returns the amount of knights, bishops, rooks and queens of the team.
+    fn pieces(&self, team: PieceColor) -> u8 {
+        self.team(team)[1..5].iter().sum()
+    }
+}
+
+impl Display for MaterialSignature {
+    /// Writes the pieces of light and dark like `KBNvK`, the strongest piece first.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let team = |counts: &[u8; 6]| {
+            let mut result = String::new();
+            for (piece_type, letter) in [
+                (PieceType::King, 'K'),
+                (PieceType::Queen, 'Q'),
+                (PieceType::Rook, 'R'),
+                (PieceType::Bishop, 'B'),
+                (PieceType::Knight, 'N'),
+                (PieceType::Pawn, 'P'),
+            ]
+            .iter()
+            {
+                for _ in 0..counts[piece_index(*piece_type)] {
+                    result.push(*letter);
+                }
+            }
+            result
+        };
+        write!(f, "{}v{}", team(&self.light), team(&self.dark))
+    }
+}
+
+/// A material signature that couldn't be parsed.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("invalid material signature {0}, expected something like KRPvKR")]
+pub struct SignatureError(String);
+
+impl FromStr for MaterialSignature {
+    type Err = SignatureError;
+
+    /// Parses the format of [`Display`]. Both teams need exactly one king, the order of the other
+    /// pieces doesn't matter.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || SignatureError(s.to_string());
+        let team = |pieces: &str| {
+            let mut counts = [0; 6];
+            for letter in pieces.chars() {
+                let piece_type = match letter {
+                    'K' => PieceType::King,
+                    'Q' => PieceType::Queen,
+                    'R' => PieceType::Rook,
+                    'B' => PieceType::Bishop,
+                    'N' => PieceType::Knight,
+                    'P' => PieceType::Pawn,
+                    _ => return Err(error()),
+                };
+                counts[piece_index(piece_type)] += 1;
+            }
+            if counts[piece_index(PieceType::King)] != 1 {
+                return Err(error());
+            }
+            Ok(counts)
+        };
+        let (light, dark) = s.split_once('v').ok_or_else(error)?;
+        Ok(MaterialSignature {
+            light: team(light)?,
+            dark: team(dark)?,
+        })
+    }
+}
+
+/// Evaluates an endgame for the strong team given as the second argument and returns the result
+/// from its own view.
+type Recognizer = fn(&Board, PieceColor) -> Option<Endgame>;
+
+/// The endgames with their own evaluation, keyed by their signature with the strong team first.
+const RECOGNIZERS: [(&str, Recognizer); 4] = [
+    ("KQvK", drive_to_edge),
+    ("KRvK", drive_to_edge),
+    ("KBNvK", drive_to_bishop_corner),
+    ("KPvK", king_and_pawn),
+];
+
+static RECOGNIZER_TABLE: OnceLock<HashMap<MaterialSignature, Recognizer>> = OnceLock::new();
+
+/// Returns the [`RECOGNIZERS`] keyed by their parsed signature.
+fn recognizers() -> &'static HashMap<MaterialSignature, Recognizer> {
+    RECOGNIZER_TABLE.get_or_init(|| {
+        RECOGNIZERS
+            .iter()
+            .map(|(name, recognizer)| {
+                let signature = name.parse().expect("invalid recognizer signature");
+                (signature, *recognizer)
+            })
+            .collect()
+    })
+}
+
+/// Returns the result of the endgame on the board if it is a known one.
+pub fn probe(board: &Board) -> Option<Endgame> {
+    let signature = MaterialSignature::from_board(board);
+    if signature.pieces(PieceColor::Light) + signature.pieces(PieceColor::Dark) > MAX_PIECES {
+        return None;
+    }
+    for (strong, signature) in [
+        (PieceColor::Light, signature),
+        (PieceColor::Dark, signature.flipped()),
+    ]
+    .iter()
+    {
+        if let Some(recognizer) = recognizers().get(signature) {
+            return recognizer(board, *strong).map(|result| from_view(result, *strong));
+        }
+    }
+    for strong in [PieceColor::Light, PieceColor::Dark].iter() {
+        if let Some(result) = wrong_bishop(board, &signature, *strong) {
+            return Some(from_view(result, *strong));
+        }
+    }
+    opposite_bishops(board, &signature)
+}
+
+/// Turns a result from the view of the team into one from the view of light.
+fn from_view(result: Endgame, team: PieceColor) -> Endgame {
+    match (result, team) {
+        (Endgame::Evaluation(score), PieceColor::Dark) => Endgame::Evaluation(-score),
+        _ => result,
+    }
+}
+
+/// KQK and KRK: The king of the opponent has to be driven to the edge, where it can be mated.
+fn drive_to_edge(board: &Board, strong: PieceColor) -> Option<Endgame> {
+    let (strong_king, weak_king) = kings(board, strong)?;
+    let score = KNOWN_WIN.get_value()
+        + 20 * centre_distance(weak_king)
+        + 10 * (7 - distance(strong_king, weak_king));
+    Some(Endgame::Evaluation(Score::new(score)))
+}
+
+/// KBNK: The mate is only possible in the corners of the color of the bishop, so the king of the
+/// opponent gets driven there.
+fn drive_to_bishop_corner(board: &Board, strong: PieceColor) -> Option<Endgame> {
+    let (strong_king, weak_king) = kings(board, strong)?;
+    let bishop = find_piece(board, strong, PieceType::Bishop)?;
+    let corners: [Coordinate; 2] = if is_dark_square(bishop) {
+        [(0, 0).into(), (7, 7).into()]
+    } else {
+        [(0, 7).into(), (7, 0).into()]
+    };
+    let corner_distance = corners
+        .iter()
+        .map(|corner| manhattan_distance(*corner, weak_king))
+        .min()
+        .unwrap_or_default();
+    let score = KNOWN_WIN.get_value()
+        + 20 * (14 - corner_distance)
+        + 10 * (7 - distance(strong_king, weak_king));
+    Some(Endgame::Evaluation(Score::new(score)))
+}
+
+/// KPK: The result is known exactly from the [`kpk`] bitbase. Won positions get a bonus for
+/// advancing the pawn so that the search makes progress towards the promotion.
+fn king_and_pawn(board: &Board, strong: PieceColor) -> Option<Endgame> {
+    let pawn = find_piece(board, strong, PieceType::Pawn)?;
+    match kpk::probe(board)? {
+        KpkResult::Win => {
+            let rank = relative_rank(strong, pawn.get_y()) as i32;
+            Some(Endgame::Evaluation(Score::new(
+                KNOWN_WIN.get_value() + 20 * rank,
+            )))
+        }
+        KpkResult::Draw => Some(Endgame::Evaluation(Score::DRAW)),
+    }
+}
+
+/// A bishop and only pawns on one of the rook files can't win against a king in the promotion
+/// corner if the bishop doesn't control the promotion square.
+fn wrong_bishop(
+    board: &Board,
+    signature: &MaterialSignature,
+    strong: PieceColor,
+) -> Option<Endgame> {
+    let weak = strong.get_opponent();
+    let is_candidate = signature.pieces(strong) == 1
+        && signature.get(strong, PieceType::Bishop) == 1
+        && signature.get(strong, PieceType::Pawn) > 0
+        && signature.pieces(weak) == 0
+        && signature.get(weak, PieceType::Pawn) == 0;
+    if !is_candidate {
+        return None;
+    }
+    let pawns = find_pieces(board, strong, PieceType::Pawn);
+    let file = pawns[0].get_x();
+    if !is_rook_file(file) || pawns.iter().any(|pawn| pawn.get_x() != file) {
+        return None;
+    }
+    let promotion = Coordinate::new(file, relative_rank(strong, 7));
+    let bishop = find_piece(board, strong, PieceType::Bishop)?;
+    let (_, weak_king) = kings(board, strong)?;
+    if is_dark_square(bishop) != is_dark_square(promotion) && distance(weak_king, promotion) <= 1 {
+        return Some(Endgame::Evaluation(Score::DRAW));
+    }
+    None
+}
+
+/// Bishops of opposite colors make it hard to win, especially if there are no other pieces.
+fn opposite_bishops(board: &Board, signature: &MaterialSignature) -> Option<Endgame> {
+    if signature.get(PieceColor::Light, PieceType::Bishop) != 1
+        || signature.get(PieceColor::Dark, PieceType::Bishop) != 1
+    {
+        return None;
+    }
+    let light = find_piece(board, PieceColor::Light, PieceType::Bishop)?;
+    let dark = find_piece(board, PieceColor::Dark, PieceType::Bishop)?;
+    if is_dark_square(light) == is_dark_square(dark) {
+        return None;
+    }
+    let only_bishops =
+        signature.pieces(PieceColor::Light) == 1 && signature.pieces(PieceColor::Dark) == 1;
+    Some(Endgame::Scale(if only_bishops { 24 } else { 48 }))
+}
+
+fn kings(board: &Board, strong: PieceColor) -> Option<(Coordinate, Coordinate)> {
+    Some((
+        find_king(board, strong)?,
+        find_king(board, strong.get_opponent())?,
+    ))
+}
+
+fn find_piece(board: &Board, team: PieceColor, piece_type: PieceType) -> Option<Coordinate> {
+    find_pieces(board, team, piece_type).first().copied()
+}
+
+fn find_pieces(board: &Board, team: PieceColor, piece_type: PieceType) -> Vec<Coordinate> {
+    board
+        .get_pieces()
+        .iter()
+        .map(|inner| inner.deref().borrow())
+        .filter(|piece| piece.get_color() == team && piece.get_piece().get_type() == piece_type)
+        .map(|piece| piece.get_coordinate())
+        .collect()
+}
+
+/// Returns true if the square is a dark one like a1.
+fn is_dark_square(square: Coordinate) -> bool {
+    (square.get_x() + square.get_y()).is_multiple_of(2)
+}
+
+fn is_rook_file(x: u8) -> bool {
+    x == 0 || x == 7
+}
+
+/// Returns the amount of king moves between the squares.
+fn distance(a: Coordinate, b: Coordinate) -> i32 {
+    let dx = (a.get_x() as i32 - b.get_x() as i32).abs();
+    let dy = (a.get_y() as i32 - b.get_y() as i32).abs();
+    dx.max(dy)
+}
+
+fn manhattan_distance(a: Coordinate, b: Coordinate) -> i32 {
+    (a.get_x() as i32 - b.get_x() as i32).abs() + (a.get_y() as i32 - b.get_y() as i32).abs()
+}
+
+/// Returns how far the square is away from the four middle squares, which is `0` to `6`.
+fn centre_distance(square: Coordinate) -> i32 {
+    let from_centre = |value: u8| (3 - value as i32).max(value as i32 - 4);
+    from_centre(square.get_x()) + from_centre(square.get_y())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    fn probe_fen(fen: &str) -> Option<Endgame> {
+        probe(&board_from_fen(fen))
+    }
+
+    #[test]
+    fn test_signature() {
+        let board = board_from_fen("4k3/8/8/8/8/8/8/1N2KB2 w - - 0 1");
+        let signature = MaterialSignature::from_board(&board);
+        assert_eq!("KBNvK", signature.to_string());
+        assert_eq!("KvKBN", signature.flipped().to_string());
+        assert_eq!(1, signature.get(PieceColor::Light, PieceType::Knight));
+        assert_eq!(
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP",
+            MaterialSignature::from_board(&Board::default()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_signature() {
+        for signature in ["KvK", "KQvK", "KBNvK", "KRPvKR", "KPPvKNP"].iter() {
+            assert_eq!(
+                *signature,
+                MaterialSignature::from_str(signature).unwrap().to_string()
+            );
+        }
+        assert_eq!(
+            MaterialSignature::from_str("KBNvK"),
+            MaterialSignature::from_str("KNBvK")
+        );
+        for invalid in ["", "KQK", "QvK", "KKvK", "KXvK"].iter() {
+            assert!(MaterialSignature::from_str(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_drive_to_edge() {
+        let centre = probe_fen("8/8/8/3k4/8/8/8/R3K3 w - - 0 1");
+        let edge = probe_fen("3k4/8/8/8/8/8/8/R3K3 w - - 0 1");
+        match (centre, edge) {
+            (Some(Endgame::Evaluation(centre)), Some(Endgame::Evaluation(edge))) => {
+                assert!(centre > KNOWN_WIN && edge > centre);
+            }
+            _ => panic!("KRK is not recognized"),
+        }
+        // Dark has the queen.
+        match probe_fen("8/8/8/3K4/8/8/8/q3k3 w - - 0 1") {
+            Some(Endgame::Evaluation(score)) => assert!(score < -KNOWN_WIN),
+            _ => panic!("KQK is not recognized"),
+        }
+    }
+
+    #[test]
+    fn test_drive_to_bishop_corner() {
+        // The bishop on f1 controls the light squares, so the king belongs to a8 or h1.
+        let right = probe_fen("k7/8/8/8/8/8/8/1N2KB2 w - - 0 1").unwrap();
+        let wrong = probe_fen("7k/8/8/8/8/8/8/1N2KB2 w - - 0 1").unwrap();
+        match (right, wrong) {
+            (Endgame::Evaluation(right), Endgame::Evaluation(wrong)) => assert!(right > wrong),
+            _ => panic!("KBNK is not recognized"),
+        }
+    }
+
+    #[test]
+    fn test_king_and_pawn() {
+        // The king can't catch the pawn.
+        match probe_fen("7k/8/8/8/P7/8/8/4K3 w - - 0 1") {
+            Some(Endgame::Evaluation(score)) => assert!(score > KNOWN_WIN),
+            other => panic!("unexpected {:?}", other),
+        }
+        // The king is in the corner in front of the rook pawn.
+        assert_eq!(
+            Some(Endgame::Evaluation(Score::DRAW)),
+            probe_fen("k7/8/8/8/P7/8/8/4K3 w - - 0 1")
+        );
+        // The king catches the pawn.
+        assert_eq!(
+            Some(Endgame::Evaluation(Score::DRAW)),
+            probe_fen("4k3/8/8/8/3P4/8/8/4K3 w - - 0 1")
+        );
+        // Dark wins with its king in front of the pawn.
+        match probe_fen("8/8/8/3p4/8/3k4/8/3K4 b - - 0 1") {
+            Some(Endgame::Evaluation(score)) => assert!(score < -KNOWN_WIN),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_wrong_bishop() {
+        // The light-squared bishop can't control h8.
+        assert_eq!(
+            Some(Endgame::Evaluation(Score::DRAW)),
+            probe_fen("7k/8/8/7P/7P/8/8/4KB2 w - - 0 1")
+        );
+        // With the dark-squared bishop it is won.
+        assert_eq!(None, probe_fen("7k/8/8/7P/7P/8/8/2B1K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_opposite_bishops() {
+        assert_eq!(
+            Some(Endgame::Scale(24)),
+            probe_fen("2b1k3/8/8/8/8/8/PP6/2B1K3 w - - 0 1")
+        );
+        assert_eq!(
+            Some(Endgame::Scale(48)),
+            probe_fen("r1b1k3/8/8/8/8/8/PP6/R1B1K3 w - - 0 1")
+        );
+        // Both bishops are on light squares.
+        assert_eq!(None, probe_fen("4k1b1/8/8/8/8/8/PP6/4KB2 w - - 0 1"));
+        assert_eq!(None, probe(&Board::default()));
+    }
+
+    #[test]
+    fn test_recognizers() {
+        assert_eq!(RECOGNIZERS.len(), recognizers().len());
+        let krk: MaterialSignature = "KRvK".parse().unwrap();
+        assert!(recognizers().contains_key(&krk));
+        assert!(!recognizers().contains_key(&krk.flipped()));
+        // Boards with many pieces are skipped.
+        assert_eq!(None, probe(&Board::default()));
+        assert_eq!(None, probe_fen("rnb1k3/8/8/8/8/8/PP6/RNB1K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(Score::new(50), Endgame::Scale(32).apply(Score::new(100)));
+        assert_eq!(
+            Score::DRAW,
+            Endgame::Evaluation(Score::DRAW).apply(Score::new(100))
+        );
+    }
+}
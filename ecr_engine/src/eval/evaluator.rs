@@ -0,0 +1,147 @@
+//! The [`Evaluator`] trait, which lets the search use any evaluation, and the evaluations that are
+//! built into the engine.
+
+use std::fmt::Debug;
+
+use ecr_shared::pieces::{PieceColor, PieceType};
+
+use crate::board::Board;
+use crate::eval::params::EvalParams;
+use crate::eval::pawns::PawnHashTable;
+use crate::eval::{team_material, TaperedScore};
+use crate::score::Score;
+
+/// Evaluates the boards of a search. The search tells the evaluator about every board it enters
+/// and leaves, so an evaluator can update its state incrementally instead of looking at every
+/// board from scratch.
+pub trait Evaluator: Debug + Send {
+    /// Returns the score of the board from the view of light. The board is the one of the last
+    /// [`Evaluator::make`] or [`Evaluator::reset`] that wasn't taken back yet.
+    fn evaluate(&mut self, board: &Board) -> Score;
+
+    /// Gets called with the board the search starts from.
+    fn reset(&mut self, _board: &Board) {}
+
+    /// Gets called when the search enters the board after a move.
+    fn make(&mut self, _board: &Board) {}
+
+    /// Gets called when the search leaves the board of the last [`Evaluator::make`].
+    fn unmake(&mut self) {}
+
+    /// Returns the value of a piece of the given type. The search tries to capture the valuable
+    /// pieces first.
+    fn piece_value(&self, piece_type: PieceType) -> TaperedScore {
+        EvalParams::DEFAULT.material(piece_type)
+    }
+}
+
+/// Lets the search use an evaluator that is only chosen at runtime, e.g. a `Box<dyn Evaluator>`
+/// picked by the configuration of a front end.
+impl<E: Evaluator + ?Sized> Evaluator for Box<E> {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        (**self).evaluate(board)
+    }
+
+    fn reset(&mut self, board: &Board) {
+        (**self).reset(board)
+    }
+
+    fn make(&mut self, board: &Board) {
+        (**self).make(board)
+    }
+
+    fn unmake(&mut self) {
+        (**self).unmake()
+    }
+
+    fn piece_value(&self, piece_type: PieceType) -> TaperedScore {
+        (**self).piece_value(piece_type)
+    }
+}
+
+/// The hand-crafted evaluation of [`Board::eval_with_params`] with the pawn structure cached in a
+/// [`PawnHashTable`].
+#[derive(Debug, Clone, Default)]
+pub struct ClassicEvaluator {
+    params: EvalParams,
+    pawn_table: PawnHashTable,
+}
+
+impl ClassicEvaluator {
+    pub fn new() -> ClassicEvaluator {
+        ClassicEvaluator::default()
+    }
+
+    /// Returns an evaluator with the given weights.
+    pub fn with_params(params: EvalParams) -> ClassicEvaluator {
+        ClassicEvaluator {
+            params,
+            pawn_table: PawnHashTable::default(),
+        }
+    }
+
+    /// Returns the weights of the evaluation.
+    pub fn get_params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Sets the weights of the evaluation, which lets the search play with a different style.
+    pub fn set_params(&mut self, params: EvalParams) {
+        self.params = params;
+        self.pawn_table.clear();
+    }
+}
+
+impl Evaluator for ClassicEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        board.eval_with_pawn_table(&mut self.pawn_table, &self.params)
+    }
+
+    fn piece_value(&self, piece_type: PieceType) -> TaperedScore {
+        self.params.material(piece_type)
+    }
+}
+
+/// Only counts the material with the values of [`EvalParams::DEFAULT`], interpolated by the game
+/// phase.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&mut self, board: &Board) -> Score {
+        let params = &EvalParams::DEFAULT;
+        let material = team_material(board, PieceColor::Light, params)
+            - team_material(board, PieceColor::Dark, params);
+        material.taper(board.get_game_phase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::TaperedScore;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_classic_evaluator() {
+        let board =
+            board_from_fen("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4");
+        let mut evaluator = ClassicEvaluator::new();
+        assert_eq!(board.eval(), evaluator.evaluate(&board));
+        let mut params = EvalParams::DEFAULT;
+        params.centre_threat = TaperedScore::new(100, 100);
+        evaluator.set_params(params.clone());
+        assert_eq!(&params, evaluator.get_params());
+        assert_eq!(board.eval_with_params(&params), evaluator.evaluate(&board));
+    }
+
+    #[test]
+    fn test_material_evaluator() {
+        assert_eq!(Score::DRAW, MaterialEvaluator.evaluate(&Board::default()));
+        // Light has an extra knight, which is worth its endgame value without other pieces.
+        let board = board_from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        let knight = EvalParams::DEFAULT.material[1];
+        assert_eq!(knight.taper(1), MaterialEvaluator.evaluate(&board));
+    }
+}
@@ -0,0 +1,205 @@
+//! Evaluation of the safety of the kings. A king is safe if pawns of its team shelter it, the pawns
+//! of the opponent are far away, the files around it are closed and few pieces of the opponent
+//! attack the squares around it.
+
+use std::ops::Deref;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+
+use crate::board::Board;
+use crate::eval::params::{piece_index, EvalParams};
+use crate::eval::pawns::{ahead, file_mask, square_bit, Pawns};
+use crate::eval::TaperedScore;
+
+/// Returns the king safety of light minus the one of dark.
+pub fn evaluate_king_safety(board: &Board, params: &EvalParams) -> TaperedScore {
+    let pawns = Pawns::from_board(board);
+    team_safety(board, &pawns, PieceColor::Light, params)
+        - team_safety(board, &pawns, PieceColor::Dark, params)
+}
+
+/// Returns the safety of the king of the team. The pawn shelter only matters in the midgame, the
+/// attack on the king matters less in the endgame.
+pub(crate) fn team_safety(
+    board: &Board,
+    pawns: &Pawns,
+    team: PieceColor,
+    params: &EvalParams,
+) -> TaperedScore {
+    let king = match find_king(board, team) {
+        Some(king) => king,
+        None => return TaperedScore::default(),
+    };
+    let shelter = pawn_shelter(pawns, team, king, params);
+    let attack = king_attack(board, team, king, params);
+    TaperedScore::new(shelter - attack, -attack / 4)
+}
+
+/// Scores the pawns and files on the file of the king and the files next to it.
+fn pawn_shelter(pawns: &Pawns, team: PieceColor, king: Coordinate, params: &EvalParams) -> i32 {
+    let own = pawns.get(team);
+    let opponent = pawns.get(team.get_opponent());
+    let mut score = 0;
+    for x in king.get_x().saturating_sub(1)..=(king.get_x() + 1).min(7) {
+        let nearest = |bits: u64| {
+            ahead(team, king.get_y())
+                .position(|y| bits & square_bit(Coordinate::new(x, y)) != 0)
+                .map_or(0, |index| (index + 1).min(params.shelter.len() - 1))
+        };
+        score += params.shelter[nearest(own)] + params.storm[nearest(opponent)];
+        if own & file_mask(x) == 0 {
+            score += if opponent & file_mask(x) == 0 {
+                params.king_open_file
+            } else {
+                params.king_semi_open_file
+            };
+        }
+    }
+    score
+}
+
+/// Returns the penalty for the attack of the opponent on the king zone. Every attacked square of
+/// the zone adds attack units depending on the attacking piece. A single piece can rarely mate on
+/// its own, so there is no penalty if less than two pieces attack the zone.
+fn king_attack(board: &Board, team: PieceColor, king: Coordinate, params: &EvalParams) -> i32 {
+    let zone = king_zone(team, king);
+    let opponent = team.get_opponent();
+    let mut attackers = 0;
+    let mut units = 0;
+    for inner in board.get_pieces() {
+        let piece = inner.deref().borrow();
+        if piece.get_color() != opponent {
+            continue;
+        }
+        let weight = params.attack_units[piece_index(piece.get_piece().get_type())];
+        if weight == 0 {
+            continue;
+        }
+        let hits = piece
+            .get_piece()
+            .get_pseudo_legal_moves(
+                board,
+                &piece.get_coordinate(),
+                opponent,
+                piece.get_has_moved(),
+            )
+            .iter()
+            .filter(|m| zone & square_bit(m.get_target_square()) != 0)
+            .count() as i32;
+        if hits > 0 {
+            attackers += 1;
+            units += hits * weight;
+        }
+    }
+    if attackers < 2 {
+        return 0;
+    }
+    let mut bits = zone;
+    while bits != 0 {
+        let index = bits.trailing_zeros() as u8;
+        bits &= bits - 1;
+        let state = board.get_threatened_state(Coordinate::new(index % 8, index / 8));
+        if state.get_by_team(opponent) > state.get_by_team(team) {
+            units += params.controlled_square;
+        }
+    }
+    (units * units / 2).min(params.max_attack_penalty)
+}
+
+/// Returns the squares around the king and the three squares two ranks in front of it as a
+/// bitboard.
+fn king_zone(team: PieceColor, king: Coordinate) -> u64 {
+    let mut zone = 0;
+    let in_front = ahead(team, king.get_y()).nth(1);
+    for x in king.get_x().saturating_sub(1)..=(king.get_x() + 1).min(7) {
+        for y in king.get_y().saturating_sub(1)..=(king.get_y() + 1).min(7) {
+            zone |= square_bit(Coordinate::new(x, y));
+        }
+        if let Some(y) = in_front {
+            zone |= square_bit(Coordinate::new(x, y));
+        }
+    }
+    zone
+}
+
+/// Returns the square of the king of the team.
+pub(crate) fn find_king(board: &Board, team: PieceColor) -> Option<Coordinate> {
+    board.get_pieces().iter().find_map(|inner| {
+        let piece = inner.deref().borrow();
+        if piece.get_color() == team && piece.get_piece().get_type() == PieceType::King {
+            Some(piece.get_coordinate())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    const PARAMS: EvalParams = EvalParams::DEFAULT;
+
+    fn light_shelter(fen: &str) -> i32 {
+        let board = board_from_fen(fen);
+        let king = find_king(&board, PieceColor::Light).unwrap();
+        pawn_shelter(&Pawns::from_board(&board), PieceColor::Light, king, &PARAMS)
+    }
+
+    #[test]
+    fn test_symmetric() {
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_king_safety(&Board::default(), &PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_pawn_shelter() {
+        let castled = light_shelter("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1");
+        assert_eq!(3 * PARAMS.shelter[1], castled);
+        let pushed = light_shelter("6k1/5ppp/8/8/8/5PPP/8/6K1 w - - 0 1");
+        assert_eq!(3 * PARAMS.shelter[2], pushed);
+        // The g-file has no light pawn and the h-file no pawn at all.
+        let open = light_shelter("6k1/6p1/8/8/8/8/5P2/6K1 w - - 0 1");
+        assert_eq!(
+            PARAMS.shelter[1]
+                + 2 * PARAMS.shelter[0]
+                + PARAMS.king_semi_open_file
+                + PARAMS.king_open_file,
+            open
+        );
+        // A pawn of the opponent is about to hit the shelter.
+        let storm = light_shelter("6k1/5pp1/8/8/8/7p/5PPP/6K1 w - - 0 1");
+        assert_eq!(castled + PARAMS.storm[2], storm);
+    }
+
+    #[test]
+    fn test_king_zone() {
+        // g1 has five neighbours and f3, g3 and h3 are two ranks in front of it.
+        assert_eq!(9, king_zone(PieceColor::Light, (6, 0).into()).count_ones());
+        assert_eq!(12, king_zone(PieceColor::Dark, (4, 4).into()).count_ones());
+    }
+
+    #[test]
+    fn test_king_attack() {
+        // The knight and the rook attack the king zone.
+        let board = board_from_fen("6k1/8/8/8/8/5n2/r4PPP/6K1 w - - 0 1");
+        assert!(king_attack(&board, PieceColor::Light, (6, 0).into(), &PARAMS) > 0);
+        // The rook alone is not enough.
+        let board = board_from_fen("6k1/8/8/8/8/8/r4PPP/6K1 w - - 0 1");
+        assert_eq!(
+            0,
+            king_attack(&board, PieceColor::Light, (6, 0).into(), &PARAMS)
+        );
+        let safe = board_from_fen("n5k1/8/8/8/8/8/r4PPP/6K1 w - - 0 1");
+        let attacked = board_from_fen("6k1/8/8/8/8/5n2/r4PPP/6K1 w - - 0 1");
+        assert!(
+            evaluate_king_safety(&attacked, &PARAMS).midgame
+                < evaluate_king_safety(&safe, &PARAMS).midgame
+        );
+    }
+}
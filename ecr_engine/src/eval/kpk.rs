@@ -0,0 +1,452 @@
+//! An exact bitbase for king and pawn against king. It gets computed by retrograde analysis the
+//! first time it is needed and stores for every position whether the team with the pawn wins.
+//!
+//! The positions are stored from the view of the team with the pawn, which is called strong here,
+//! and with the pawn on one of the files a to d. All other positions are mirrored into these.
+//!
+//! The bitbase is exact for the rules of this engine, which always promotes to a queen because a
+//! move of the board has no way to underpromote. A position where only promoting to a rook or a
+//! knight wins, e.g. because the queen would stalemate, is stored as a draw.
+
+use std::sync::OnceLock;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+
+use crate::board::Board;
+use crate::eval::endgame::MaterialSignature;
+use crate::eval::king_safety::find_king;
+use crate::eval::pawns::relative_rank;
+
+/// The pawn can stand on 4 files and the 6 ranks between the first and the last one.
+const PAWN_SQUARES: usize = 24;
+/// The amount of positions in the bitbase.
+const POSITIONS: usize = 2 * 64 * 64 * PAWN_SQUARES;
+
+static BITBASE: OnceLock<Vec<u64>> = OnceLock::new();
+
+/// The result of a KPK position from the view of the team with the pawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KpkResult {
+    Win,
+    Draw,
+}
+
+/// Returns the result of the board if it only has two kings and one pawn.
+pub fn probe(board: &Board) -> Option<KpkResult> {
+    // Most boards of a search have more pieces, so this is checked first.
+    if board.get_pieces().len() != 3 {
+        return None;
+    }
+    let signature = MaterialSignature::from_board(board);
+    let strong = [PieceColor::Light, PieceColor::Dark]
+        .iter()
+        .copied()
+        .find(|team| is_kpk(&signature, *team))?;
+    let weak = strong.get_opponent();
+    let strong_king = find_king(board, strong)?;
+    let weak_king = find_king(board, weak)?;
+    let pawn = board
+        .get_pieces()
+        .iter()
+        .map(|inner| inner.borrow())
+        .find(|piece| piece.get_piece().get_type() == PieceType::Pawn)?
+        .get_coordinate();
+    probe_squares(
+        strong,
+        strong_king,
+        pawn,
+        weak_king,
+        board.get_light_to_move() == (strong == PieceColor::Light),
+    )
+}
+
+/// Returns the result of the position with the given squares from the view of the strong team,
+/// which is the one with the pawn. Returns `None` if the pawn is on the first or the last rank,
+/// since the bitbase has no such positions.
+pub fn probe_squares(
+    strong: PieceColor,
+    strong_king: Coordinate,
+    pawn: Coordinate,
+    weak_king: Coordinate,
+    strong_to_move: bool,
+) -> Option<KpkResult> {
+    let mirror = pawn.get_x() > 3;
+    let square = |coordinate: Coordinate| {
+        let x = if mirror {
+            7 - coordinate.get_x()
+        } else {
+            coordinate.get_x()
+        };
+        (relative_rank(strong, coordinate.get_y()) * 8 + x) as usize
+    };
+    let pawn = square(pawn);
+    if !(8..56).contains(&pawn) {
+        return None;
+    }
+    let index = index(strong_to_move, square(strong_king), square(weak_king), pawn);
+    let bitbase = BITBASE.get_or_init(generate);
+    if bitbase[index / 64] & (1 << (index % 64)) != 0 {
+        Some(KpkResult::Win)
+    } else {
+        Some(KpkResult::Draw)
+    }
+}
+
+/// Computes the bitbase now instead of when it is probed the first time, which would slow down
+/// the search that does it. Meant to be called once by the front end when it starts.
+pub fn init() {
+    BITBASE.get_or_init(generate);
+}
+
+fn is_kpk(signature: &MaterialSignature, strong: PieceColor) -> bool {
+    let weak = strong.get_opponent();
+    [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+    ]
+    .iter()
+    .all(|piece_type| {
+        let expected = (*piece_type == PieceType::Pawn) as u8;
+        signature.get(strong, *piece_type) == expected && signature.get(weak, *piece_type) == 0
+    })
+}
+
+/// The squares are numbered `y * 8 + x` from the view of the strong team and the pawn has to be
+/// on the files a to d.
+fn index(strong_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> usize {
+    let pawn = (pawn / 8 - 1) * 4 + pawn % 8;
+    strong_to_move as usize + 2 * (weak_king + 64 * (strong_king + 64 * pawn))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Unknown,
+    Invalid,
+    Win,
+    Draw,
+}
+
+/// A position of the bitbase with the squares numbered like in [`index`].
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    strong_to_move: bool,
+    strong_king: usize,
+    weak_king: usize,
+    pawn: usize,
+}
+
+impl Position {
+    fn from_index(index: usize) -> Position {
+        let pawn = index / (2 * 64 * 64);
+        Position {
+            strong_to_move: index % 2 == 1,
+            weak_king: (index / 2) % 64,
+            strong_king: (index / (2 * 64)) % 64,
+            pawn: (pawn / 4 + 1) * 8 + pawn % 4,
+        }
+    }
+
+    fn index(&self) -> usize {
+        index(
+            self.strong_to_move,
+            self.strong_king,
+            self.weak_king,
+            self.pawn,
+        )
+    }
+
+    /// Returns the state of the position that doesn't depend on the positions after it.
+    fn initial_state(&self) -> State {
+        if self.strong_king == self.weak_king
+            || self.strong_king == self.pawn
+            || self.weak_king == self.pawn
+            || distance(self.strong_king, self.weak_king) <= 1
+        {
+            return State::Invalid;
+        }
+        if self.strong_to_move {
+            // The weak king can't be in check when the strong team has to move.
+            if pawn_attacks(self.pawn, self.weak_king) {
+                return State::Invalid;
+            }
+            // The search always promotes to a queen, so a promotion that stalemates isn't a win
+            // even if promoting to a rook would be.
+            let promotion = self.pawn + 8;
+            if self.pawn / 8 == 6
+                && promotion != self.strong_king
+                && promotion != self.weak_king
+                && (distance(self.weak_king, promotion) > 1
+                    || distance(self.strong_king, promotion) == 1)
+                && !self.is_stalemate_after_promotion(promotion)
+            {
+                return State::Win;
+            }
+        } else {
+            if self.weak_moves().next().is_none() {
+                return State::Draw;
+            }
+            if distance(self.weak_king, self.pawn) == 1 && distance(self.strong_king, self.pawn) > 1
+            {
+                return State::Draw;
+            }
+        }
+        State::Unknown
+    }
+
+    /// Returns true if the weak king isn't in check after the pawn promoted to a queen on the
+    /// square but can't move either.
+    fn is_stalemate_after_promotion(&self, queen: usize) -> bool {
+        let attacked = |square: usize| {
+            distance(square, self.strong_king) <= 1
+                || queen_attacks(queen, square, self.strong_king)
+        };
+        !attacked(self.weak_king)
+            && king_moves(self.weak_king).all(|square| square != queen && attacked(square))
+    }
+
+    /// Returns the squares the weak king can move to without being in check.
+    fn weak_moves(&self) -> impl Iterator<Item = usize> {
+        let position = *self;
+        king_moves(self.weak_king).filter(move |square| {
+            distance(*square, position.strong_king) > 1 && !pawn_attacks(position.pawn, *square)
+        })
+    }
+
+    /// Returns the positions after every move of the team to move except promotions, which are
+    /// covered by [`Position::initial_state`].
+    fn successors(&self) -> Vec<Position> {
+        let mut result = Vec::new();
+        if self.strong_to_move {
+            for square in king_moves(self.strong_king) {
+                if square != self.pawn && distance(square, self.weak_king) > 1 {
+                    result.push(Position {
+                        strong_to_move: false,
+                        strong_king: square,
+                        ..*self
+                    });
+                }
+            }
+            let single = self.pawn + 8;
+            let is_free = |square: usize| square != self.strong_king && square != self.weak_king;
+            if self.pawn / 8 < 6 && is_free(single) {
+                result.push(Position {
+                    strong_to_move: false,
+                    pawn: single,
+                    ..*self
+                });
+                if self.pawn / 8 == 1 && is_free(single + 8) {
+                    result.push(Position {
+                        strong_to_move: false,
+                        pawn: single + 8,
+                        ..*self
+                    });
+                }
+            }
+        } else {
+            for square in self.weak_moves() {
+                if square != self.pawn {
+                    result.push(Position {
+                        strong_to_move: true,
+                        weak_king: square,
+                        ..*self
+                    });
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Classifies every position by going backwards from the ones with a known result until nothing
+/// changes anymore. The positions that are still unknown then are draws.
+fn generate() -> Vec<u64> {
+    let positions: Vec<Position> = (0..POSITIONS).map(Position::from_index).collect();
+    let mut states: Vec<State> = positions
+        .iter()
+        .map(|position| position.initial_state())
+        .collect();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, position) in positions.iter().enumerate() {
+            if states[index] != State::Unknown {
+                continue;
+            }
+            let state = classify(position, &states);
+            if state != State::Unknown {
+                states[index] = state;
+                changed = true;
+            }
+        }
+    }
+    let mut bitbase = vec![0; POSITIONS / 64];
+    for (index, state) in states.iter().enumerate() {
+        if *state == State::Win {
+            bitbase[index / 64] |= 1 << (index % 64);
+        }
+    }
+    bitbase
+}
+
+/// The strong team wins if one of its moves wins, the weak team draws if one of its moves draws.
+fn classify(position: &Position, states: &[State]) -> State {
+    let (good, bad) = if position.strong_to_move {
+        (State::Win, State::Draw)
+    } else {
+        (State::Draw, State::Win)
+    };
+    let mut all_bad = true;
+    for successor in position.successors() {
+        match states[successor.index()] {
+            state if state == good => return good,
+            State::Unknown => all_bad = false,
+            _ => {}
+        }
+    }
+    if all_bad {
+        bad
+    } else {
+        State::Unknown
+    }
+}
+
+fn king_moves(square: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = ((square % 8) as i32, (square / 8) as i32);
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter(move |(new_x, new_y)| {
+            (*new_x, *new_y) != (x, y) && (0..8).contains(new_x) && (0..8).contains(new_y)
+        })
+        .map(|(x, y)| (y * 8 + x) as usize)
+}
+
+fn pawn_attacks(pawn: usize, square: usize) -> bool {
+    square / 8 == pawn / 8 + 1 && ((square % 8) as i32 - (pawn % 8) as i32).abs() == 1
+}
+
+/// Returns true if a queen on the first square attacks the second one. Only the blocker can be in
+/// the way.
+fn queen_attacks(queen: usize, square: usize, blocker: usize) -> bool {
+    let (dx, dy) = (
+        (square % 8) as i32 - (queen % 8) as i32,
+        (square / 8) as i32 - (queen / 8) as i32,
+    );
+    if (dx, dy) == (0, 0) || (dx != 0 && dy != 0 && dx.abs() != dy.abs()) {
+        return false;
+    }
+    let step = (dy.signum() * 8 + dx.signum()) as isize;
+    let mut current = queen as isize + step;
+    while current != square as isize {
+        if current == blocker as isize {
+            return false;
+        }
+        current += step;
+    }
+    true
+}
+
+fn distance(a: usize, b: usize) -> i32 {
+    let dx = ((a % 8) as i32 - (b % 8) as i32).abs();
+    let dy = ((a / 8) as i32 - (b / 8) as i32).abs();
+    dx.max(dy)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    fn probe_fen(fen: &str) -> Option<KpkResult> {
+        probe(&board_from_fen(fen))
+    }
+
+    #[test]
+    fn test_index() {
+        for index in [0, 1, 4097, POSITIONS - 1].iter() {
+            assert_eq!(*index, Position::from_index(*index).index());
+        }
+    }
+
+    #[test]
+    fn test_probe() {
+        // The king in front of the pawn on the sixth rank wins no matter who moves.
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1")
+        );
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1")
+        );
+        // The pawn on the sixth rank in front of its king only draws if it has to move.
+        assert_eq!(
+            Some(KpkResult::Draw),
+            probe_fen("4k3/8/4P3/4K3/8/8/8/8 w - - 0 1")
+        );
+        // The king can't catch the pawn.
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("7k/8/8/8/P7/8/8/4K3 w - - 0 1")
+        );
+        // The king is in the corner in front of the rook pawn.
+        assert_eq!(
+            Some(KpkResult::Draw),
+            probe_fen("k7/8/1K6/P7/8/8/8/8 w - - 0 1")
+        );
+        assert_eq!(None, probe_fen("4k3/8/8/8/3P4/8/8/3QK3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_probe_squares_outside_bitbase() {
+        let probe_pawn = |pawn: Coordinate| {
+            probe_squares(
+                PieceColor::Light,
+                Coordinate::new(4, 0),
+                pawn,
+                Coordinate::new(4, 7),
+                true,
+            )
+        };
+        assert_eq!(None, probe_pawn(Coordinate::new(3, 0)));
+        assert_eq!(None, probe_pawn(Coordinate::new(3, 7)));
+        assert_eq!(Some(KpkResult::Win), probe_pawn(Coordinate::new(0, 6)));
+    }
+
+    #[test]
+    fn test_stalemate_promotion() {
+        // Promoting to a queen stalemates and every other move lets the king block the pawn.
+        assert_eq!(
+            Some(KpkResult::Draw),
+            probe_fen("8/k1P5/8/K7/8/8/8/8 w - - 0 1")
+        );
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("8/k1P5/8/8/8/8/8/K7 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn test_mirrored() {
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("8/8/8/3p4/8/3k4/8/3K4 b - - 0 1")
+        );
+        assert_eq!(
+            Some(KpkResult::Win),
+            probe_fen("4k3/8/8/8/8/8/7p/K7 b - - 0 1")
+        );
+        assert_eq!(
+            Some(KpkResult::Draw),
+            probe_fen("8/8/8/8/4k3/4p3/8/4K3 b - - 0 1")
+        );
+        assert_eq!(
+            Some(KpkResult::Draw),
+            probe_fen("8/8/8/8/8/6k1/7p/7K b - - 0 1")
+        );
+    }
+}
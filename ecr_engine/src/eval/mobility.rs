@@ -0,0 +1,219 @@
+//! Evaluation of the activity of the pieces. Every knight, bishop, rook and queen is scored by the
+//! amount of safe squares it can move to, and some placements get an extra bonus or penalty.
+
+use std::ops::Deref;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+
+use crate::board::Board;
+use crate::eval::params::{piece_index, EvalParams};
+use crate::eval::pawns::{
+    adjacent_files, ahead, file_mask, rank_mask, relative_rank, square_bit, Pawns,
+};
+use crate::eval::TaperedScore;
+
+/// Returns the mobility and activity of the pieces of light minus the one of dark.
+pub fn evaluate_mobility(board: &Board, params: &EvalParams) -> TaperedScore {
+    let pawns = Pawns::from_board(board);
+    team_mobility(board, &pawns, PieceColor::Light, params)
+        - team_mobility(board, &pawns, PieceColor::Dark, params)
+}
+
+/// Returns the mobility and activity of the pieces of the team.
+pub(crate) fn team_mobility(
+    board: &Board,
+    pawns: &Pawns,
+    team: PieceColor,
+    params: &EvalParams,
+) -> TaperedScore {
+    let unsafe_squares = pawn_attacks(pawns, team.get_opponent());
+    let mut score = TaperedScore::default();
+    let mut bishops = 0;
+    for inner in board.get_pieces() {
+        let piece = inner.deref().borrow();
+        if piece.get_color() != team {
+            continue;
+        }
+        let piece_type = piece.get_piece().get_type();
+        let weight = params.mobility[piece_index(piece_type)];
+        let average = params.mobility_average[piece_index(piece_type)];
+        if average == 0 {
+            continue;
+        }
+        let square = piece.get_coordinate();
+        let mobility = piece
+            .get_piece()
+            .get_pseudo_legal_moves(board, &square, team, piece.get_has_moved())
+            .iter()
+            .filter(|m| unsafe_squares & square_bit(m.get_target_square()) == 0)
+            .count() as i32;
+        score += weight * (mobility - average);
+        if mobility <= 1 && piece_type != PieceType::Queen {
+            score += params.trapped;
+        }
+        match piece_type {
+            PieceType::Bishop => bishops += 1,
+            PieceType::Rook => score += rook_activity(board, pawns, team, square, params),
+            PieceType::Knight if is_outpost(pawns, team, square) => score += params.knight_outpost,
+            _ => {}
+        }
+    }
+    if bishops >= 2 {
+        score += params.bishop_pair;
+    }
+    score
+}
+
+/// Scores a rook on an open or semi-open file and on the seventh rank.
+fn rook_activity(
+    board: &Board,
+    pawns: &Pawns,
+    team: PieceColor,
+    square: Coordinate,
+    params: &EvalParams,
+) -> TaperedScore {
+    let own = pawns.get(team);
+    let opponent = pawns.get(team.get_opponent());
+    let file = file_mask(square.get_x());
+    let mut score = TaperedScore::default();
+    if (own | opponent) & file == 0 {
+        score += params.rook_open_file;
+    } else if own & file == 0 {
+        score += params.rook_semi_open_file;
+    }
+    if relative_rank(team, square.get_y()) == 6 {
+        let seventh = square.get_y();
+        let last = ahead(team, seventh).next().unwrap_or(seventh);
+        let king_on_last_rank = board.get_pieces().iter().any(|inner| {
+            let piece = inner.deref().borrow();
+            piece.get_color() != team
+                && piece.get_piece().get_type() == PieceType::King
+                && piece.get_coordinate().get_y() == last
+        });
+        let pawns_on_seventh = opponent & rank_mask(seventh) != 0;
+        if king_on_last_rank || pawns_on_seventh {
+            score += params.rook_on_seventh;
+        }
+    }
+    score
+}
+
+/// Returns true if the knight stands on the fourth to sixth rank, is protected by a pawn and no
+/// pawn of the opponent can attack its square anymore.
+fn is_outpost(pawns: &Pawns, team: PieceColor, square: Coordinate) -> bool {
+    let rank = relative_rank(team, square.get_y());
+    if !(3..=5).contains(&rank) {
+        return false;
+    }
+    let protected = pawn_attacks(pawns, team) & square_bit(square) != 0;
+    let front = ahead(team, square.get_y()).fold(0, |bits, y| bits | rank_mask(y));
+    let attackable = pawns.get(team.get_opponent()) & adjacent_files(square.get_x()) & front != 0;
+    protected && !attackable
+}
+
+/// Returns every square that is attacked by a pawn of the team as a bitboard.
+fn pawn_attacks(pawns: &Pawns, team: PieceColor) -> u64 {
+    const NOT_A_FILE: u64 = !0x0101_0101_0101_0101;
+    const NOT_H_FILE: u64 = !0x8080_8080_8080_8080;
+    let bits = pawns.get(team);
+    match team {
+        PieceColor::Light => ((bits & NOT_A_FILE) << 7) | ((bits & NOT_H_FILE) << 9),
+        PieceColor::Dark => ((bits & NOT_A_FILE) >> 9) | ((bits & NOT_H_FILE) >> 7),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    const PARAMS: EvalParams = EvalParams::DEFAULT;
+
+    #[test]
+    fn test_symmetric() {
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_mobility(&Board::default(), &PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_pawn_attacks() {
+        let pawns = Pawns::from_board(&board_from_fen("4k3/7p/8/8/8/8/P7/4K3 w - - 0 1"));
+        // a2 attacks b3, h7 attacks g6.
+        assert_eq!(
+            square_bit((1, 2).into()),
+            pawn_attacks(&pawns, PieceColor::Light)
+        );
+        assert_eq!(
+            square_bit((6, 5).into()),
+            pawn_attacks(&pawns, PieceColor::Dark)
+        );
+    }
+
+    #[test]
+    fn test_mobility() {
+        // A knight in the centre can move to more squares than one in the corner.
+        let centre = evaluate_mobility(&board_from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1"), &PARAMS);
+        let corner = evaluate_mobility(&board_from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1"), &PARAMS);
+        assert_eq!(TaperedScore::new(16, 16), centre);
+        assert!(corner.midgame < centre.midgame);
+        // Squares that are attacked by pawns don't count.
+        let guarded = evaluate_mobility(
+            &board_from_fen("4k3/8/2p1p3/8/3N4/8/8/4K3 w - - 0 1"),
+            &PARAMS,
+        );
+        assert_eq!(TaperedScore::new(8, 8), guarded);
+    }
+
+    #[test]
+    fn test_bishop_pair() {
+        let pair = evaluate_mobility(&board_from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1"), &PARAMS);
+        let single = evaluate_mobility(&board_from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"), &PARAMS);
+        let bishop = PARAMS.mobility[piece_index(PieceType::Bishop)];
+        assert_eq!(pair - single, PARAMS.bishop_pair + bishop);
+    }
+
+    #[test]
+    fn test_rook_activity() {
+        let pawns = |fen: &str| (board_from_fen(fen), Pawns::from_board(&board_from_fen(fen)));
+        let (board, p) = pawns("4k3/p7/8/8/8/8/1P6/R3K3 w - - 0 1");
+        assert_eq!(
+            PARAMS.rook_semi_open_file,
+            rook_activity(&board, &p, PieceColor::Light, (0, 0).into(), &PARAMS)
+        );
+        let (board, p) = pawns("4k3/8/8/8/8/8/1P6/R3K3 w - - 0 1");
+        assert_eq!(
+            PARAMS.rook_open_file,
+            rook_activity(&board, &p, PieceColor::Light, (0, 0).into(), &PARAMS)
+        );
+        let (board, p) = pawns("4k3/R7/8/8/8/8/PP6/4K3 w - - 0 1");
+        assert_eq!(
+            PARAMS.rook_on_seventh,
+            rook_activity(&board, &p, PieceColor::Light, (0, 6).into(), &PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_outpost() {
+        // The knight on d5 is protected by e4 and no dark pawn can attack it.
+        let pawns = Pawns::from_board(&board_from_fen("4k3/p7/8/3N4/4P3/8/8/4K3 w - - 0 1"));
+        assert!(is_outpost(&pawns, PieceColor::Light, (3, 4).into()));
+        // The pawn on c7 can chase it away.
+        let pawns = Pawns::from_board(&board_from_fen("4k3/2p5/8/3N4/4P3/8/8/4K3 w - - 0 1"));
+        assert!(!is_outpost(&pawns, PieceColor::Light, (3, 4).into()));
+    }
+
+    #[test]
+    fn test_trapped() {
+        // The bishop on h7 can only move to g8.
+        let board = board_from_fen("4k3/5p1B/6p1/8/8/8/8/4K3 w - - 0 1");
+        let index = piece_index(PieceType::Bishop);
+        let pawns = Pawns::from_board(&board);
+        let score = team_mobility(&board, &pawns, PieceColor::Light, &PARAMS);
+        let mobility = PARAMS.mobility[index] * (1 - PARAMS.mobility_average[index]);
+        assert_eq!(mobility + PARAMS.trapped, score);
+    }
+}
@@ -0,0 +1,288 @@
+//! Contains Functions used for evaluating the current board.
+
+use std::ops::{Add, AddAssign, Deref, Mul, Neg, Sub, SubAssign};
+
+use ecr_shared::pieces::PieceColor;
+
+use crate::board;
+use crate::board::{Board, ThreatenedState};
+use crate::eval::params::EvalParams;
+use crate::eval::pawns::{PawnEntry, PawnHashTable};
+use crate::score::Score;
+use ecr_shared::coordinate::Coordinate;
+
+pub mod endgame;
+pub mod evaluator;
+pub mod king_safety;
+pub mod kpk;
+pub mod mobility;
+pub mod nnue;
+pub mod params;
+pub mod pawns;
+pub mod pst;
+pub mod trace;
+pub mod tuner;
+
+/// A score with separate values for the midgame and the endgame. The score of a board is
+/// interpolated between them depending on the game phase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TaperedScore {
+    pub midgame: i32,
+    pub endgame: i32,
+}
+
+impl TaperedScore {
+    pub const fn new(midgame: i32, endgame: i32) -> TaperedScore {
+        TaperedScore { midgame, endgame }
+    }
+
+    /// Interpolates between the midgame and the endgame value. A phase of [`pst::MAX_PHASE`] is
+    /// the pure midgame and a phase of zero the pure endgame.
+    pub fn taper(self, phase: i32) -> Score {
+        let phase = phase.clamp(0, pst::MAX_PHASE);
+        Score::new(
+            (self.midgame * phase + self.endgame * (pst::MAX_PHASE - phase)) / pst::MAX_PHASE,
+        )
+    }
+}
+
+impl Add for TaperedScore {
+    type Output = TaperedScore;
+
+    fn add(self, rhs: TaperedScore) -> TaperedScore {
+        TaperedScore::new(self.midgame + rhs.midgame, self.endgame + rhs.endgame)
+    }
+}
+
+impl AddAssign for TaperedScore {
+    fn add_assign(&mut self, rhs: TaperedScore) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for TaperedScore {
+    type Output = TaperedScore;
+
+    fn sub(self, rhs: TaperedScore) -> TaperedScore {
+        TaperedScore::new(self.midgame - rhs.midgame, self.endgame - rhs.endgame)
+    }
+}
+
+impl SubAssign for TaperedScore {
+    fn sub_assign(&mut self, rhs: TaperedScore) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<i32> for TaperedScore {
+    type Output = TaperedScore;
+
+    fn mul(self, rhs: i32) -> TaperedScore {
+        TaperedScore::new(self.midgame * rhs, self.endgame * rhs)
+    }
+}
+
+impl Neg for TaperedScore {
+    type Output = TaperedScore;
+
+    fn neg(self) -> TaperedScore {
+        TaperedScore::new(-self.midgame, -self.endgame)
+    }
+}
+
+impl board::Board {
+    /// Returns the score of the board from the view of light. The material, the piece-square
+    /// tables, the pawn structure, the king safety, the mobility and the threats on the centre are
+    /// interpolated by the game phase. Known endgames replace or scale the score, see
+    /// [`endgame::probe`]. Uses the weights of [`EvalParams::DEFAULT`].
+    pub fn eval(&self) -> Score {
+        self.eval_with_params(&EvalParams::DEFAULT)
+    }
+
+    /// Returns the score of the board from the view of light with the given weights.
+    pub fn eval_with_params(&self, params: &EvalParams) -> Score {
+        self.eval_with_pawns(&pawns::evaluate_pawns(self, params), params)
+    }
+
+    /// Returns the same score as [`Board::eval_with_params`], but takes the pawn structure from
+    /// the table if it was already evaluated.
+    pub fn eval_with_pawn_table(&self, table: &mut PawnHashTable, params: &EvalParams) -> Score {
+        self.eval_with_pawns(&table.probe(self, params), params)
+    }
+
+    fn eval_with_pawns(&self, pawns: &PawnEntry, params: &EvalParams) -> Score {
+        let tapered = evaluate_pieces(self, params)
+            + pawns.score
+            + pawns::evaluate_free_paths(self, pawns, params)
+            + king_safety::evaluate_king_safety(self, params)
+            + mobility::evaluate_mobility(self, params)
+            + middle_squares_score(self, PieceColor::Light, params)
+            - middle_squares_score(self, PieceColor::Dark, params);
+        let score = tapered.taper(self.get_game_phase_with_params(params));
+        endgame::probe(self).map_or(score, |endgame| endgame.apply(score))
+    }
+
+    /// Returns the game phase, which goes down from [`pst::MAX_PHASE`] at the start to zero once
+    /// only kings and pawns are left. Boards with promoted pieces are capped at the maximum.
+    pub fn get_game_phase(&self) -> i32 {
+        self.get_game_phase_with_params(&EvalParams::DEFAULT)
+    }
+
+    /// Returns the game phase with the given phase weights of the pieces.
+    pub fn get_game_phase_with_params(&self, params: &EvalParams) -> i32 {
+        let phase: i32 = self
+            .get_pieces()
+            .iter()
+            .map(|piece| params.phase_weight(piece.borrow().get_piece().get_type()))
+            .sum();
+        phase.min(pst::MAX_PHASE)
+    }
+}
+
+/// Returns the material and piece-square bonus of light minus the one of dark.
+fn evaluate_pieces(board: &Board, params: &EvalParams) -> TaperedScore {
+    team_material(board, PieceColor::Light, params)
+        + team_piece_squares(board, PieceColor::Light, params)
+        - team_material(board, PieceColor::Dark, params)
+        - team_piece_squares(board, PieceColor::Dark, params)
+}
+
+/// Returns the value of the pieces of the team.
+pub(crate) fn team_material(board: &Board, team: PieceColor, params: &EvalParams) -> TaperedScore {
+    let mut result = TaperedScore::default();
+    for piece in board.get_team_pieces(team) {
+        result += params.material(piece.borrow().get_piece().get_type());
+    }
+    result
+}
+
+/// Returns the piece-square bonus of the pieces of the team.
+pub(crate) fn team_piece_squares(
+    board: &Board,
+    team: PieceColor,
+    params: &EvalParams,
+) -> TaperedScore {
+    let mut result = TaperedScore::default();
+    for piece in board.get_team_pieces(team) {
+        let piece = piece.borrow();
+        let piece = piece.deref();
+        result += params.piece_square(piece.get_piece().get_type(), team, piece.get_coordinate());
+    }
+    result
+}
+
+/// Returns the score of the threats of a team on the four middle squares.
+pub(crate) fn middle_squares_score(
+    board: &Board,
+    team: PieceColor,
+    params: &EvalParams,
+) -> TaperedScore {
+    let threats =
+        get_threatened_score(get_threatened_states(board, get_middle_squares()), team) as i32;
+    params.centre_threat * threats
+}
+
+fn get_middle_squares() -> Vec<Coordinate> {
+    vec![
+        Coordinate { y: 3, x: 3 },
+        Coordinate { y: 4, x: 3 },
+        Coordinate { y: 3, x: 4 },
+        Coordinate { y: 4, x: 4 },
+    ]
+}
+
+fn get_threatened_states(board: &Board, coords: Vec<Coordinate>) -> Vec<ThreatenedState> {
+    let mut result = vec![];
+    for coord in coords {
+        result.push(board.get_threatened_state(coord));
+    }
+    result
+}
+
+/// Gets the threats of a particular team on given squares
+fn get_threatened_score(states: Vec<ThreatenedState>, team: PieceColor) -> u64 {
+    let mut result: u64 = 0;
+    for state in states {
+        result += state.get_by_team(team) as u64;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate_pieces() {
+        let default_board = Board::default();
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_pieces(&default_board, &EvalParams::DEFAULT)
+        );
+        let empty_board = Board::empty();
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_pieces(&empty_board, &EvalParams::DEFAULT)
+        );
+        // Light has an extra knight.
+        let board = board_from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        assert!(evaluate_pieces(&board, &EvalParams::DEFAULT).midgame > 300);
+    }
+
+    #[test]
+    fn test_game_phase() {
+        assert_eq!(pst::MAX_PHASE, Board::default().get_game_phase());
+        assert_eq!(0, Board::empty().get_game_phase());
+        let board = board_from_fen("4k3/8/8/8/8/2N5/8/3QK3 w - - 0 1");
+        assert_eq!(5, board.get_game_phase());
+    }
+
+    #[test]
+    fn test_taper() {
+        let score = TaperedScore::new(100, 20);
+        assert_eq!(Score::new(100), score.taper(pst::MAX_PHASE));
+        assert_eq!(Score::new(20), score.taper(0));
+        assert_eq!(Score::new(60), score.taper(pst::MAX_PHASE / 2));
+        assert_eq!(Score::new(100), score.taper(40));
+    }
+
+    #[test]
+    fn test_eval_is_symmetric() {
+        assert_eq!(Score::DRAW, Board::default().eval());
+        // The king belongs in the centre in the endgame.
+        let centre = board_from_fen("4k3/8/8/8/3K4/8/8/8 w - - 0 1");
+        let corner = board_from_fen("4k3/8/8/8/8/8/8/K7 w - - 0 1");
+        assert!(centre.eval() > corner.eval());
+    }
+
+    #[test]
+    fn test_eval_with_params() {
+        let board = board_from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1");
+        assert_eq!(board.eval(), board.eval_with_params(&EvalParams::DEFAULT));
+        // Without material and piece-square tables only the other terms are left.
+        let mut params = EvalParams::DEFAULT;
+        params.material = [TaperedScore::default(); 6];
+        params.piece_squares = [[TaperedScore::default(); 64]; 6];
+        assert!(board.eval_with_params(&params) < board.eval());
+        params.phase_weights = [0; 6];
+        assert_eq!(0, board.get_game_phase_with_params(&params));
+    }
+
+    #[test]
+    fn test_eval_with_pawn_table() {
+        let board = board_from_fen("4k3/pp6/8/8/8/8/PPP5/4K3 w - - 0 1");
+        let mut table = PawnHashTable::default();
+        let params = EvalParams::DEFAULT;
+        assert_eq!(
+            board.eval(),
+            board.eval_with_pawn_table(&mut table, &params)
+        );
+        assert_eq!(
+            board.eval(),
+            board.eval_with_pawn_table(&mut table, &params)
+        );
+        assert_eq!(1, table.get_hits());
+    }
+}
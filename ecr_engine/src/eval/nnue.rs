@@ -0,0 +1,691 @@
+//! An evaluation by a small efficiently updatable neural network (NNUE) that runs on the CPU.
+//!
+//! The input of the network is one feature for every combination of piece type, team and square,
+//! seen once from the view of light and once mirrored from the view of dark. The first layer sums
+//! up the weights of the active features into an accumulator per view. A move only changes a few
+//! features, so the accumulators are updated with the difference to the previous board instead of
+//! being computed from scratch. The accumulator of the team that has to move and the one of its
+//! opponent are clipped and multiplied with the output weights, which gives the score.
+//!
+//! The weights are quantized to integers and read from a file, see [`Network::from_bytes`] for
+//! the format. There is no network built into the engine.
+
+use std::convert::TryInto;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+
+use ecr_shared::pieces::PieceColor;
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::eval::evaluator::Evaluator;
+use crate::eval::params::piece_index;
+use crate::score::Score;
+
+/// The amount of input features, which is two teams times six piece types times 64 squares.
+pub const INPUTS: usize = 768;
+
+/// The accumulator values are clipped to `0..=QA` before the output layer.
+const QA: i32 = 255;
+/// The factor the output weights are quantized with.
+const QB: i32 = 64;
+/// The output of the network times this factor is the score in centipawns.
+const SCALE: i32 = 400;
+
+const MAGIC: &[u8; 8] = b"ECRNNUE\0";
+const VERSION: u32 = 1;
+
+/// An error that occurred while reading or writing a network.
+#[derive(Debug, Error)]
+pub enum NnueError {
+    #[error("the file is not an NNUE network")]
+    InvalidMagic,
+
+    #[error("unsupported network version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("the network needs {expected} bytes, but got {actual}")]
+    WrongSize { expected: usize, actual: usize },
+
+    #[error("{layer} has {actual} weights instead of {expected}")]
+    WrongWeightAmount {
+        layer: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("cannot access the file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The quantized weights of a network with a single hidden layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    /// The weights of every feature one after another, `hidden` values each.
+    feature_weights: Vec<i16>,
+    feature_bias: Vec<i16>,
+    /// The weights of the accumulator of the team that has to move followed by the ones of its
+    /// opponent.
+    output_weights: Vec<i16>,
+    output_bias: i32,
+}
+
+impl Network {
+    /// Returns a network with the given weights. Fails if the amount of weights doesn't fit to
+    /// the size of the hidden layer.
+    pub fn new(
+        hidden: usize,
+        feature_weights: Vec<i16>,
+        feature_bias: Vec<i16>,
+        output_weights: Vec<i16>,
+        output_bias: i32,
+    ) -> Result<Network, NnueError> {
+        let check = |layer, expected, actual| {
+            if expected == actual {
+                Ok(())
+            } else {
+                Err(NnueError::WrongWeightAmount {
+                    layer,
+                    expected,
+                    actual,
+                })
+            }
+        };
+        check("feature weights", INPUTS * hidden, feature_weights.len())?;
+        check("feature bias", hidden, feature_bias.len())?;
+        check("output weights", 2 * hidden, output_weights.len())?;
+        Ok(Network {
+            hidden,
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        })
+    }
+
+    /// Returns the size of the hidden layer.
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden
+    }
+
+    /// Reads a network from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Network, NnueError> {
+        Network::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the network to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), NnueError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a network. Every number is stored in little endian:
+    ///
+    /// - the magic bytes `ECRNNUE\0`, the version `1` and the size of the hidden layer as `u32`
+    /// - the feature weights as `i16`, feature by feature
+    /// - the feature bias as `i16`
+    /// - the output weights as `i16`, first for the team that has to move
+    /// - the output bias as `i32`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Network, NnueError> {
+        if bytes.len() < 16 {
+            return Err(NnueError::WrongSize {
+                expected: 16,
+                actual: bytes.len(),
+            });
+        }
+        if &bytes[..8] != MAGIC {
+            return Err(NnueError::InvalidMagic);
+        }
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let version = read_u32(8);
+        if version != VERSION {
+            return Err(NnueError::UnsupportedVersion(version));
+        }
+        let hidden = read_u32(12) as usize;
+        let expected = 16 + 2 * (INPUTS * hidden + hidden + 2 * hidden) + 4;
+        if bytes.len() != expected {
+            return Err(NnueError::WrongSize {
+                expected,
+                actual: bytes.len(),
+            });
+        }
+        let mut offset = 16;
+        let mut read_i16s = |amount: usize| {
+            let values = bytes[offset..offset + 2 * amount]
+                .chunks_exact(2)
+                .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+                .collect::<Vec<i16>>();
+            offset += 2 * amount;
+            values
+        };
+        let feature_weights = read_i16s(INPUTS * hidden);
+        let feature_bias = read_i16s(hidden);
+        let output_weights = read_i16s(2 * hidden);
+        let output_bias = i32::from_le_bytes(bytes[expected - 4..].try_into().unwrap());
+        Network::new(
+            hidden,
+            feature_weights,
+            feature_bias,
+            output_weights,
+            output_bias,
+        )
+    }
+
+    /// Returns the network in the format of [`Network::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden as u32).to_le_bytes());
+        for value in self
+            .feature_weights
+            .iter()
+            .chain(&self.feature_bias)
+            .chain(&self.output_weights)
+        {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    fn feature(&self, index: usize) -> &[i16] {
+        &self.feature_weights[index * self.hidden..(index + 1) * self.hidden]
+    }
+
+    /// Returns the score from the view of the team that has to move in centipawns.
+    fn output(&self, us: &[i16], them: &[i16]) -> i32 {
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden);
+        let sum = simd::clipped_dot(us, our_weights)
+            .wrapping_add(simd::clipped_dot(them, their_weights))
+            .wrapping_add(self.output_bias);
+        (sum as i64 * SCALE as i64 / (QA * QB) as i64) as i32
+    }
+}
+
+/// The pieces of a board as one bitboard per team and piece type, where the square `(x, y)` is
+/// the bit `y * 8 + x`. Light's pieces come first.
+type PieceBoards = [u64; 12];
+
+fn piece_boards(board: &Board) -> PieceBoards {
+    let mut boards = [0; 12];
+    for inner in board.get_pieces() {
+        let piece = inner.deref().borrow();
+        let square = piece.get_coordinate();
+        boards[kind_index(piece.get_color(), piece_index(piece.get_piece().get_type()))] |=
+            1 << (square.get_y() as u64 * 8 + square.get_x() as u64);
+    }
+    boards
+}
+
+fn kind_index(color: PieceColor, piece: usize) -> usize {
+    match color {
+        PieceColor::Light => piece,
+        PieceColor::Dark => 6 + piece,
+    }
+}
+
+/// Returns the input feature of a piece from the view of a team. Dark sees the board mirrored, so
+/// both teams see their own pieces first and their first rank at the bottom.
+fn feature_index(view: PieceColor, color: PieceColor, piece: usize, square: usize) -> usize {
+    let (opponent, square) = match view {
+        PieceColor::Light => (color != view, square),
+        PieceColor::Dark => (color != view, square ^ 56),
+    };
+    opponent as usize * 384 + piece * 64 + square
+}
+
+/// The output of the first layer for both views together with the pieces it was computed for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Accumulator {
+    light: Vec<i16>,
+    dark: Vec<i16>,
+    pieces: PieceBoards,
+}
+
+impl Accumulator {
+    fn new(network: &Network, board: &Board) -> Accumulator {
+        let mut accumulator = Accumulator {
+            light: network.feature_bias.clone(),
+            dark: network.feature_bias.clone(),
+            pieces: [0; 12],
+        };
+        accumulator.update(network, piece_boards(board));
+        accumulator
+    }
+
+    /// Adds the features of the pieces that are new and removes the ones that are gone.
+    fn update(&mut self, network: &Network, pieces: PieceBoards) {
+        for (kind, (old, new)) in self.pieces.iter().zip(pieces.iter()).enumerate() {
+            let (color, piece) = if kind < 6 {
+                (PieceColor::Light, kind)
+            } else {
+                (PieceColor::Dark, kind - 6)
+            };
+            let removed = old & !new;
+            let added = new & !old;
+            for (mut bits, add) in [(removed, false), (added, true)].iter().copied() {
+                while bits != 0 {
+                    let square = bits.trailing_zeros() as usize;
+                    bits &= bits - 1;
+                    for (view, values) in [
+                        (PieceColor::Light, &mut self.light),
+                        (PieceColor::Dark, &mut self.dark),
+                    ]
+                    .iter_mut()
+                    {
+                        let weights = network.feature(feature_index(*view, color, piece, square));
+                        if add {
+                            simd::add_assign(values, weights);
+                        } else {
+                            simd::sub_assign(values, weights);
+                        }
+                    }
+                }
+            }
+        }
+        self.pieces = pieces;
+    }
+}
+
+/// Evaluates boards with a [`Network`]. Keeps a stack of accumulators, so the search can update
+/// them with every move it makes and go back to the previous one when it takes the move back.
+#[derive(Debug, Clone)]
+pub struct NnueEvaluator {
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+    /// The amount of accumulators on the stack that are in use. The others are kept to avoid
+    /// allocating them again.
+    len: usize,
+}
+
+impl NnueEvaluator {
+    /// Returns an evaluator with an empty stack. The network can be shared between evaluators.
+    pub fn new(network: Arc<Network>) -> NnueEvaluator {
+        NnueEvaluator {
+            network,
+            stack: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn get_network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    /// Returns the amount of boards on the stack.
+    pub fn get_depth(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, board: &Board) {
+        let pieces = piece_boards(board);
+        if self.len == 0 {
+            let accumulator = Accumulator::new(&self.network, board);
+            if self.stack.is_empty() {
+                self.stack.push(accumulator);
+            } else {
+                self.stack[0] = accumulator;
+            }
+        } else {
+            if self.len == self.stack.len() {
+                let top = self.stack[self.len - 1].clone();
+                self.stack.push(top);
+            } else {
+                let (done, free) = self.stack.split_at_mut(self.len);
+                free[0].clone_from(&done[self.len - 1]);
+            }
+            self.stack[self.len].update(&self.network, pieces);
+        }
+        self.len += 1;
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    /// Computes the accumulator of the board from scratch and makes it the only one on the stack.
+    fn reset(&mut self, board: &Board) {
+        self.len = 0;
+        self.push(board);
+    }
+
+    /// Pushes the accumulator of the board after a move, which is updated from the one of the
+    /// board before the move. Computes it from scratch if the stack is empty.
+    fn make(&mut self, board: &Board) {
+        if self.len == 0 {
+            self.reset(board);
+        } else {
+            self.push(board);
+        }
+    }
+
+    /// Goes back to the board before the last move.
+    fn unmake(&mut self) {
+        self.len = self.len.saturating_sub(1);
+    }
+
+    /// Returns the score of the board from the view of light. Uses the accumulator on top of the
+    /// stack, which has to belong to the board, or computes it from scratch if the stack is empty.
+    fn evaluate(&mut self, board: &Board) -> Score {
+        if self.len == 0 {
+            self.reset(board);
+        }
+        let top = &self.stack[self.len - 1];
+        let score = if board.get_light_to_move() {
+            self.network.output(&top.light, &top.dark)
+        } else {
+            -self.network.output(&top.dark, &top.light)
+        };
+        Score::new(score)
+    }
+}
+
+/// The vector operations of the network. Uses AVX2 if the CPU supports it and plain loops that
+/// the compiler can vectorize otherwise.
+mod simd {
+    use super::QA;
+
+    pub(super) fn add_assign(values: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: The CPU supports AVX2.
+                unsafe { avx2::add_assign(values, weights) };
+                return;
+            }
+        }
+        scalar::add_assign(values, weights);
+    }
+
+    pub(super) fn sub_assign(values: &mut [i16], weights: &[i16]) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: The CPU supports AVX2.
+                unsafe { avx2::sub_assign(values, weights) };
+                return;
+            }
+        }
+        scalar::sub_assign(values, weights);
+    }
+
+    /// Returns the sum of the values clipped to `0..=QA` times the weights.
+    pub(super) fn clipped_dot(values: &[i16], weights: &[i16]) -> i32 {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                // SAFETY: The CPU supports AVX2.
+                return unsafe { avx2::clipped_dot(values, weights) };
+            }
+        }
+        scalar::clipped_dot(values, weights)
+    }
+
+    pub(super) mod scalar {
+        use super::QA;
+
+        pub(in super::super) fn add_assign(values: &mut [i16], weights: &[i16]) {
+            for (value, weight) in values.iter_mut().zip(weights) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+
+        pub(in super::super) fn sub_assign(values: &mut [i16], weights: &[i16]) {
+            for (value, weight) in values.iter_mut().zip(weights) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+
+        pub(in super::super) fn clipped_dot(values: &[i16], weights: &[i16]) -> i32 {
+            values
+                .iter()
+                .zip(weights)
+                .fold(0i32, |sum, (value, weight)| {
+                    let clipped = (*value as i32).clamp(0, QA);
+                    sum.wrapping_add(clipped * *weight as i32)
+                })
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    mod avx2 {
+        use std::arch::x86_64::*;
+
+        use super::{scalar, QA};
+
+        /// The amount of `i16` in a register.
+        const LANES: usize = 16;
+
+        #[target_feature(enable = "avx2")]
+        pub(super) unsafe fn add_assign(values: &mut [i16], weights: &[i16]) {
+            let length = values.len().min(weights.len());
+            let full = length / LANES * LANES;
+            for index in (0..full).step_by(LANES) {
+                let value = _mm256_loadu_si256(values.as_ptr().add(index) as *const __m256i);
+                let weight = _mm256_loadu_si256(weights.as_ptr().add(index) as *const __m256i);
+                _mm256_storeu_si256(
+                    values.as_mut_ptr().add(index) as *mut __m256i,
+                    _mm256_add_epi16(value, weight),
+                );
+            }
+            scalar::add_assign(&mut values[full..length], &weights[full..length]);
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub(super) unsafe fn sub_assign(values: &mut [i16], weights: &[i16]) {
+            let length = values.len().min(weights.len());
+            let full = length / LANES * LANES;
+            for index in (0..full).step_by(LANES) {
+                let value = _mm256_loadu_si256(values.as_ptr().add(index) as *const __m256i);
+                let weight = _mm256_loadu_si256(weights.as_ptr().add(index) as *const __m256i);
+                _mm256_storeu_si256(
+                    values.as_mut_ptr().add(index) as *mut __m256i,
+                    _mm256_sub_epi16(value, weight),
+                );
+            }
+            scalar::sub_assign(&mut values[full..length], &weights[full..length]);
+        }
+
+        #[target_feature(enable = "avx2")]
+        pub(super) unsafe fn clipped_dot(values: &[i16], weights: &[i16]) -> i32 {
+            let length = values.len().min(weights.len());
+            let full = length / LANES * LANES;
+            let zero = _mm256_setzero_si256();
+            let max = _mm256_set1_epi16(QA as i16);
+            let mut sum = _mm256_setzero_si256();
+            for index in (0..full).step_by(LANES) {
+                let value = _mm256_loadu_si256(values.as_ptr().add(index) as *const __m256i);
+                let weight = _mm256_loadu_si256(weights.as_ptr().add(index) as *const __m256i);
+                let clipped = _mm256_min_epi16(_mm256_max_epi16(value, zero), max);
+                sum = _mm256_add_epi32(sum, _mm256_madd_epi16(clipped, weight));
+            }
+            let mut lanes = [0i32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sum);
+            lanes
+                .iter()
+                .fold(0i32, |total, lane| total.wrapping_add(*lane))
+                .wrapping_add(scalar::clipped_dot(
+                    &values[full..length],
+                    &weights[full..length],
+                ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::eval::evaluator::ClassicEvaluator;
+    use crate::search::alpha_beta::AlphaBeta;
+    use crate::search::config::SearchConfig;
+    use crate::search::legal_moves;
+    use crate::search::transposition::TranspositionTable;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    /// Returns a network with small pseudo-random weights.
+    fn network(hidden: usize) -> Network {
+        let mut state: u32 = 12345;
+        let mut next = |range: i32| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((state >> 16) as i32 % (2 * range + 1) - range) as i16
+        };
+        let feature_weights = (0..INPUTS * hidden).map(|_| next(60)).collect();
+        let feature_bias = (0..hidden).map(|_| next(60)).collect();
+        let output_weights = (0..2 * hidden).map(|_| next(60)).collect();
+        Network::new(hidden, feature_weights, feature_bias, output_weights, 50).unwrap()
+    }
+
+    #[test]
+    fn test_feature_index() {
+        // A light pawn on e2 looks like a dark pawn on e7 from the view of dark.
+        let light = feature_index(PieceColor::Light, PieceColor::Light, 0, 12);
+        assert_eq!(12, light);
+        assert_eq!(
+            light,
+            feature_index(PieceColor::Dark, PieceColor::Dark, 0, 52)
+        );
+        assert_eq!(
+            384 + 12,
+            feature_index(PieceColor::Dark, PieceColor::Light, 0, 52)
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        let network = network(20);
+        let bytes = network.to_bytes();
+        assert_eq!(network, Network::from_bytes(&bytes).unwrap());
+        assert!(matches!(
+            Network::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(NnueError::WrongSize { .. })
+        ));
+        assert!(matches!(
+            Network::from_bytes(&[0; 20]),
+            Err(NnueError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Network::new(2, vec![], vec![0; 2], vec![0; 4], 0),
+            Err(NnueError::WrongWeightAmount { .. })
+        ));
+
+        let path = env::temp_dir().join(format!("ecr_nnue_{}.bin", std::process::id()));
+        network.save(&path).unwrap();
+        let loaded = Network::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(network, loaded.unwrap());
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        let network = network(37);
+        let values: Vec<i16> = network.feature(3).iter().map(|v| v * 5).collect();
+        let weights = network.feature(500);
+        assert_eq!(
+            simd::scalar::clipped_dot(&values, weights),
+            simd::clipped_dot(&values, weights)
+        );
+        let mut fast = values.clone();
+        let mut slow = values;
+        simd::add_assign(&mut fast, weights);
+        simd::scalar::add_assign(&mut slow, weights);
+        assert_eq!(slow, fast);
+        simd::sub_assign(&mut fast, network.feature(7));
+        simd::scalar::sub_assign(&mut slow, network.feature(7));
+        assert_eq!(slow, fast);
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let network = Arc::new(network(32));
+        let mut evaluator = NnueEvaluator::new(network.clone());
+        let mut board = board_from_fen("r3k2r/ppp2ppp/2n5/3pP3/8/2N5/PPP2PPP/R3K2R w KQkq d6 0 1");
+        evaluator.reset(&board);
+        let start = evaluator.evaluate(&board);
+        // Follow a line with en passant and castling and compare with a fresh accumulator.
+        for _ in 0..6 {
+            let m = legal_moves(&board)
+                .into_iter()
+                .max_by_key(|m| (m.is_capture(), m.get_to().get_x()))
+                .unwrap();
+            board = m.apply(&board);
+            evaluator.make(&board);
+            let mut fresh = NnueEvaluator::new(network.clone());
+            assert_eq!(fresh.evaluate(&board), evaluator.evaluate(&board));
+        }
+        assert_eq!(7, evaluator.get_depth());
+        for _ in 0..6 {
+            evaluator.unmake();
+        }
+        assert_eq!(
+            start,
+            evaluator.evaluate(&board_from_fen(
+                "r3k2r/ppp2ppp/2n5/3pP3/8/2N5/PPP2PPP/R3K2R w KQkq d6 0 1"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let evaluator = NnueEvaluator::new(Arc::new(network(16)));
+        let table = Arc::new(TranspositionTable::default());
+        let mut search = AlphaBeta::with_evaluator(SearchConfig::default(), table, evaluator);
+        assert!(search.search(&board, 2).get_best_move().is_some());
+        // Every move was taken back.
+        assert_eq!(1, search.get_evaluator().get_depth());
+    }
+
+    /// Returns the evaluator with the given name, like a front end that reads it from its
+    /// configuration.
+    fn evaluator_from_config(name: &str) -> Box<dyn Evaluator> {
+        match name {
+            "nnue" => Box::new(NnueEvaluator::new(Arc::new(network(16)))),
+            _ => Box::new(ClassicEvaluator::new()),
+        }
+    }
+
+    #[test]
+    fn test_switch_at_runtime() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let search_with = |evaluator: Box<dyn Evaluator>| {
+            let table = Arc::new(TranspositionTable::default());
+            AlphaBeta::with_evaluator(SearchConfig::default(), table, evaluator).search(&board, 2)
+        };
+        let classic = AlphaBeta::new().search(&board, 2);
+        let nnue = AlphaBeta::with_evaluator(
+            SearchConfig::default(),
+            Arc::new(TranspositionTable::default()),
+            NnueEvaluator::new(Arc::new(network(16))),
+        )
+        .search(&board, 2);
+        assert_eq!(classic, search_with(evaluator_from_config("classic")));
+        assert_eq!(nnue, search_with(evaluator_from_config("nnue")));
+
+        // The evaluator of an existing search can be replaced as well.
+        let table = Arc::new(TranspositionTable::default());
+        let mut search = AlphaBeta::with_evaluator(
+            SearchConfig::default(),
+            table,
+            evaluator_from_config("nnue"),
+        );
+        assert_eq!(nnue.score, search.search(&board, 2).score);
+        *search.get_evaluator_mut() = evaluator_from_config("classic");
+        search.get_table().clear();
+        assert_eq!(classic.score, search.search(&board, 2).score);
+    }
+
+    #[test]
+    fn test_mirrored_board() {
+        let mut evaluator = NnueEvaluator::new(Arc::new(network(16)));
+        let light = evaluator.evaluate(&board_from_fen("4k3/8/8/8/2N5/8/PP6/4K3 w - - 0 1"));
+        evaluator.reset(&board_from_fen("4k3/pp6/8/2n5/8/8/8/4K3 b - - 0 1"));
+        let dark = evaluator.evaluate(&board_from_fen("4k3/pp6/8/2n5/8/8/8/4K3 b - - 0 1"));
+        assert_ne!(Score::DRAW, light);
+        assert_eq!(light, -dark);
+    }
+}
@@ -0,0 +1,542 @@
+//! Every weight of the evaluation in one place. The weights can be written to and read from a text
+//! file, so different playing styles can be tuned and used without recompiling the engine.
+//!
+//! Every line of the file has the form `name = values`, where the values are separated by spaces
+//! and a tapered value is written as its midgame value followed by its endgame value. Empty lines
+//! and lines starting with `#` are ignored. Weights that are missing in the file keep their default
+//! value. Every weight has to be within [`MAX_WEIGHT`], so summing them up can't overflow.
+
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::num::ParseIntError;
+use std::path::Path;
+use std::slice;
+use std::str::FromStr;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+use thiserror::Error;
+
+use crate::eval::{pst, TaperedScore};
+
+/// The largest absolute value of a weight that is read from a file.
+pub const MAX_WEIGHT: i32 = 10_000;
+
+/// An error that occurred while reading or writing evaluation parameters.
+#[derive(Debug, Error)]
+pub enum ParamsError {
+    #[error("line {0} is not of the form `name = values`")]
+    InvalidLine(usize),
+
+    #[error("unknown parameter {0}")]
+    UnknownParameter(String),
+
+    #[error("parameter {name} needs {expected} values, but got {actual}")]
+    WrongValueAmount {
+        name: String,
+        expected: usize,
+        actual: usize,
+    },
+
+    #[error("parameter {name} has the value {value}, which is outside of ±{MAX_WEIGHT}")]
+    ValueOutOfRange { name: String, value: i32 },
+
+    #[error("cannot parse as int: {0}")]
+    ParseIntError(#[from] ParseIntError),
+
+    #[error("cannot access the file: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// The weights of the evaluation. Arrays that hold a value for every piece type are ordered pawn,
+/// knight, bishop, rook, queen and king. Arrays indexed by rank use the rank from the view of the
+/// team, so the starting rank of the pawns is `1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalParams {
+    /// The value of every piece type.
+    pub material: [TaperedScore; 6],
+    /// The piece-square tables, written like [`pst`] from the view of light with the eighth rank
+    /// first.
+    pub piece_squares: [[TaperedScore; 64]; 6],
+    /// How much a piece counts towards the game phase. The weights of the starting pieces should
+    /// add up to [`pst::MAX_PHASE`].
+    pub phase_weights: [i32; 6],
+
+    /// Penalty for every pawn that has another pawn of its team in front of it on the same file.
+    pub doubled: TaperedScore,
+    /// Penalty for a pawn without pawns of its team on the neighbouring files.
+    pub isolated: TaperedScore,
+    /// Penalty for a pawn that can't be protected by other pawns and whose way forward is guarded
+    /// by a pawn of the opponent.
+    pub backward: TaperedScore,
+    /// Bonus for a pawn that is protected by a pawn or stands next to one, indexed by rank.
+    pub connected: [TaperedScore; 8],
+    /// Bonus for a pawn without pawns of the opponent in front of it on its own and the
+    /// neighbouring files and without a pawn of its team in front of it, indexed by rank.
+    pub passed: [TaperedScore; 8],
+    /// Additional bonus for a passed pawn if no piece stands between it and its promotion square,
+    /// indexed by rank.
+    pub free_path: [TaperedScore; 8],
+
+    /// Bonus for the nearest pawn of the team in front of the king on its file and the files next
+    /// to it, indexed by its distance to the king. `0` means that there is no such pawn and the
+    /// last entry is used for every pawn that is further away.
+    pub shelter: [i32; 5],
+    /// Penalty for the nearest pawn of the opponent in front of the king on its file and the files
+    /// next to it, indexed like [`EvalParams::shelter`].
+    pub storm: [i32; 5],
+    /// Penalty for a file near the king without pawns of the team.
+    pub king_semi_open_file: i32,
+    /// Penalty for a file near the king without any pawns.
+    pub king_open_file: i32,
+    /// How dangerous an attack of a piece on the king zone is. Pieces with zero units are ignored.
+    pub attack_units: [i32; 6],
+    /// Attack units for every square of the king zone that the opponent threatens more often than
+    /// the team.
+    pub controlled_square: i32,
+    /// The highest penalty for an attack on the king.
+    pub max_attack_penalty: i32,
+
+    /// The bonus per safe square a piece can move to. Pieces with a zero average are ignored.
+    pub mobility: [TaperedScore; 6],
+    /// The amount of safe squares that counts as average mobility. Pieces with less squares get a
+    /// penalty.
+    pub mobility_average: [i32; 6],
+    /// Bonus for having two bishops.
+    pub bishop_pair: TaperedScore,
+    /// Bonus for a rook on a file without pawns.
+    pub rook_open_file: TaperedScore,
+    /// Bonus for a rook on a file without pawns of its team.
+    pub rook_semi_open_file: TaperedScore,
+    /// Bonus for a rook on the seventh rank if it traps the king of the opponent on the last rank
+    /// or attacks pawns there.
+    pub rook_on_seventh: TaperedScore,
+    /// Bonus for a knight in the half of the opponent that is protected by a pawn and can't be
+    /// chased away by the pawns of the opponent.
+    pub knight_outpost: TaperedScore,
+    /// Penalty for a piece besides the queen that has at most one safe square to move to.
+    pub trapped: TaperedScore,
+
+    /// Bonus for every threat on one of the four middle squares.
+    pub centre_threat: TaperedScore,
+}
+
+impl EvalParams {
+    /// The weights the engine uses if nothing else is set.
+    pub const DEFAULT: EvalParams = EvalParams {
+        material: [
+            TaperedScore::new(82, 94),
+            TaperedScore::new(337, 281),
+            TaperedScore::new(365, 297),
+            TaperedScore::new(477, 512),
+            TaperedScore::new(1025, 936),
+            TaperedScore::new(0, 0),
+        ],
+        piece_squares: pst::PIECE_SQUARES,
+        phase_weights: [0, 1, 1, 2, 4, 0],
+
+        doubled: TaperedScore::new(-10, -20),
+        isolated: TaperedScore::new(-10, -15),
+        backward: TaperedScore::new(-8, -10),
+        connected: [
+            TaperedScore::new(0, 0),
+            TaperedScore::new(3, 0),
+            TaperedScore::new(6, 3),
+            TaperedScore::new(9, 6),
+            TaperedScore::new(15, 12),
+            TaperedScore::new(25, 20),
+            TaperedScore::new(40, 35),
+            TaperedScore::new(0, 0),
+        ],
+        passed: [
+            TaperedScore::new(0, 0),
+            TaperedScore::new(5, 10),
+            TaperedScore::new(10, 15),
+            TaperedScore::new(15, 25),
+            TaperedScore::new(25, 45),
+            TaperedScore::new(45, 75),
+            TaperedScore::new(70, 120),
+            TaperedScore::new(0, 0),
+        ],
+        free_path: [
+            TaperedScore::new(0, 0),
+            TaperedScore::new(0, 0),
+            TaperedScore::new(0, 5),
+            TaperedScore::new(0, 10),
+            TaperedScore::new(5, 20),
+            TaperedScore::new(10, 35),
+            TaperedScore::new(20, 60),
+            TaperedScore::new(0, 0),
+        ],
+
+        shelter: [-25, 25, 12, 4, -10],
+        // A pawn right in front of the king is blocked by it and is less dangerous than one that
+        // is about to hit the shelter.
+        storm: [0, -5, -30, -15, 0],
+        king_semi_open_file: -15,
+        king_open_file: -25,
+        attack_units: [0, 2, 2, 3, 5, 0],
+        controlled_square: 1,
+        max_attack_penalty: 500,
+
+        mobility: [
+            TaperedScore::new(0, 0),
+            TaperedScore::new(4, 4),
+            TaperedScore::new(5, 5),
+            TaperedScore::new(2, 4),
+            TaperedScore::new(1, 2),
+            TaperedScore::new(0, 0),
+        ],
+        mobility_average: [0, 4, 6, 7, 14, 0],
+        bishop_pair: TaperedScore::new(30, 50),
+        rook_open_file: TaperedScore::new(40, 20),
+        rook_semi_open_file: TaperedScore::new(20, 10),
+        rook_on_seventh: TaperedScore::new(20, 40),
+        knight_outpost: TaperedScore::new(25, 15),
+        trapped: TaperedScore::new(-40, -30),
+
+        centre_threat: TaperedScore::new(10, 10),
+    };
+
+    /// Reads the weights from a file, see the [module](self) for the format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EvalParams, ParamsError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes every weight to a file, see the [module](self) for the format.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ParamsError> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    /// Returns the value of a piece of the given type.
+    pub fn material(&self, piece_type: PieceType) -> TaperedScore {
+        self.material[piece_index(piece_type)]
+    }
+
+    /// Returns the bonus of a piece of the given type and team on the square.
+    pub fn piece_square(
+        &self,
+        piece_type: PieceType,
+        color: PieceColor,
+        square: Coordinate,
+    ) -> TaperedScore {
+        self.piece_squares[piece_index(piece_type)][pst::table_index(color, square)]
+    }
+
+    /// Returns how much a piece of the given type counts towards the game phase.
+    pub fn phase_weight(&self, piece_type: PieceType) -> i32 {
+        self.phase_weights[piece_index(piece_type)]
+    }
+
+    /// Returns every weight in the order of the file with tapered values split into their midgame
+    /// and endgame value.
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut params = self.clone();
+        let slots = params.slots();
+        slots
+            .into_iter()
+            .flat_map(|(_, values)| values)
+            .map(|value| *value)
+            .collect()
+    }
+
+    /// Sets every weight to the values in the order of [`EvalParams::to_vector`].
+    ///
+    /// # Panics
+    /// Panics if the amount of values doesn't match the amount of weights.
+    pub fn set_vector(&mut self, values: &[i32]) {
+        let targets: Vec<&mut i32> = self
+            .slots()
+            .into_iter()
+            .flat_map(|(_, slot)| slot)
+            .collect();
+        assert_eq!(targets.len(), values.len(), "wrong amount of weights");
+        for (target, value) in targets.into_iter().zip(values) {
+            *target = *value;
+        }
+    }
+
+    /// Returns the name of the parameter of every value of [`EvalParams::to_vector`].
+    pub fn vector_names() -> Vec<&'static str> {
+        let mut params = EvalParams::DEFAULT;
+        let slots = params.slots();
+        slots
+            .into_iter()
+            .flat_map(|(name, values)| vec![name; values.len()])
+            .collect()
+    }
+
+    /// Returns every weight as a mutable reference together with the name of its line in the
+    /// file. Tapered values are split into their midgame and endgame value.
+    fn slots(&mut self) -> Vec<(&'static str, Vec<&mut i32>)> {
+        let [pawn, knight, bishop, rook, queen, king] = &mut self.piece_squares;
+        vec![
+            ("material", tapered(&mut self.material)),
+            ("piece_squares.pawn", tapered(pawn)),
+            ("piece_squares.knight", tapered(knight)),
+            ("piece_squares.bishop", tapered(bishop)),
+            ("piece_squares.rook", tapered(rook)),
+            ("piece_squares.queen", tapered(queen)),
+            ("piece_squares.king", tapered(king)),
+            ("phase_weights", plain(&mut self.phase_weights)),
+            ("pawns.doubled", tapered(slice::from_mut(&mut self.doubled))),
+            (
+                "pawns.isolated",
+                tapered(slice::from_mut(&mut self.isolated)),
+            ),
+            (
+                "pawns.backward",
+                tapered(slice::from_mut(&mut self.backward)),
+            ),
+            ("pawns.connected", tapered(&mut self.connected)),
+            ("pawns.passed", tapered(&mut self.passed)),
+            ("pawns.free_path", tapered(&mut self.free_path)),
+            ("king.shelter", plain(&mut self.shelter)),
+            ("king.storm", plain(&mut self.storm)),
+            (
+                "king.semi_open_file",
+                plain(slice::from_mut(&mut self.king_semi_open_file)),
+            ),
+            (
+                "king.open_file",
+                plain(slice::from_mut(&mut self.king_open_file)),
+            ),
+            ("king.attack_units", plain(&mut self.attack_units)),
+            (
+                "king.controlled_square",
+                plain(slice::from_mut(&mut self.controlled_square)),
+            ),
+            (
+                "king.max_attack_penalty",
+                plain(slice::from_mut(&mut self.max_attack_penalty)),
+            ),
+            ("mobility.weight", tapered(&mut self.mobility)),
+            ("mobility.average", plain(&mut self.mobility_average)),
+            (
+                "mobility.bishop_pair",
+                tapered(slice::from_mut(&mut self.bishop_pair)),
+            ),
+            (
+                "mobility.rook_open_file",
+                tapered(slice::from_mut(&mut self.rook_open_file)),
+            ),
+            (
+                "mobility.rook_semi_open_file",
+                tapered(slice::from_mut(&mut self.rook_semi_open_file)),
+            ),
+            (
+                "mobility.rook_on_seventh",
+                tapered(slice::from_mut(&mut self.rook_on_seventh)),
+            ),
+            (
+                "mobility.knight_outpost",
+                tapered(slice::from_mut(&mut self.knight_outpost)),
+            ),
+            (
+                "mobility.trapped",
+                tapered(slice::from_mut(&mut self.trapped)),
+            ),
+            (
+                "centre.threat",
+                tapered(slice::from_mut(&mut self.centre_threat)),
+            ),
+        ]
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> Self {
+        EvalParams::DEFAULT
+    }
+}
+
+impl Display for EvalParams {
+    /// Writes every weight in the format of the file, one line per parameter.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "# Evaluation parameters, tapered values as midgame endgame pairs"
+        )?;
+        for (name, values) in self.clone().slots() {
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            writeln!(f, "{} = {}", name, values.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EvalParams {
+    type Err = ParamsError;
+
+    /// Reads the weights in the format of the file. Missing weights keep their default value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut params = EvalParams::DEFAULT;
+        let mut slots = params.slots();
+        for (number, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, values) = line
+                .split_once('=')
+                .ok_or(ParamsError::InvalidLine(number + 1))?;
+            let name = name.trim();
+            let slot = slots
+                .iter_mut()
+                .find(|(slot_name, _)| *slot_name == name)
+                .map(|(_, slot)| slot)
+                .ok_or_else(|| ParamsError::UnknownParameter(name.to_string()))?;
+            let values = values
+                .split_whitespace()
+                .map(i32::from_str)
+                .collect::<Result<Vec<i32>, ParseIntError>>()?;
+            if values.len() != slot.len() {
+                return Err(ParamsError::WrongValueAmount {
+                    name: name.to_string(),
+                    expected: slot.len(),
+                    actual: values.len(),
+                });
+            }
+            if let Some(&value) = values
+                .iter()
+                .find(|value| !(-MAX_WEIGHT..=MAX_WEIGHT).contains(*value))
+            {
+                return Err(ParamsError::ValueOutOfRange {
+                    name: name.to_string(),
+                    value,
+                });
+            }
+            for (target, value) in slot.iter_mut().zip(values) {
+                **target = value;
+            }
+        }
+        Ok(params)
+    }
+}
+
+/// Returns the index of the piece type in the arrays of [`EvalParams`].
+pub(crate) fn piece_index(piece_type: PieceType) -> usize {
+    match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+fn tapered(values: &mut [TaperedScore]) -> Vec<&mut i32> {
+    values
+        .iter_mut()
+        .flat_map(|value| {
+            let TaperedScore { midgame, endgame } = value;
+            vec![midgame, endgame]
+        })
+        .collect()
+}
+
+fn plain(values: &mut [i32]) -> Vec<&mut i32> {
+    values.iter_mut().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut params = EvalParams::default();
+        params.material[1] = TaperedScore::new(300, 320);
+        params.shelter[2] = 7;
+        params.piece_squares[5][63] = TaperedScore::new(-1, 2);
+        let parsed: EvalParams = params.to_string().parse().unwrap();
+        assert_eq!(params, parsed);
+    }
+
+    #[test]
+    fn test_vector() {
+        let mut params = EvalParams::DEFAULT;
+        let mut values = params.to_vector();
+        let names = EvalParams::vector_names();
+        assert_eq!(names.len(), values.len());
+        assert_eq!(("material", 82), (names[0], values[0]));
+        let last = values.len() - 1;
+        assert_eq!(("centre.threat", 10), (names[last], values[last]));
+        values[last] = 12;
+        params.set_vector(&values);
+        assert_eq!(TaperedScore::new(10, 12), params.centre_threat);
+    }
+
+    #[test]
+    fn test_partial() {
+        let params: EvalParams =
+            "# Aggressive\n\nking.max_attack_penalty = 800\npawns.doubled = -5 -30\n"
+                .parse()
+                .unwrap();
+        assert_eq!(800, params.max_attack_penalty);
+        assert_eq!(TaperedScore::new(-5, -30), params.doubled);
+        assert_eq!(EvalParams::DEFAULT.material, params.material);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            "material".parse::<EvalParams>(),
+            Err(ParamsError::InvalidLine(1))
+        ));
+        assert!(matches!(
+            "\nfoo = 1".parse::<EvalParams>(),
+            Err(ParamsError::UnknownParameter(name)) if name == "foo"
+        ));
+        assert!(matches!(
+            "pawns.doubled = 1".parse::<EvalParams>(),
+            Err(ParamsError::WrongValueAmount {
+                expected: 2,
+                actual: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            "king.open_file = x".parse::<EvalParams>(),
+            Err(ParamsError::ParseIntError(_))
+        ));
+        assert!(matches!(
+            "pawns.doubled = 10 -2147483648".parse::<EvalParams>(),
+            Err(ParamsError::ValueOutOfRange {
+                value: i32::MIN,
+                ..
+            })
+        ));
+        assert!("pawns.doubled = 10000 -10000".parse::<EvalParams>().is_ok());
+    }
+
+    #[test]
+    fn test_piece_square_is_mirrored() {
+        let params = EvalParams::DEFAULT;
+        let light = params.piece_square(PieceType::Knight, PieceColor::Light, (6, 0).into());
+        let dark = params.piece_square(PieceType::Knight, PieceColor::Dark, (6, 7).into());
+        assert_eq!(light, dark);
+        // A knight in the centre is better than one on the rim.
+        let centre = params.piece_square(PieceType::Knight, PieceColor::Light, (4, 4).into());
+        let rim = params.piece_square(PieceType::Knight, PieceColor::Light, (0, 4).into());
+        assert!(centre.midgame > rim.midgame && centre.endgame > rim.endgame);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = env::temp_dir().join(format!("ecr_eval_params_{}.txt", std::process::id()));
+        let params = EvalParams {
+            centre_threat: TaperedScore::new(15, 5),
+            ..EvalParams::default()
+        };
+        params.save(&path).unwrap();
+        let loaded = EvalParams::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(params, loaded.unwrap());
+        assert!(matches!(EvalParams::load(&path), Err(ParamsError::Io(_))));
+    }
+}
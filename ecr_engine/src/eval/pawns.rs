@@ -0,0 +1,392 @@
+//! Evaluation of the pawn structure. The pawn structure changes rarely during a search, so its
+//! score is cached in a [`PawnHashTable`] that is keyed by the pawn hash of the board.
+
+use std::ops::Deref;
+
+use ecr_shared::coordinate::Coordinate;
+use ecr_shared::pieces::{PieceColor, PieceType};
+
+use crate::board::Board;
+use crate::eval::params::EvalParams;
+use crate::eval::TaperedScore;
+
+/// The pawns of both teams as bitboards, where the square `(x, y)` is the bit `y * 8 + x`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) struct Pawns {
+    light: u64,
+    dark: u64,
+}
+
+impl Pawns {
+    pub(crate) fn from_board(board: &Board) -> Pawns {
+        let mut pawns = Pawns::default();
+        for inner in board.get_pieces() {
+            let piece = inner.deref().borrow();
+            if piece.get_piece().get_type() == PieceType::Pawn {
+                let bit = square_bit(piece.get_coordinate());
+                match piece.get_color() {
+                    PieceColor::Light => pawns.light |= bit,
+                    PieceColor::Dark => pawns.dark |= bit,
+                }
+            }
+        }
+        pawns
+    }
+
+    pub(crate) fn get(&self, team: PieceColor) -> u64 {
+        match team {
+            PieceColor::Light => self.light,
+            PieceColor::Dark => self.dark,
+        }
+    }
+}
+
+/// The cached result of the pawn evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PawnEntry {
+    /// The score of the pawn structure from the view of light.
+    pub score: TaperedScore,
+    /// The passed pawns of both teams as a bitboard, where the square `(x, y)` is the bit
+    /// `y * 8 + x`. Whether their path is free depends on the other pieces, so it can't be cached.
+    pub passed: u64,
+}
+
+impl PawnEntry {
+    /// Returns true if the pawn on the square is a passed pawn.
+    pub fn is_passed(&self, square: Coordinate) -> bool {
+        self.passed & square_bit(square) != 0
+    }
+}
+
+/// Caches the pawn evaluation by the pawn hash of the board. Every slot holds one entry and a new
+/// entry always replaces the old one.
+#[derive(Debug, Clone)]
+pub struct PawnHashTable {
+    slots: Vec<Option<(u64, PawnEntry)>>,
+    hits: u64,
+}
+
+impl PawnHashTable {
+    /// Returns a table with the given amount of slots, which gets rounded up to a power of two.
+    pub fn new(slots: usize) -> PawnHashTable {
+        PawnHashTable {
+            slots: vec![None; slots.max(1).next_power_of_two()],
+            hits: 0,
+        }
+    }
+
+    /// Returns the amount of slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if the table has no slots, which never happens.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Returns how often an entry was found in the table.
+    pub fn get_hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the pawn evaluation of the board. It is only computed if it isn't in the table yet,
+    /// so the table has to be cleared once the parameters change.
+    pub fn probe(&mut self, board: &Board, params: &EvalParams) -> PawnEntry {
+        let key = board.get_pawn_hash();
+        let index = key as usize & (self.slots.len() - 1);
+        if let Some((stored_key, entry)) = self.slots[index] {
+            if stored_key == key {
+                self.hits += 1;
+                return entry;
+            }
+        }
+        let entry = evaluate_pawns(board, params);
+        self.slots[index] = Some((key, entry));
+        entry
+    }
+
+    /// Removes every entry.
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.hits = 0;
+    }
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        PawnHashTable::new(1 << 12)
+    }
+}
+
+/// Evaluates the doubled, isolated, backward, connected and passed pawns of the board.
+pub fn evaluate_pawns(board: &Board, params: &EvalParams) -> PawnEntry {
+    let pawns = Pawns::from_board(board);
+    let (light, light_passed) = team_pawns(&pawns, PieceColor::Light, params);
+    let (dark, dark_passed) = team_pawns(&pawns, PieceColor::Dark, params);
+    PawnEntry {
+        score: light - dark,
+        passed: light_passed | dark_passed,
+    }
+}
+
+/// Returns the score of the pawns of the team from its own view and its passed pawns.
+pub(crate) fn team_pawns(
+    pawns: &Pawns,
+    team: PieceColor,
+    params: &EvalParams,
+) -> (TaperedScore, u64) {
+    let mut score = TaperedScore::default();
+    let mut passed = 0;
+    let mut bits = pawns.get(team);
+    while bits != 0 {
+        let index = bits.trailing_zeros() as u8;
+        bits &= bits - 1;
+        let square = Coordinate::new(index % 8, index / 8);
+        let (pawn_score, is_passed) = evaluate_pawn(pawns, team, square, params);
+        if is_passed {
+            passed |= square_bit(square);
+        }
+        score += pawn_score;
+    }
+    (score, passed)
+}
+
+/// Returns the bonus for the passed pawns whose way to the promotion square is free, from the
+/// view of light.
+pub fn evaluate_free_paths(board: &Board, entry: &PawnEntry, params: &EvalParams) -> TaperedScore {
+    team_free_paths(board, entry, PieceColor::Light, params)
+        - team_free_paths(board, entry, PieceColor::Dark, params)
+}
+
+/// Returns the bonus for the passed pawns of the team whose way to the promotion square is free.
+pub(crate) fn team_free_paths(
+    board: &Board,
+    entry: &PawnEntry,
+    team: PieceColor,
+    params: &EvalParams,
+) -> TaperedScore {
+    let mut result = TaperedScore::default();
+    let mut bits = entry.passed;
+    while bits != 0 {
+        let index = bits.trailing_zeros() as u8;
+        bits &= bits - 1;
+        let square = Coordinate::new(index % 8, index / 8);
+        let is_own = board
+            .get_at(square)
+            .is_some_and(|piece| piece.borrow().get_color() == team);
+        if !is_own {
+            continue;
+        }
+        let free = ahead(team, square.get_y())
+            .all(|y| board.get_at(Coordinate::new(square.get_x(), y)).is_none());
+        if free {
+            result += params.free_path[relative_rank(team, square.get_y()) as usize];
+        }
+    }
+    result
+}
+
+/// Returns the score of a single pawn from the view of its team and whether it is passed.
+fn evaluate_pawn(
+    pawns: &Pawns,
+    team: PieceColor,
+    square: Coordinate,
+    params: &EvalParams,
+) -> (TaperedScore, bool) {
+    let own = pawns.get(team);
+    let opponent = pawns.get(team.get_opponent());
+    let x = square.get_x();
+    let y = square.get_y();
+    let rank = relative_rank(team, y);
+    let neighbour_files = adjacent_files(x);
+    let mut score = TaperedScore::default();
+
+    let front = ahead(team, y).fold(0, |bits, rank| bits | rank_mask(rank));
+    if own & file_mask(x) & front != 0 {
+        score += params.doubled;
+    }
+
+    let isolated = own & neighbour_files == 0;
+    if isolated {
+        score += params.isolated;
+    }
+
+    // Protected by a pawn behind it or standing next to one.
+    let behind = behind(team, y).map_or(0, rank_mask);
+    let connected = own & neighbour_files & (rank_mask(y) | behind) != 0;
+    if connected {
+        score += params.connected[rank as usize];
+    }
+
+    // Every pawn that could protect it is already in front of it and the opponent guards the
+    // square in front of it.
+    if !isolated && !connected {
+        let supporters = own & neighbour_files & !front;
+        let guarded = ahead(team, y)
+            .nth(1)
+            .is_some_and(|guard_rank| opponent & neighbour_files & rank_mask(guard_rank) != 0);
+        if supporters == 0 && guarded {
+            score += params.backward;
+        }
+    }
+
+    // The rear pawn of doubled pawns is blocked by its own team.
+    let passed = (opponent & (file_mask(x) | neighbour_files) | own & file_mask(x)) & front == 0;
+    if passed {
+        score += params.passed[rank as usize];
+    }
+    (score, passed)
+}
+
+/// Returns the rank of the square from the view of the team, so the starting rank of its pawns is
+/// `1` and the promotion rank is `7`.
+pub(crate) fn relative_rank(team: PieceColor, y: u8) -> u8 {
+    match team {
+        PieceColor::Light => y,
+        PieceColor::Dark => 7 - y,
+    }
+}
+
+/// Returns the ranks in front of the rank from the view of the team, the nearest one first.
+pub(crate) fn ahead(team: PieceColor, y: u8) -> Box<dyn Iterator<Item = u8>> {
+    match team {
+        PieceColor::Light => Box::new(y + 1..8),
+        PieceColor::Dark => Box::new((0..y).rev()),
+    }
+}
+
+/// Returns the rank directly behind the rank from the view of the team.
+fn behind(team: PieceColor, y: u8) -> Option<u8> {
+    match team {
+        PieceColor::Light => y.checked_sub(1),
+        PieceColor::Dark => Some(y + 1).filter(|y| *y < 8),
+    }
+}
+
+/// Returns the bit of the square in a bitboard.
+pub(crate) fn square_bit(square: Coordinate) -> u64 {
+    1 << (square.get_y() as u64 * 8 + square.get_x() as u64)
+}
+
+/// Returns a bitboard of every square on the file.
+pub(crate) fn file_mask(x: u8) -> u64 {
+    0x0101_0101_0101_0101 << x
+}
+
+/// Returns a bitboard of every square on the rank.
+pub(crate) fn rank_mask(y: u8) -> u64 {
+    0xff << (y as u64 * 8)
+}
+
+/// Returns a bitboard of every square on the files next to the file.
+pub(crate) fn adjacent_files(x: u8) -> u64 {
+    let mut mask = 0;
+    if x > 0 {
+        mask |= file_mask(x - 1);
+    }
+    if x < 7 {
+        mask |= file_mask(x + 1);
+    }
+    mask
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    const PARAMS: EvalParams = EvalParams::DEFAULT;
+
+    /// Returns the score of the light pawn on the square of the board.
+    fn light_pawn(fen: &str, square: (u8, u8)) -> (TaperedScore, bool) {
+        let pawns = Pawns::from_board(&board_from_fen(fen));
+        evaluate_pawn(
+            &pawns,
+            PieceColor::Light,
+            square.into(),
+            &EvalParams::DEFAULT,
+        )
+    }
+
+    #[test]
+    fn test_doubled_and_isolated() {
+        // The pawns on the e-file are doubled and isolated, but the front one is passed.
+        let (rear, passed) = light_pawn("4k3/8/8/8/4P3/8/4P3/4K3 w - - 0 1", (4, 1));
+        assert_eq!(PARAMS.doubled + PARAMS.isolated, rear);
+        assert!(!passed);
+        let (front, passed) = light_pawn("4k3/8/8/8/4P3/8/4P3/4K3 w - - 0 1", (4, 3));
+        assert_eq!(PARAMS.isolated + PARAMS.passed[3], front);
+        assert!(passed);
+    }
+
+    #[test]
+    fn test_connected() {
+        // d4 and e4 stand next to each other and e4 protects f5.
+        let fen = "4k3/p7/8/5P2/3PP3/8/8/4K3 w - - 0 1";
+        let (d4, _) = light_pawn(fen, (3, 3));
+        assert_eq!(PARAMS.connected[3] + PARAMS.passed[3], d4);
+        let (f5, _) = light_pawn(fen, (5, 4));
+        assert_eq!(PARAMS.connected[4] + PARAMS.passed[4], f5);
+    }
+
+    #[test]
+    fn test_backward() {
+        // d3 can't be protected by the pawn on e4 and d4 is guarded by the pawn on c5.
+        let fen = "4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1";
+        let (d3, passed) = light_pawn(fen, (3, 2));
+        assert_eq!(PARAMS.backward, d3);
+        assert!(!passed);
+        // Without the pawn on c5 it is passed instead of backward.
+        let (d3, _) = light_pawn("4k3/8/8/8/4P3/3P4/8/4K3 w - - 0 1", (3, 2));
+        assert_eq!(PARAMS.passed[2], d3);
+    }
+
+    #[test]
+    fn test_evaluate_pawns_is_symmetric() {
+        let entry = evaluate_pawns(
+            &board_from_fen("4k3/pp3p2/8/3p4/3P4/8/PP3P2/4K3 w - - 0 1"),
+            &PARAMS,
+        );
+        assert_eq!(TaperedScore::default(), entry.score);
+        assert_eq!(0, entry.passed);
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_pawns(&Board::default(), &PARAMS).score
+        );
+    }
+
+    #[test]
+    fn test_free_path() {
+        let board = board_from_fen("4k3/8/1P6/8/8/8/8/4K3 w - - 0 1");
+        let entry = evaluate_pawns(&board, &PARAMS);
+        assert!(entry.is_passed((1, 5).into()));
+        assert_eq!(
+            PARAMS.free_path[5],
+            evaluate_free_paths(&board, &entry, &PARAMS)
+        );
+        // A piece in front of the pawn blocks its path.
+        let board = board_from_fen("1n2k3/8/1P6/8/8/8/8/4K3 w - - 0 1");
+        let entry = evaluate_pawns(&board, &PARAMS);
+        assert_eq!(
+            TaperedScore::default(),
+            evaluate_free_paths(&board, &entry, &PARAMS)
+        );
+    }
+
+    #[test]
+    fn test_pawn_hash_table() {
+        let mut table = PawnHashTable::new(100);
+        assert_eq!(128, table.len());
+        let board = board_from_fen("4k3/8/1P6/8/8/8/8/4K3 w - - 0 1");
+        let entry = table.probe(&board, &PARAMS);
+        assert_eq!(0, table.get_hits());
+        // Only the pawns matter for the cache.
+        let other = board_from_fen("r3k3/8/1P6/8/8/8/8/3QK3 b - - 0 1");
+        assert_eq!(entry, table.probe(&other, &PARAMS));
+        assert_eq!(1, table.get_hits());
+        table.clear();
+        assert_eq!(0, table.get_hits());
+    }
+}
@@ -0,0 +1,269 @@
+//! A breakdown of the evaluation into its terms. Explains why the evaluation prefers a board and
+//! helps to see what a change of the evaluation does.
+
+use std::fmt::{self, Display, Formatter};
+
+use ecr_shared::pieces::PieceColor;
+
+use crate::board::Board;
+use crate::eval::endgame::{self, Endgame};
+use crate::eval::params::EvalParams;
+use crate::eval::pawns::{self, PawnEntry, Pawns};
+use crate::eval::{king_safety, mobility, pst, TaperedScore};
+use crate::score::Score;
+
+/// A term of the evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EvalTerm {
+    Material,
+    PieceSquares,
+    /// The pawn structure and the passed pawns with a free path.
+    Pawns,
+    KingSafety,
+    Mobility,
+    /// The threats on the four middle squares.
+    Centre,
+}
+
+impl EvalTerm {
+    /// Every term in the order of the trace.
+    pub const ALL: [EvalTerm; 6] = [
+        EvalTerm::Material,
+        EvalTerm::PieceSquares,
+        EvalTerm::Pawns,
+        EvalTerm::KingSafety,
+        EvalTerm::Mobility,
+        EvalTerm::Centre,
+    ];
+}
+
+impl Display for EvalTerm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EvalTerm::Material => "Material",
+            EvalTerm::PieceSquares => "Piece-square",
+            EvalTerm::Pawns => "Pawns",
+            EvalTerm::KingSafety => "King safety",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::Centre => "Centre",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The score of a term for both teams, each from its own view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermTrace {
+    pub term: EvalTerm,
+    pub light: TaperedScore,
+    pub dark: TaperedScore,
+}
+
+impl TermTrace {
+    /// Returns the score of the team.
+    pub fn get(&self, team: PieceColor) -> TaperedScore {
+        match team {
+            PieceColor::Light => self.light,
+            PieceColor::Dark => self.dark,
+        }
+    }
+
+    /// Returns the score of light minus the score of dark.
+    pub fn get_difference(&self) -> TaperedScore {
+        self.light - self.dark
+    }
+}
+
+/// The evaluation of a board split into its terms. The sum of every term interpolated by the phase
+/// is the score of [`Board::eval`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalTrace {
+    phase: i32,
+    terms: Vec<TermTrace>,
+    endgame: Option<Endgame>,
+}
+
+impl EvalTrace {
+    /// Returns the game phase of the board, see [`Board::get_game_phase`].
+    pub fn get_phase(&self) -> i32 {
+        self.phase
+    }
+
+    /// Returns every term in the order of [`EvalTerm::ALL`].
+    pub fn get_terms(&self) -> &[TermTrace] {
+        &self.terms
+    }
+
+    /// Returns the known endgame that replaces or scales the sum of the terms.
+    pub fn get_endgame(&self) -> Option<Endgame> {
+        self.endgame
+    }
+
+    /// Returns the trace of a single term.
+    pub fn get(&self, term: EvalTerm) -> Option<&TermTrace> {
+        self.terms.iter().find(|trace| trace.term == term)
+    }
+
+    /// Returns the score of a single term from the view of light, interpolated by the phase.
+    pub fn get_term_score(&self, term: EvalTerm) -> Score {
+        self.get(term).map_or(Score::DRAW, |trace| {
+            trace.get_difference().taper(self.phase)
+        })
+    }
+
+    /// Returns the score of the board from the view of light.
+    pub fn get_total(&self) -> Score {
+        let sum = self
+            .terms
+            .iter()
+            .fold(TaperedScore::default(), |sum, trace| {
+                sum + trace.get_difference()
+            });
+        let score = sum.taper(self.phase);
+        self.endgame.map_or(score, |endgame| endgame.apply(score))
+    }
+}
+
+impl Display for EvalTrace {
+    /// Writes a table with the midgame and endgame score of every term for both teams and the
+    /// interpolated difference.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14}{:>14}{:>14}", "", "Light", "Dark")?;
+        writeln!(
+            f,
+            "{:<14}{:>7}{:>7}{:>7}{:>7}{:>9}",
+            "Term", "mg", "eg", "mg", "eg", "Total"
+        )?;
+        for trace in &self.terms {
+            writeln!(
+                f,
+                "{:<14}{:>7}{:>7}{:>7}{:>7}{:>9}",
+                trace.term.to_string(),
+                trace.light.midgame,
+                trace.light.endgame,
+                trace.dark.midgame,
+                trace.dark.endgame,
+                self.get_term_score(trace.term).to_string()
+            )?;
+        }
+        match self.endgame {
+            Some(Endgame::Evaluation(score)) => {
+                writeln!(f, "{:<42}{:>9}", "Known endgame", score.to_string())?
+            }
+            Some(Endgame::Scale(factor)) => {
+                writeln!(f, "Endgame scale {}/{}", factor, endgame::SCALE_NORMAL)?
+            }
+            None => {}
+        }
+        writeln!(
+            f,
+            "{:<42}{:>9}",
+            format!("Phase {}/{}", self.phase, pst::MAX_PHASE),
+            self.get_total().to_string()
+        )
+    }
+}
+
+impl Board {
+    /// Returns the evaluation of the board split into its terms. The total is the same as the one
+    /// of [`Board::eval`].
+    pub fn eval_trace(&self) -> EvalTrace {
+        self.eval_trace_with_params(&EvalParams::DEFAULT)
+    }
+
+    /// Returns the evaluation of the board with the given weights split into its terms.
+    pub fn eval_trace_with_params(&self, params: &EvalParams) -> EvalTrace {
+        let pawns = Pawns::from_board(self);
+        let (light_pawns, light_passed) = pawns::team_pawns(&pawns, PieceColor::Light, params);
+        let (dark_pawns, dark_passed) = pawns::team_pawns(&pawns, PieceColor::Dark, params);
+        let entry = PawnEntry {
+            score: light_pawns - dark_pawns,
+            passed: light_passed | dark_passed,
+        };
+        let terms = EvalTerm::ALL
+            .iter()
+            .map(|term| {
+                let score = |team| match term {
+                    EvalTerm::Material => super::team_material(self, team, params),
+                    EvalTerm::PieceSquares => super::team_piece_squares(self, team, params),
+                    EvalTerm::Pawns => {
+                        let structure = match team {
+                            PieceColor::Light => light_pawns,
+                            PieceColor::Dark => dark_pawns,
+                        };
+                        structure + pawns::team_free_paths(self, &entry, team, params)
+                    }
+                    EvalTerm::KingSafety => king_safety::team_safety(self, &pawns, team, params),
+                    EvalTerm::Mobility => mobility::team_mobility(self, &pawns, team, params),
+                    EvalTerm::Centre => super::middle_squares_score(self, team, params),
+                };
+                TermTrace {
+                    term: *term,
+                    light: score(PieceColor::Light),
+                    dark: score(PieceColor::Dark),
+                }
+            })
+            .collect();
+        EvalTrace {
+            phase: self.get_game_phase_with_params(params),
+            terms,
+            endgame: endgame::probe(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecr_shared::pieces::PieceType;
+
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_total_is_eval() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+            "6k1/5ppp/8/1P6/8/2n5/r4PPP/3R2K1 b - - 0 30",
+            "8/8/4k3/8/2P5/8/5K2/8 w - - 0 60",
+            "8/8/8/3k4/8/8/8/R3K3 w - - 0 1",
+            "2b1k3/8/8/8/8/8/PP6/2B1K3 w - - 0 1",
+        ];
+        for fen in fens.iter() {
+            let board = board_from_fen(fen);
+            assert_eq!(board.eval(), board.eval_trace().get_total(), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_terms() {
+        // Light has an extra knight.
+        let trace = board_from_fen("4k3/8/8/8/8/2N5/8/4K3 w - - 0 1").eval_trace();
+        assert_eq!(EvalTerm::ALL.len(), trace.get_terms().len());
+        let material = trace.get(EvalTerm::Material).unwrap();
+        assert_eq!(
+            EvalParams::DEFAULT.material(PieceType::Knight),
+            material.light
+        );
+        assert_eq!(TaperedScore::default(), material.dark);
+        assert_eq!(1, trace.get_phase());
+        assert!(trace.get_term_score(EvalTerm::Material) > Score::DRAW);
+    }
+
+    #[test]
+    fn test_display() {
+        let trace = board_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").eval_trace();
+        let expected = "                       Light          Dark
+Term               mg     eg     mg     eg    Total
+Material            0      0      0      0     0.00
+Piece-square        8    -28      8    -28     0.00
+Pawns               0      0      0      0     0.00
+King safety      -150      0   -150      0     0.00
+Mobility            0      0      0      0     0.00
+Centre              0      0      0      0     0.00
+Phase 0/24                                     0.00
+";
+        assert_eq!(expected, trace.to_string());
+    }
+}
@@ -0,0 +1,361 @@
+//! Tunes the weights of the evaluation with the method of the Texel engine. The result of a game
+//! is predicted from the evaluation of a quiet position of it with a sigmoid, and every weight is
+//! moved step by step as long as the mean squared error of the predictions of many positions goes
+//! down.
+//!
+//! Every line of a position file holds a FEN followed by the result of the game from the view of
+//! light, either as `1-0`, `0-1` and `1/2-1/2` or as `1.0`, `0.0` and `0.5`. The result may be
+//! wrapped in brackets or quotes, so the common formats `<fen> [0.5]` and
+//! `<fen> c9 "1/2-1/2";` can be read. The move counters of the FEN are optional.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use ecr_formats::fen::{Fen, FenError};
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::eval::params::{EvalParams, ParamsError};
+use crate::search::legal_moves;
+
+/// An error that occurred while reading positions or writing the tuned parameters.
+#[derive(Debug, Error)]
+pub enum TunerError {
+    #[error("line {0} has no game result")]
+    MissingResult(usize),
+
+    #[error("invalid game result {0}")]
+    InvalidResult(String),
+
+    #[error("invalid FEN on line {line}: {source}")]
+    InvalidFen { line: usize, source: FenError },
+
+    #[error("unknown parameter {0}")]
+    UnknownParameter(String),
+
+    #[error("cannot access the file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Params(#[from] ParamsError),
+}
+
+/// A position of a game together with the result of the game.
+#[derive(Debug, Clone)]
+pub struct TuningPosition {
+    board: Board,
+    result: f64,
+}
+
+impl TuningPosition {
+    /// Returns a position with the result from the view of light, which is `1.0` for a win,
+    /// `0.5` for a draw and `0.0` for a loss.
+    pub fn new(board: Board, result: f64) -> TuningPosition {
+        TuningPosition { board, result }
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn get_result(&self) -> f64 {
+        self.result
+    }
+}
+
+/// Reads every position of a position file, see the [module](self) for the format.
+pub fn load_positions<P: AsRef<Path>>(path: P) -> Result<Vec<TuningPosition>, TunerError> {
+    parse_positions(&fs::read_to_string(path)?)
+}
+
+/// Reads every position of the lines. Empty lines and lines starting with `#` are ignored.
+pub fn parse_positions(s: &str) -> Result<Vec<TuningPosition>, TunerError> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with('#'))
+        .map(|(number, line)| parse_position(line, number + 1))
+        .collect()
+}
+
+fn parse_position(line: &str, number: usize) -> Result<TuningPosition, TunerError> {
+    let tokens: Vec<&str> = line
+        .split_whitespace()
+        .map(|token| token.trim_matches(|c| matches!(c, '[' | ']' | '"' | ';')))
+        .collect();
+    if tokens.len() < 5 {
+        return Err(TunerError::MissingResult(number));
+    }
+    let result = parse_result(tokens[tokens.len() - 1])?;
+    let counters = &tokens[4..tokens.len() - 1];
+    let counters = if counters.len() >= 2 && counters[..2].iter().all(|c| c.parse::<u32>().is_ok())
+    {
+        counters[..2].join(" ")
+    } else {
+        String::from("0 1")
+    };
+    let fen = format!("{} {}", tokens[..4].join(" "), counters);
+    let fen = Fen::from_str(&fen).map_err(|source| TunerError::InvalidFen {
+        line: number,
+        source,
+    })?;
+    Ok(TuningPosition::new(fen.into(), result))
+}
+
+fn parse_result(token: &str) -> Result<f64, TunerError> {
+    match token {
+        "1-0" => Ok(1.0),
+        "0-1" => Ok(0.0),
+        "1/2-1/2" => Ok(0.5),
+        _ => token
+            .parse::<f64>()
+            .ok()
+            .filter(|result| (0.0..=1.0).contains(result))
+            .ok_or_else(|| TunerError::InvalidResult(token.to_string())),
+    }
+}
+
+/// Returns true if the team that has to move is not in check and can't capture or promote. The
+/// evaluation of other positions says little about the result, because the next move changes the
+/// material.
+pub fn is_quiet(board: &Board) -> bool {
+    !board.is_in_check(board.to_move)
+        && legal_moves(board)
+            .iter()
+            .all(|m| !m.is_capture() && !m.is_promotion(board))
+}
+
+/// Fits the weights of the evaluation to the results of a set of positions.
+#[derive(Debug, Clone)]
+pub struct Tuner {
+    positions: Vec<TuningPosition>,
+    params: EvalParams,
+    /// The indices of the weights in [`EvalParams::to_vector`] that get tuned.
+    tuned: Vec<usize>,
+    /// Scales the evaluation before it is turned into an expected result.
+    scaling: f64,
+    max_iterations: usize,
+}
+
+impl Tuner {
+    /// Returns a tuner that starts from the given weights and tunes all of them. Positions that
+    /// aren't quiet are dropped.
+    pub fn new(positions: Vec<TuningPosition>, params: EvalParams) -> Tuner {
+        let positions = positions
+            .into_iter()
+            .filter(|position| is_quiet(&position.board))
+            .collect();
+        Tuner {
+            positions,
+            tuned: (0..params.to_vector().len()).collect(),
+            params,
+            scaling: 1.0,
+            max_iterations: 100,
+        }
+    }
+
+    /// Returns the quiet positions the weights are fitted to.
+    pub fn get_positions(&self) -> &[TuningPosition] {
+        &self.positions
+    }
+
+    /// Returns the current weights.
+    pub fn get_params(&self) -> &EvalParams {
+        &self.params
+    }
+
+    /// Only tunes the weights of the parameters with the given names as they appear in the file
+    /// of the weights. All other weights keep their value.
+    pub fn set_parameters(&mut self, names: &[&str]) -> Result<(), TunerError> {
+        let vector_names = EvalParams::vector_names();
+        if let Some(unknown) = names.iter().find(|name| !vector_names.contains(name)) {
+            return Err(TunerError::UnknownParameter(unknown.to_string()));
+        }
+        self.tuned = (0..vector_names.len())
+            .filter(|index| names.contains(&vector_names[*index]))
+            .collect();
+        Ok(())
+    }
+
+    /// Sets how often every weight is changed at most.
+    pub fn set_max_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations;
+    }
+
+    /// Returns the factor the evaluation is scaled with before it is turned into an expected
+    /// result.
+    pub fn get_scaling(&self) -> f64 {
+        self.scaling
+    }
+
+    /// Returns the mean squared error of the expected results with the given weights.
+    pub fn error(&self, params: &EvalParams) -> f64 {
+        self.error_with_scaling(params, self.scaling)
+    }
+
+    fn error_with_scaling(&self, params: &EvalParams, scaling: f64) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .positions
+            .iter()
+            .map(|position| {
+                let score = position.board.eval_with_params(params).get_value() as f64;
+                (position.result - expected_result(score, scaling)).powi(2)
+            })
+            .sum();
+        sum / self.positions.len() as f64
+    }
+
+    /// Finds the scaling that fits the current weights best, so the tuning changes the weights
+    /// as little as possible. Assumes that the error has a single minimum.
+    pub fn fit_scaling(&mut self) -> f64 {
+        let (mut low, mut high) = (0.0, 10.0);
+        for _ in 0..50 {
+            let first = low + (high - low) / 3.0;
+            let second = high - (high - low) / 3.0;
+            if self.error_with_scaling(&self.params, first)
+                < self.error_with_scaling(&self.params, second)
+            {
+                high = second;
+            } else {
+                low = first;
+            }
+        }
+        self.scaling = (low + high) / 2.0;
+        self.scaling
+    }
+
+    /// Changes every tuned weight by one in both directions and keeps the change if it lowers the
+    /// error, until no weight changes anymore or the maximum amount of iterations is reached.
+    /// Returns the error of the tuned weights.
+    pub fn tune(&mut self) -> f64 {
+        let mut values = self.params.to_vector();
+        let mut params = self.params.clone();
+        let mut best_error = self.error(&params);
+        for _ in 0..self.max_iterations {
+            let mut improved = false;
+            for index in self.tuned.iter().copied() {
+                for step in [1, -1].iter() {
+                    values[index] += step;
+                    params.set_vector(&values);
+                    let error = self.error(&params);
+                    if error < best_error {
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                    values[index] -= step;
+                }
+            }
+            params.set_vector(&values);
+            if !improved {
+                break;
+            }
+        }
+        self.params = params;
+        best_error
+    }
+}
+
+/// Returns the expected result of the game from the view of light for the score in centipawns.
+fn expected_result(score: f64, scaling: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-scaling * score / 400.0))
+}
+
+/// Reads the positions, fits the scaling and then tunes every weight starting from the given
+/// ones. The tuned weights are written to the output file and returned.
+pub fn tune_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    positions: P,
+    output: Q,
+    params: EvalParams,
+) -> Result<EvalParams, TunerError> {
+    let mut tuner = Tuner::new(load_positions(positions)?, params);
+    tuner.fit_scaling();
+    tuner.tune();
+    tuner.get_params().save(output)?;
+    Ok(tuner.params)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::TaperedScore;
+
+    use super::*;
+
+    /// Positions where light has an extra rook and wins, and drawn positions with equal
+    /// material.
+    const POSITIONS: &str = "
+# Extra rook
+4k3/pp6/8/8/8/2R5/PP6/4K3 w - - 0 1 [1.0]
+4k3/pp6/8/8/8/5R2/PP6/3K4 b - - 0 1 \"1-0\";
+3k4/pp6/8/8/7R/8/PP6/4K3 w - - c9 \"1-0\";
+4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1 [1/2-1/2]
+";
+
+    #[test]
+    fn test_parse_positions() {
+        let positions = parse_positions(POSITIONS).unwrap();
+        assert_eq!(4, positions.len());
+        assert_eq!(1.0, positions[0].get_result());
+        assert!(!positions[1].get_board().get_light_to_move());
+        assert_eq!(0.5, positions[3].get_result());
+        assert!(matches!(
+            parse_positions("4k3/8/8/8/8/8/8/4K3 w - -"),
+            Err(TunerError::MissingResult(1))
+        ));
+        assert!(matches!(
+            parse_positions("\n4k3/8/8/8/8/8/8/4K3 w - - 0 1 2.0"),
+            Err(TunerError::InvalidResult(_))
+        ));
+        assert!(matches!(
+            parse_positions("4k3/8/8 w - - 0 1 1-0"),
+            Err(TunerError::InvalidFen { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_is_quiet() {
+        assert!(is_quiet(&Board::default()));
+        let positions = parse_positions("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1 0.5").unwrap();
+        assert!(!is_quiet(positions[0].get_board()));
+    }
+
+    #[test]
+    fn test_expected_result() {
+        assert_eq!(0.5, expected_result(0.0, 1.0));
+        assert!((expected_result(400.0, 1.0) - 10.0 / 11.0).abs() < 1e-9);
+        assert!(expected_result(-300.0, 1.0) < 0.5);
+    }
+
+    #[test]
+    fn test_tune() {
+        let mut params = EvalParams::DEFAULT;
+        params.material[3] = TaperedScore::new(0, 0);
+        let mut tuner = Tuner::new(parse_positions(POSITIONS).unwrap(), params);
+        assert_eq!(4, tuner.get_positions().len());
+        assert!(tuner.set_parameters(&["foo"]).is_err());
+        tuner.set_parameters(&["material"]).unwrap();
+        tuner.set_max_iterations(3);
+        let before = tuner.error(tuner.get_params());
+        let after = tuner.tune();
+        assert!(after < before);
+        // A rook wins, so it has to be worth something.
+        assert!(tuner.get_params().material[3].endgame > 0);
+        // Only the material was tuned.
+        assert_eq!(
+            EvalParams::DEFAULT.piece_squares,
+            tuner.get_params().piece_squares
+        );
+    }
+
+    #[test]
+    fn test_fit_scaling() {
+        let mut tuner = Tuner::new(parse_positions(POSITIONS).unwrap(), EvalParams::DEFAULT);
+        let error = tuner.error(&EvalParams::DEFAULT);
+        let scaling = tuner.fit_scaling();
+        assert!(scaling > 0.0);
+        assert!(tuner.error(&EvalParams::DEFAULT) <= error);
+    }
+}
@@ -3,5 +3,7 @@ pub mod eval;
 pub mod r#move;
 pub mod movement;
 pub mod pieces;
+pub mod score;
 pub mod search;
 mod utils;
+pub mod zobrist;
@@ -71,13 +71,10 @@ impl Moves {
 
     /// Removes all illegal moves from the Basic_Moves
     pub fn remove_illegal_moves(&mut self, board: &Board) {
-        let cloned_board = board.clone();
-        for i in 0..self.basic_move.len() {
-            // If the Move is illegal we want to remove it from the vector.
-            if !cloned_board.check_if_legal_move(self.from, &self.basic_move[i]) {
-                self.basic_move.remove(i);
-            }
-        }
+        let from = self.from;
+        // If the Move is illegal we want to remove it from the vector.
+        self.basic_move
+            .retain(|basic_move| board.check_if_legal_move(from, basic_move));
     }
 }
 
@@ -23,7 +23,7 @@ impl MoveProperties {
     /// Find out the properties of a move. Useful for movement.
     fn get_properties(
         basic_move: BasicMove,
-        board: board::Board,
+        board: &board::Board,
         start: Coordinate,
     ) -> MoveProperties {
         // We can safely unwrap here since no move is generated without a piece at the start of it.
@@ -63,7 +63,7 @@ impl board::Board {
     /// This function moves a piece from a given start square to another square, contained in a
     /// BasicMove. Note: This function doesn't complain if a piece by the wrong team is moved.
     pub fn r#move(&mut self, start: Coordinate, basic_move: &BasicMove) {
-        let move_properties = MoveProperties::get_properties(*basic_move, self.clone(), start);
+        let move_properties = MoveProperties::get_properties(*basic_move, self, start);
 
         self.pre_move(start, &move_properties.inner);
 
@@ -91,13 +91,16 @@ impl board::Board {
             );
         }
 
-        if move_properties.capture.is_some() {
-            let mut target = move_properties.capture.unwrap().target;
+        if let Some(capture) = move_properties.capture {
+            let mut target = capture.target;
             if move_properties.en_passant {
                 // We can safely unwrap here since en_passant is only true if  en_passant is possible.
                 target = self.get_en_passant_target().unwrap().actual_square;
             }
-            self.capture_piece(&move_properties.inner, target);
+            // The captured piece is the one on the target square, not the one that moves there.
+            if let Some(captured) = self.get_at(target) {
+                self.capture_piece(&captured, target);
+            }
         }
 
         // The piece has now moved
@@ -225,7 +228,9 @@ impl board::Board {
     fn capture_piece(&mut self, target: &SquareInner, target_square: Coordinate) {
         // TODO: Testing
         target.borrow_mut().set_out_of_game();
-        self.pieces.retain(|inner| inner != target);
+        // The piece list holds its own copies of the pieces, so we have to compare the coordinates.
+        self.pieces
+            .retain(|inner| inner.as_ref().borrow().get_coordinate() != target_square);
         self.remove_piece(target_square);
     }
 
@@ -257,7 +262,7 @@ impl board::Board {
                 .borrow()
                 .get_piece()
                 .get_pseudo_legal_moves(
-                    &self,
+                    self,
                     // These calls seem kinda dumb and i don't know why we need the first deref now but it works fine. If Anyone wants to improve them please do so.
                     &square_inner.deref().borrow().borrow().get_coordinate(),
                     square_inner.deref().borrow().borrow().get_color(),
@@ -286,7 +291,7 @@ impl board::Board {
     }
 
     /// Returns true if the given team has a check
-    fn check_checker(&self, team: PieceColor) -> bool {
+    pub fn check_checker(&self, team: PieceColor) -> bool {
         let all_moves: Vec<Moves> = self.get_pseudo_legal_moves(team);
         for moves in all_moves {
             if moves.contains_check(self) {
@@ -317,18 +322,46 @@ impl board::Board {
 
     /// Returns true if the move is legal, false if it is illegal.
     pub fn check_if_legal_move(&self, start: Coordinate, basic_move: &BasicMove) -> bool {
-        // TODO: Testing
+        // The team that moves is the team of the piece on the starting square.
+        let team = match self.get_at(start) {
+            Some(inner) => inner.as_ref().borrow().get_color(),
+            None => return false,
+        };
         // Clone the current board
         let mut future_board = self.clone();
         // Do the move in the cloned board
-        future_board.r#move(start, &basic_move);
-        // Check if the the king can be captured by the team that can currently move.
-        // We need to invert the result since moves where the opponent does not have check after are legal.
-        !future_board.check_checker(future_board.to_move)
+        future_board.r#move(start, basic_move);
+        // A move is legal if our own king can't be captured afterwards.
+        !future_board.is_in_check(team)
+    }
+
+    /// Returns true if the king of the given team could be captured by the opponent right now.
+    pub fn is_in_check(&self, team: PieceColor) -> bool {
+        for moves in self.get_pseudo_legal_moves(team.get_opponent()) {
+            for basic_move in moves.basic_move {
+                if let Some(capture) = basic_move.capture {
+                    if capture.piece_type == PieceType::King {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Returns the legal moves of a specific team. Pieces without a legal move are not contained.
+    pub fn get_legal_moves(&self, team_color: PieceColor) -> Vec<Moves> {
+        let mut result = self.get_pseudo_legal_moves(team_color);
+        for moves in result.iter_mut() {
+            moves.remove_illegal_moves(self);
+        }
+        result.retain(|moves| !moves.basic_move.is_empty());
+        result
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison, clippy::nonminimal_bool)]
 mod tests {
     use super::*;
 
@@ -441,11 +474,65 @@ mod tests {
             assert_eq!(true, light_check);
         }
 
-        /*#[test]
+        #[test]
+        fn test_capture_removes_target() {
+            let mut board: Board =
+                Board::from(Fen::from_str("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1").unwrap());
+            board.r#move(
+                (4, 3).into(),
+                &BasicMove {
+                    to: (3, 4).into(),
+                    capture: Some(Capture {
+                        piece_type: PieceType::Pawn,
+                        target: (3, 4).into(),
+                    }),
+                },
+            );
+            assert_eq!(3, board.get_pieces().len());
+            assert_eq!(1, board.get_team_pieces(PieceColor::Dark).len());
+            let pawn = board.get_at((3, 4).into()).unwrap();
+            assert_eq!(PieceColor::Light, pawn.deref().borrow().get_color());
+            assert_eq!(2, board.get_team_pieces(PieceColor::Light).len());
+            assert_eq!(
+                "4k3/8/8/3P4/8/8/8/4K3 b - - 0 1".to_string(),
+                Fen::from(board).to_string()
+            );
+        }
+
+        #[test]
         fn test_check_if_legal_move() {
-            let mut default_board = Board::default();
-            todo!()
-        }*/
+            let board: Board =
+                Board::from(Fen::from_str("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1").unwrap());
+            let bishop_move = BasicMove {
+                to: (3, 2).into(),
+                capture: None,
+            };
+            let king_move = BasicMove {
+                to: (3, 0).into(),
+                capture: None,
+            };
+            // The bishop is pinned to its king.
+            assert!(!board.check_if_legal_move((4, 1).into(), &bishop_move));
+            assert!(board.check_if_legal_move((4, 0).into(), &king_move));
+            // The team of the moving piece counts, not the team to move.
+            let board: Board =
+                Board::from(Fen::from_str("4k3/4r3/8/8/8/8/4B3/4K3 b - - 0 1").unwrap());
+            assert!(!board.check_if_legal_move((4, 1).into(), &bishop_move));
+            // The king can't move into check.
+            let board: Board =
+                Board::from(Fen::from_str("4k3/3r4/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+            assert!(!board.check_if_legal_move((4, 0).into(), &king_move));
+            assert!(!board.check_if_legal_move((3, 1).into(), &king_move));
+        }
+
+        #[test]
+        fn test_is_in_check() {
+            let board: Board =
+                Board::from(Fen::from_str("4k3/4r3/8/8/8/8/8/4K3 w - - 0 1").unwrap());
+            assert!(board.is_in_check(PieceColor::Light));
+            assert!(!board.is_in_check(PieceColor::Dark));
+            assert!(!Board::default().is_in_check(PieceColor::Light));
+        }
 
         #[test]
         fn test_count_half_moves() {
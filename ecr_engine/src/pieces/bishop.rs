@@ -22,10 +22,6 @@ impl Piece for Bishop {
     ) -> Vec<BasicMove> {
         diagonal_moves(piece_coordinate, board, piece_color)
     }
-
-    fn get_value(&self) -> u8 {
-        35
-    }
 }
 
 #[cfg(test)]
@@ -22,11 +22,6 @@ impl Piece for King {
     ) -> Vec<BasicMove> {
         king_moves(piece_coordinate, board, piece_color)
     }
-
-    fn get_value(&self) -> u8 {
-        // Doesn't really matter what we put in here since we lose the game when we lose the king.
-        20
-    }
 }
 
 #[cfg(test)]
@@ -22,10 +22,6 @@ impl Piece for Knight {
     ) -> Vec<BasicMove> {
         knight_moves(piece_coordinate, board, piece_color)
     }
-
-    fn get_value(&self) -> u8 {
-        30
-    }
 }
 
 #[cfg(test)]
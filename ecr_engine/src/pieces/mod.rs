@@ -24,7 +24,7 @@ pub mod rook;
 
 /// A [`Piece`] represents a chess figure on the [`Board`](struct@crate::board::Board).
 #[clonable]
-pub trait Piece: Debug + Clone {
+pub trait Piece: Debug + Clone + Send + Sync {
     /// Returns the short code of [`Piece`]'s type according to the algebraic standard.
     fn get_shortcode_algebraic(&self) -> &'static str {
         self.get_type().get_shortcode_algebraic()
@@ -41,11 +41,6 @@ pub trait Piece: Debug + Clone {
         piece_color: PieceColor,
         has_moved: bool,
     ) -> Vec<BasicMove>;
-
-    /// Returns the internal value of the piece.
-    ///
-    /// We use a usize here since we want to value the bishop slightly higher than the knight.
-    fn get_value(&self) -> u8;
 }
 
 impl From<PieceType> for Box<dyn Piece> {
@@ -176,7 +171,6 @@ mod tests {
                 piece_color: PieceColor,
                 has_moved: bool,
                 ) -> Vec<BasicMove>;
-            fn get_value(&self) -> u8;
         }
 
         impl Clone for MockPiece {
@@ -336,6 +330,7 @@ mod tests {
         use super::*;
 
         #[test]
+        #[allow(unused_allocation)]
         fn test_into_box_dyn_piece() {
             // Actually this is `impl From<PieceType> for Box<dyn Piece>`
             assert_eq!(
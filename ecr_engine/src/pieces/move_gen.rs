@@ -256,6 +256,7 @@ fn explore_linear_direction(
 }
 
 /// Used for generating moves for pawns.
+#[allow(clippy::unnecessary_cast, clippy::needless_late_init)]
 pub fn pawn_moves(
     start: &Coordinate,
     board: &board::Board,
@@ -275,7 +276,12 @@ pub fn pawn_moves(
             capture: None,
         });
         // If this is the first move of the pawn and there is not a Piece in the way we can move two squares.
-        if !piece_in_front(start, team_color, board, 2) && !has_moved {
+        // Pawns loaded from a fen never have moved, so we also have to check the starting row.
+        let start_row = match team_color {
+            PieceColor::Light => 1,
+            PieceColor::Dark => 6,
+        };
+        if !has_moved && from_y == start_row && !piece_in_front(start, team_color, board, 2) {
             result.push(BasicMove {
                 to: (from_x, next_row(from_y, team_color, 2)).into(),
                 capture: None,
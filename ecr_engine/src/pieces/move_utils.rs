@@ -1,5 +1,5 @@
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::Arc;
 
 use ecr_shared::coordinate::Coordinate;
 
@@ -16,6 +16,7 @@ macro_rules! check_square {
     ($x: expr, $y: expr, $team_color: expr, $result: expr, $board: expr) => {
         let possible_square =  coordinate_check(&$x, &$y, $team_color, $board);
         // If the square is occupied by a piece
+        #[allow(clippy::unnecessary_unwrap)]
         if possible_square.0.is_some() {
             // Check if it is our own piece.
             if !possible_square.1 {
@@ -39,6 +40,7 @@ macro_rules! check_this_move {
     ($x: expr, $y: expr, $team_color: expr, $result: expr, $board: expr) => {
         let possible_square =  coordinate_check(&$x, &$y , $team_color, $board);
         // If the square is occupied by a piece
+        #[allow(clippy::unnecessary_unwrap)]
         if possible_square.0.is_some(){
             // Check if it is our own piece.
             if !possible_square.1 {
@@ -85,6 +87,7 @@ pub fn distance_to_border(coords: &Coordinate) -> DistanceToBorder {
 /// This function returns the next row of the corresponding team. (If the team_color is white it's
 /// higher, otherwise it's lower). So far there is no check whether the returning row is valid but in
 /// most variants it is impossible since the pawn promotes when reaching the last row.
+#[allow(clippy::unnecessary_cast)]
 pub fn next_row(y: u8, team_color: PieceColor, step: u8) -> u8 {
     let mut result: u8 = y;
     // The next row for a pawn is higher if the piece is light and lower if the pawn is dark.
@@ -97,6 +100,7 @@ pub fn next_row(y: u8, team_color: PieceColor, step: u8) -> u8 {
 }
 
 /// Calculates a square and then just calls square_check()
+#[allow(clippy::unnecessary_cast)]
 pub fn coordinate_check(
     x: &u8,
     y: &u8,
@@ -133,5 +137,5 @@ fn check_square(
 // Returns the Piece a square is occupied by. If the square is not occupied it returns None
 pub(crate) fn piece_on_square(square: Coordinate, board: &board::Board) -> Option<SquareInner> {
     // Get the SquareInner
-    board.get_at(square).map(|i| Rc::clone(&i))
+    board.get_at(square).map(|i| Arc::clone(&i))
 }
@@ -22,10 +22,6 @@ impl Piece for Pawn {
     ) -> Vec<BasicMove> {
         pawn_moves(piece_coordinate, board, piece_color, has_moved)
     }
-
-    fn get_value(&self) -> u8 {
-        10
-    }
 }
 
 #[cfg(test)]
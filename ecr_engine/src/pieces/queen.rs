@@ -25,10 +25,6 @@ impl Piece for Queen {
         result.append(&mut diagonal_moves(piece_coordinate, board, piece_color));
         result
     }
-
-    fn get_value(&self) -> u8 {
-        90
-    }
 }
 
 #[cfg(test)]
@@ -22,10 +22,6 @@ impl Piece for Rook {
     ) -> Vec<BasicMove> {
         linear_moves(piece_coordinate, board, piece_color)
     }
-
-    fn get_value(&self) -> u8 {
-        50
-    }
 }
 
 #[cfg(test)]
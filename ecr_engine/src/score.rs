@@ -0,0 +1,197 @@
+//! The [`Score`] of a board, which is used by the evaluation and the search.
+
+use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// The score of a board in centipawns, so a pawn is worth `100`. Scores near [`Score::MATE`]
+/// encode a mate: `Score::MATE - n` means that the team mates after `n` plies and
+/// `-(Score::MATE - n)` that it gets mated after `n` plies. All arithmetic saturates at
+/// [`Score::INFINITY`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Score(i32);
+
+impl Score {
+    /// A draw or an equal board.
+    pub const DRAW: Score = Score(0);
+    /// The score of a mate on the current board. Mates that are further away are lower.
+    pub const MATE: Score = Score(30_000);
+    /// Larger than every other score.
+    pub const INFINITY: Score = Score(30_001);
+    /// The smallest positive score.
+    pub const UNIT: Score = Score(1);
+    /// The largest score that isn't a mate score. Evaluations are capped at it.
+    pub const MAX_EVAL: Score = Score(Score::MATE.0 - Score::MAX_MATE_PLY - 1);
+
+    /// Every score that is at most this amount of plies away from a mate is a mate score.
+    const MAX_MATE_PLY: i32 = u8::MAX as i32;
+
+    /// Returns a score of the given amount of centipawns. The value is capped at
+    /// [`Score::INFINITY`].
+    pub const fn new(centipawns: i32) -> Score {
+        if centipawns > Score::INFINITY.0 {
+            Score::INFINITY
+        } else if centipawns < -Score::INFINITY.0 {
+            Score(-Score::INFINITY.0)
+        } else {
+            Score(centipawns)
+        }
+    }
+
+    /// Returns the score of mating the opponent after the given amount of plies.
+    pub const fn mate_in(ply: u8) -> Score {
+        Score(Score::MATE.0 - ply as i32)
+    }
+
+    /// Returns the score of getting mated after the given amount of plies.
+    pub const fn mated_in(ply: u8) -> Score {
+        Score(-Score::MATE.0 + ply as i32)
+    }
+
+    /// Returns the value of the score. Is the amount of centipawns unless it is a mate score.
+    pub const fn get_value(self) -> i32 {
+        self.0
+    }
+
+    /// Returns true if the score means that one of the teams gets mated.
+    pub fn is_mate(self) -> bool {
+        self.0.abs() >= Score::MATE.0 - Score::MAX_MATE_PLY && self.0.abs() <= Score::MATE.0
+    }
+
+    /// Returns in how many moves the score is a mate. The distance is positive if the team mates
+    /// and negative if it gets mated. Returns `None` if the score isn't a mate score.
+    pub fn get_mate_distance(self) -> Option<i32> {
+        if !self.is_mate() {
+            return None;
+        }
+        let plies = Score::MATE.0 - self.0.abs();
+        let moves = (plies + 1) / 2;
+        Some(if self.0 > 0 { moves } else { -moves })
+    }
+}
+
+impl Display for Score {
+    /// Formats the score in pawns with two decimals, e.g. `+0.35` or `-1.20`. Mate scores are
+    /// written as the distance to the mate, e.g. `#3` for mating in three moves or `#-3` for
+    /// getting mated in three moves.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.get_mate_distance() {
+            Some(moves) if self.0 < 0 => write!(f, "#-{}", -moves),
+            Some(moves) => write!(f, "#{}", moves),
+            None => {
+                let sign = match self.0 {
+                    0 => "",
+                    value if value > 0 => "+",
+                    _ => "-",
+                };
+                let value = self.0.abs();
+                write!(f, "{}{}.{:02}", sign, value / 100, value % 100)
+            }
+        }
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, rhs: Score) -> Score {
+        Score::new(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Score) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: Score) -> Score {
+        Score::new(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Score) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        // The value is never `i32::MIN`, so this can't overflow.
+        Score(-self.0)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, rhs: i32) -> Score {
+        Score::new(self.0.saturating_mul(rhs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert_eq!(35, Score::new(35).get_value());
+        assert_eq!(Score::INFINITY, Score::new(i32::MAX));
+        assert_eq!(-Score::INFINITY, Score::new(i32::MIN));
+    }
+
+    #[test]
+    fn test_mate() {
+        assert_eq!(Score::MATE, Score::mate_in(0));
+        assert_eq!(-Score::MATE, Score::mated_in(0));
+        assert!(Score::mate_in(3).is_mate());
+        assert!(Score::mated_in(12).is_mate());
+        assert!(!Score::new(900).is_mate());
+        assert!(!Score::INFINITY.is_mate());
+        assert!(!Score::MAX_EVAL.is_mate());
+        assert!((Score::MAX_EVAL + Score::UNIT).is_mate());
+        assert!(Score::mate_in(1) > Score::mate_in(3));
+        assert!(Score::mated_in(1) < Score::mated_in(3));
+    }
+
+    #[test]
+    fn test_get_mate_distance() {
+        assert_eq!(Some(1), Score::mate_in(1).get_mate_distance());
+        assert_eq!(Some(5), Score::mate_in(9).get_mate_distance());
+        assert_eq!(Some(-3), Score::mated_in(6).get_mate_distance());
+        assert_eq!(Some(0), Score::mated_in(0).get_mate_distance());
+        assert_eq!(None, Score::new(35).get_mate_distance());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("+0.35", Score::new(35).to_string());
+        assert_eq!("-1.20", Score::new(-120).to_string());
+        assert_eq!("0.00", Score::DRAW.to_string());
+        assert_eq!("+12.05", Score::new(1205).to_string());
+        assert_eq!("#5", Score::mate_in(9).to_string());
+        assert_eq!("#-3", Score::mated_in(6).to_string());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(Score::new(50), Score::new(20) + Score::new(30));
+        assert_eq!(Score::new(-10), Score::new(20) - Score::new(30));
+        assert_eq!(Score::new(-20), -Score::new(20));
+        assert_eq!(Score::new(60), Score::new(20) * 3);
+        let mut score = Score::new(20);
+        score += Score::new(5);
+        score -= Score::new(10);
+        assert_eq!(Score::new(15), score);
+        // Saturates instead of overflowing.
+        assert_eq!(Score::INFINITY, Score::INFINITY + Score::new(1));
+        assert_eq!(-Score::INFINITY, -Score::INFINITY - Score::MATE);
+        assert_eq!(Score::INFINITY, Score::MATE * i32::MAX);
+    }
+}
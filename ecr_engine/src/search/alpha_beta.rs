@@ -0,0 +1,1462 @@
+//! A negamax search with alpha-beta pruning that collects the principal variation.
+
+use std::cmp::Reverse;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use ecr_shared::coordinate::Coordinate;
+
+use crate::board::Board;
+use crate::eval::evaluator::{ClassicEvaluator, Evaluator};
+use crate::eval::kpk::{self, KpkResult};
+use crate::pieces::{PieceColor, PieceType};
+use crate::score::Score;
+use crate::search::config::SearchConfig;
+use crate::search::observer::{nodes_per_second, SearchEvent, SearchInfo, SearchObserver};
+use crate::search::pv::PrincipalVariation;
+use crate::search::recorder::{NodeOutcome, PruneReason, SearchRecorder};
+use crate::search::tablebase::Tablebases;
+use crate::search::transposition::{Bound, TableEntry, TranspositionTable};
+use crate::search::{legal_moves, pseudo_legal_moves, SearchLimits, SearchMove, Searcher};
+
+/// Counts how often the search had to search a move or the root again with a different window or
+/// depth.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchStatistics {
+    /// How often a move searched with a null window turned out to be inside the window and had to
+    /// be searched again with the full window.
+    pub pvs_re_searches: u64,
+    /// How often a reduced late move turned out to be better than alpha and had to be searched
+    /// again with the full depth.
+    pub lmr_re_searches: u64,
+    /// How often the score at the root was above the aspiration window.
+    pub aspiration_fail_highs: u64,
+    /// How often the score at the root was below the aspiration window.
+    pub aspiration_fail_lows: u64,
+}
+
+/// The result of a search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// The score of the searched board from the view of the team that has to move.
+    pub score: Score,
+    /// The line of moves the search expects to be played. Is empty if there is no legal move.
+    pub pv: PrincipalVariation,
+    /// The depth of the last completed iteration.
+    pub depth: u8,
+    /// The amount of visited nodes over all iterations and lines.
+    pub nodes: u64,
+    /// How often parts of the search had to be repeated.
+    pub statistics: SearchStatistics,
+}
+
+impl SearchResult {
+    /// Returns the best move, which is the first move of the principal variation.
+    pub fn get_best_move(&self) -> Option<SearchMove> {
+        self.pv.first().copied()
+    }
+
+    /// Returns in how many moves the team that has to move mates or gets mated, see
+    /// [`Score::get_mate_distance`].
+    pub fn get_mate_distance(&self) -> Option<i32> {
+        self.score.get_mate_distance()
+    }
+}
+
+/// Holds the state of an alpha-beta search. The boards are evaluated by the [`Evaluator`] `E`.
+#[derive(Debug)]
+pub struct AlphaBeta<E: Evaluator = ClassicEvaluator> {
+    config: SearchConfig,
+    nodes: u64,
+    statistics: SearchStatistics,
+    /// The principal variation of the previous iteration. Its moves are searched first.
+    previous_pv: PrincipalVariation,
+    /// Moves that are skipped at the root because they were already reported as a line of the
+    /// multi pv search.
+    excluded_root_moves: Vec<SearchMove>,
+    /// How often a quiet move from one square to another caused a beta cutoff, weighted by the
+    /// depth. Indexed by the starting and the target square.
+    history: Vec<Vec<u32>>,
+    /// Stores the results of already searched boards. Can be shared with other searches.
+    table: Arc<TranspositionTable>,
+    /// Tells the search to stop as soon as possible. The iteration that is interrupted gets
+    /// discarded, but the first iteration is always finished so there is always a result.
+    stop: Arc<AtomicBool>,
+    /// Is false while the first iteration runs, so the search can't be stopped before it has a
+    /// result.
+    can_stop: bool,
+    /// The search doesn't go deeper than this, even if it was started with a higher depth. Can be
+    /// lowered by another thread while the search runs.
+    depth_limit: Arc<AtomicU8>,
+    /// The depth of the last completed iteration.
+    completed_depth: u8,
+    /// Changes the order of quiet moves slightly. Used by the helper threads of the lazy SMP
+    /// search so they don't all search the same moves at the same time. `0` doesn't change the
+    /// order at all.
+    order_seed: u64,
+    /// The highest ply reached in the current search.
+    seldepth: u8,
+    /// Gets notified about the progress of the search.
+    observer: Option<Box<dyn SearchObserver>>,
+    /// When the current search was started.
+    start: Instant,
+    /// Records the search tree if it is set.
+    recorder: Option<SearchRecorder>,
+    /// How the search of the last node ended. Only used to record the search tree.
+    node_outcome: NodeOutcome,
+    /// The hashes of the boards of the game before the searched board, the oldest one first.
+    game_history: Vec<u64>,
+    /// The hashes of the boards from the root to the current node.
+    path: Vec<u64>,
+    /// The search stops once it visited this many nodes, but only after the first iteration.
+    max_nodes: Option<u64>,
+    evaluator: E,
+    /// Gives the exact result of the boards with few pieces.
+    tablebases: Option<Arc<Tablebases>>,
+}
+
+impl Default for AlphaBeta {
+    fn default() -> Self {
+        AlphaBeta::with_config(SearchConfig::default())
+    }
+}
+
+impl AlphaBeta {
+    pub fn new() -> AlphaBeta {
+        AlphaBeta::default()
+    }
+
+    /// Returns a search that uses the supplied configuration.
+    pub fn with_config(config: SearchConfig) -> AlphaBeta {
+        AlphaBeta::with_table(config, Arc::new(TranspositionTable::default()))
+    }
+
+    /// Returns a search that uses the supplied configuration and transposition table. The table
+    /// can be shared with other searches, even if they run on other threads.
+    pub fn with_table(config: SearchConfig, table: Arc<TranspositionTable>) -> AlphaBeta {
+        AlphaBeta::with_evaluator(config, table, ClassicEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> AlphaBeta<E> {
+    /// Returns a search that uses the supplied configuration, transposition table and evaluator.
+    pub fn with_evaluator(
+        config: SearchConfig,
+        table: Arc<TranspositionTable>,
+        evaluator: E,
+    ) -> AlphaBeta<E> {
+        AlphaBeta {
+            config,
+            nodes: 0,
+            statistics: SearchStatistics::default(),
+            previous_pv: PrincipalVariation::new(),
+            excluded_root_moves: vec![],
+            history: vec![vec![0; 64]; 64],
+            table,
+            stop: Arc::new(AtomicBool::new(false)),
+            can_stop: false,
+            depth_limit: Arc::new(AtomicU8::new(u8::MAX)),
+            completed_depth: 0,
+            order_seed: 0,
+            seldepth: 0,
+            observer: None,
+            start: Instant::now(),
+            recorder: None,
+            node_outcome: NodeOutcome::Open,
+            max_nodes: None,
+            game_history: vec![],
+            path: vec![],
+            evaluator,
+            tablebases: None,
+        }
+    }
+
+    /// Returns the configuration of the search.
+    pub fn get_config(&self) -> &SearchConfig {
+        &self.config
+    }
+
+    /// Returns the evaluator of the search.
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Returns the evaluator of the search, for example to change its weights.
+    pub fn get_evaluator_mut(&mut self) -> &mut E {
+        &mut self.evaluator
+    }
+
+    /// Sets the hashes of the boards that occurred in the game before the board that gets
+    /// searched, the oldest one first. Repeating one of them is scored as a draw.
+    pub fn set_game_history(&mut self, history: Vec<u64>) {
+        self.game_history = history;
+    }
+
+    /// Sets the tablebases that are probed instead of searching the boards they contain. They can
+    /// be shared with other searches.
+    pub fn set_tablebases(&mut self, tablebases: Option<Arc<Tablebases>>) {
+        self.tablebases = tablebases;
+    }
+
+    /// Returns the transposition table of the search.
+    pub fn get_table(&self) -> &Arc<TranspositionTable> {
+        &self.table
+    }
+
+    /// Returns the amount of nodes visited by the last search, even if it got stopped.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Sets the observer that gets notified about the progress of the search. Replaces the
+    /// previous one.
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Removes the observer and returns it.
+    pub fn take_observer(&mut self) -> Option<Box<dyn SearchObserver>> {
+        self.observer.take()
+    }
+
+    /// Sets the recorder that records the search tree. Only the last search of the root is kept,
+    /// which is the one that decided the result. Recording slows the search down a lot.
+    pub fn set_recorder(&mut self, recorder: SearchRecorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Returns the recorder with the tree of the last search.
+    pub fn get_recorder(&self) -> Option<&SearchRecorder> {
+        self.recorder.as_ref()
+    }
+
+    /// Removes the recorder and returns it.
+    pub fn take_recorder(&mut self) -> Option<SearchRecorder> {
+        self.recorder.take()
+    }
+
+    fn notify(&mut self, event: SearchEvent) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.notify(&event);
+        }
+    }
+
+    /// Sets the flag that stops the search once it is set to true.
+    pub(crate) fn set_stop_flag(&mut self, stop: Arc<AtomicBool>) {
+        self.stop = stop;
+    }
+
+    /// Sets the depth the search stops at once it is lower than the depth the search was started
+    /// with. An iteration deeper than the limit that is already running gets discarded.
+    pub(crate) fn set_depth_limit(&mut self, depth_limit: Arc<AtomicU8>) {
+        self.depth_limit = depth_limit;
+    }
+
+    /// Sets the seed that changes the order of quiet moves.
+    pub(crate) fn set_order_seed(&mut self, seed: u64) {
+        self.order_seed = seed;
+    }
+
+    /// Returns true if the search was told to stop, reached the node limit or the depth limit and
+    /// is allowed to stop.
+    fn is_stopped(&self) -> bool {
+        self.can_stop
+            && (self.stop.load(Ordering::Relaxed)
+                || self.completed_depth >= self.depth_limit.load(Ordering::Relaxed)
+                || self
+                    .max_nodes
+                    .is_some_and(|max_nodes| self.nodes >= max_nodes))
+    }
+
+    /// Searches the board with iterative deepening up to the given depth.
+    pub fn search(&mut self, board: &Board, depth: u8) -> SearchResult {
+        // There is always at least one line, even if there is no legal move.
+        self.search_multi_pv(board, depth, 1).remove(0)
+    }
+
+    /// Returns the best `lines` root moves of the tablebases with their exact scores. The results
+    /// have a depth of `0`, since nothing was searched.
+    fn probe_root(&self, board: &Board, lines: usize) -> Option<Vec<SearchResult>> {
+        let moves = self.tablebases.as_ref()?.probe_root_moves(board)?;
+        // The search scores the mates and stalemates itself.
+        if moves.is_empty() {
+            return None;
+        }
+        let results = moves
+            .into_iter()
+            .take(lines.max(1))
+            .map(|(m, result)| SearchResult {
+                score: result.to_score(0),
+                pv: PrincipalVariation::from_child(m, &PrincipalVariation::new()),
+                depth: 0,
+                nodes: 0,
+                statistics: SearchStatistics::default(),
+            })
+            .collect();
+        Some(results)
+    }
+
+    /// Searches the board with iterative deepening up to the given depth and returns the best
+    /// `lines` root moves, each with its own score and principal variation, sorted from the best
+    /// to the worst. Every line after the first one is found by searching the root again without
+    /// the moves that were already reported. Returns less lines if there are less legal moves. If
+    /// the tablebases contain every board after the moves of the root, their moves are returned
+    /// without a search.
+    pub fn search_multi_pv(&mut self, board: &Board, depth: u8, lines: usize) -> Vec<SearchResult> {
+        self.nodes = 0;
+        self.seldepth = 0;
+        self.start = Instant::now();
+        self.statistics = SearchStatistics::default();
+        if let Some(results) = self.probe_root(board, lines) {
+            return results;
+        }
+        self.history = vec![vec![0; 64]; 64];
+        self.can_stop = false;
+        self.completed_depth = 0;
+        let mut results: Vec<SearchResult> = vec![];
+        for current_depth in 1..=depth.max(1) {
+            self.can_stop = !results.is_empty();
+            if self.is_stopped() {
+                break;
+            }
+            let mut current_results = vec![];
+            self.excluded_root_moves.clear();
+            for line in 0..lines.max(1) {
+                let previous = results.get(line);
+                self.previous_pv = previous.map(|r| r.pv.clone()).unwrap_or_default();
+                let (score, pv) = match previous {
+                    Some(previous) if self.config.aspiration_windows => {
+                        self.aspiration_search(board, current_depth, previous.score)
+                    }
+                    _ => {
+                        let mut pv = PrincipalVariation::new();
+                        let score = self.negamax(
+                            board,
+                            current_depth,
+                            0,
+                            -Score::INFINITY,
+                            Score::INFINITY,
+                            false,
+                            &mut pv,
+                        );
+                        (score, pv)
+                    }
+                };
+                if self.is_stopped() {
+                    break;
+                }
+                // Every legal move has already been reported.
+                if pv.is_empty() && line > 0 {
+                    break;
+                }
+                if self.observer.is_some() {
+                    let time = self.start.elapsed();
+                    let info = SearchInfo {
+                        depth: current_depth,
+                        seldepth: self.seldepth,
+                        nodes: self.nodes,
+                        nps: nodes_per_second(self.nodes, time),
+                        hashfull: self.table.get_hashfull(),
+                        time,
+                        multi_pv: line + 1,
+                        score,
+                        pv: pv.clone(),
+                    };
+                    self.notify(SearchEvent::Iteration(info));
+                }
+                let best_move = pv.first().copied();
+                current_results.push(SearchResult {
+                    score,
+                    pv,
+                    depth: current_depth,
+                    nodes: 0,
+                    statistics: SearchStatistics::default(),
+                });
+                match best_move {
+                    Some(best_move) => self.excluded_root_moves.push(best_move),
+                    // There is no legal move at all.
+                    None => break,
+                }
+            }
+            if self.is_stopped() {
+                break;
+            }
+            current_results.sort_by_key(|result| Reverse(result.score));
+            results = current_results;
+            self.completed_depth = current_depth;
+        }
+        for result in results.iter_mut() {
+            result.nodes = self.nodes;
+            result.statistics = self.statistics.clone();
+        }
+        results
+    }
+
+    /// Searches the root with a small window around the score of the previous iteration. The
+    /// window gets wider every time the score is outside of it.
+    fn aspiration_search(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        previous_score: Score,
+    ) -> (Score, PrincipalVariation) {
+        let mut window = self.config.aspiration_window;
+        let mut alpha = previous_score - window;
+        let mut beta = previous_score + window;
+        // Mate scores change with every iteration, so a window around them is useless.
+        if previous_score.is_mate() {
+            alpha = -Score::INFINITY;
+            beta = Score::INFINITY;
+        }
+        loop {
+            let mut pv = PrincipalVariation::new();
+            let score = self.negamax(board, depth, 0, alpha, beta, false, &mut pv);
+            if self.is_stopped() {
+                return (score, pv);
+            }
+            if score <= alpha && alpha > -Score::INFINITY {
+                self.statistics.aspiration_fail_lows += 1;
+                window = window * 2;
+                alpha = (previous_score - window).max(-Score::INFINITY);
+            } else if score >= beta && beta < Score::INFINITY {
+                self.statistics.aspiration_fail_highs += 1;
+                window = window * 2;
+                beta = (previous_score + window).min(Score::INFINITY);
+            } else {
+                return (score, pv);
+            }
+        }
+    }
+
+    /// Returns the score of the board from the view of the team that has to move. The principal
+    /// variation of the board gets written into `pv`. `allow_null` is false directly after a null
+    /// move so two null moves can't follow each other.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        ply: u8,
+        alpha: Score,
+        beta: Score,
+        allow_null: bool,
+        pv: &mut PrincipalVariation,
+    ) -> Score {
+        let recording = match &mut self.recorder {
+            Some(recorder) if recorder.records(ply) => {
+                recorder.enter(ply, depth, alpha, beta);
+                true
+            }
+            _ => false,
+        };
+        self.path.push(board.get_zobrist_hash());
+        if ply == 0 {
+            self.evaluator.reset(board);
+        } else {
+            self.evaluator.make(board);
+        }
+        let score = self.search_node(board, depth, ply, alpha, beta, allow_null, pv);
+        if ply > 0 {
+            self.evaluator.unmake();
+        }
+        self.path.pop();
+        if recording {
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.exit(score, self.node_outcome);
+            }
+        }
+        score
+    }
+
+    /// Searches the board that is reached with the move. Only differs from [`Self::negamax`] by
+    /// telling the recorder which move was done.
+    #[allow(clippy::too_many_arguments)]
+    fn search_child(
+        &mut self,
+        m: SearchMove,
+        child: &Board,
+        depth: u8,
+        ply: u8,
+        alpha: Score,
+        beta: Score,
+        pv: &mut PrincipalVariation,
+    ) -> Score {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.set_next_move(Some(m));
+        }
+        self.negamax(child, depth, ply, alpha, beta, true, pv)
+    }
+
+    /// Adds a move that was skipped to the recorded tree.
+    fn record_skip(
+        &mut self,
+        m: SearchMove,
+        depth: u8,
+        alpha: Score,
+        beta: Score,
+        reason: PruneReason,
+    ) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.skip(m, depth, alpha, beta, reason);
+        }
+    }
+
+    /// Does the actual work of [`Self::negamax`]. Sets `node_outcome` before returning.
+    #[allow(clippy::too_many_arguments)]
+    fn search_node(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        ply: u8,
+        mut alpha: Score,
+        beta: Score,
+        allow_null: bool,
+        pv: &mut PrincipalVariation,
+    ) -> Score {
+        self.nodes += 1;
+        self.seldepth = self.seldepth.max(ply);
+        // The hash was pushed by `negamax`.
+        let hash = self.path.last().copied().unwrap_or_default();
+        // The root is always searched, otherwise there would be no move to play.
+        if ply > 0 && self.is_draw(board, hash) {
+            self.node_outcome = NodeOutcome::Draw;
+            return Score::DRAW;
+        }
+        // Drawn pawn endings are known exactly, no matter what the evaluator thinks of them.
+        if ply > 0 && kpk::probe(board) == Some(KpkResult::Draw) {
+            self.node_outcome = NodeOutcome::Draw;
+            return Score::DRAW;
+        }
+        if ply > 0 {
+            if let Some(result) = self.tablebases.as_ref().and_then(|t| t.probe(board)) {
+                self.node_outcome = NodeOutcome::Tablebase;
+                return result.to_score(ply);
+            }
+        }
+        if depth == 0 {
+            self.node_outcome = NodeOutcome::Leaf;
+            return self.evaluate(board);
+        }
+        // The result doesn't matter anymore.
+        if self.is_stopped() {
+            self.node_outcome = NodeOutcome::Stopped;
+            return Score::DRAW;
+        }
+
+        // Scores are whole numbers, so every window that is wider than one is a pv window.
+        let pv_node = beta - alpha > Score::UNIT;
+        let original_alpha = alpha;
+
+        let mut table_move = None;
+        if let Some(entry) = self.table.probe(hash) {
+            table_move = entry.best_move;
+            // The root and pv nodes are always searched so we get a complete principal variation.
+            if ply > 0 && !pv_node && entry.depth >= depth {
+                let score = score_from_table(entry.score, ply);
+                self.node_outcome = NodeOutcome::TableHit;
+                match entry.bound {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+        let in_check = board.is_in_check(board.to_move);
+        let static_eval = self.evaluate(board);
+
+        if !pv_node && !in_check {
+            // Reverse futility pruning: We are so far ahead that the opponent won't get back
+            // into the game in the remaining depth.
+            if self.config.reverse_futility_pruning
+                && depth <= self.config.reverse_futility_max_depth
+                && static_eval - self.config.reverse_futility_margin * depth as i32 >= beta
+            {
+                self.node_outcome = NodeOutcome::Pruned(PruneReason::ReverseFutility);
+                return static_eval;
+            }
+
+            // Null move pruning: If we are still above beta after giving the opponent a free
+            // move, a real move will most likely be above beta as well. This is wrong in
+            // zugzwang, which mostly happens when only the king and pawns are left.
+            if self.config.null_move_pruning
+                && allow_null
+                && depth > self.config.null_move_reduction
+                && static_eval >= beta
+                && has_non_pawn_material(board, board.to_move)
+            {
+                let mut null_board = board.clone();
+                null_board.null_move();
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.set_next_move(None);
+                }
+                let score = -self.negamax(
+                    &null_board,
+                    depth - 1 - self.config.null_move_reduction,
+                    ply + 1,
+                    -beta,
+                    -beta + Score::UNIT,
+                    false,
+                    &mut PrincipalVariation::new(),
+                );
+                if score >= beta {
+                    // Mate scores from a null move search are not proven, so we don't return them.
+                    self.node_outcome = NodeOutcome::Pruned(PruneReason::NullMove);
+                    return beta;
+                }
+            }
+        }
+
+        // Futility pruning: Quiet moves can't raise the score enough to reach alpha.
+        let futility_pruning = self.config.futility_pruning
+            && !pv_node
+            && !in_check
+            && depth <= self.config.futility_max_depth
+            && static_eval + self.config.futility_margin * depth as i32 <= alpha;
+
+        let mut moves = pseudo_legal_moves(board);
+        self.order_moves(&mut moves, ply, table_move);
+
+        let mut best_score = -Score::INFINITY;
+        let mut best_move = None;
+        let mut legal_moves = 0;
+        for m in moves {
+            let child = m.apply(board);
+            // Moves that leave our own king in check are illegal.
+            if child.is_in_check(board.to_move) {
+                continue;
+            }
+            legal_moves += 1;
+            if ply == 0 {
+                if self.excluded_root_moves.contains(&m) {
+                    self.record_skip(m, depth - 1, alpha, beta, PruneReason::Excluded);
+                    continue;
+                }
+                self.notify(SearchEvent::CurrentMove {
+                    depth,
+                    current_move: m,
+                    number: legal_moves,
+                });
+            }
+
+            let quiet = !m.is_capture() && !m.is_promotion(board);
+            let late_move = self.config.late_move_reductions
+                && depth >= self.config.late_move_min_depth
+                && legal_moves > self.config.late_move_full_depth_moves;
+            // Checks are never pruned or reduced, so we have to find out whether the move
+            // gives one, but only if the result matters.
+            let gives_check = quiet
+                && !in_check
+                && (futility_pruning || late_move)
+                && child.is_in_check(child.to_move);
+
+            if futility_pruning && quiet && !gives_check && legal_moves > 1 {
+                self.record_skip(m, depth - 1, alpha, beta, PruneReason::Futility);
+                continue;
+            }
+
+            let mut reduction = 0;
+            if late_move && quiet && !in_check && !gives_check {
+                reduction = self.late_move_reduction(&m, depth, legal_moves);
+            }
+
+            let mut child_pv = PrincipalVariation::new();
+            let mut score;
+            if self.config.principal_variation_search && legal_moves > 1 {
+                // We expect the first move to be the best one, so every other move only has to
+                // be proven to be worse than alpha. This can be done with a null window.
+                score = -self.search_child(
+                    m,
+                    &child,
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -alpha - Score::UNIT,
+                    -alpha,
+                    &mut child_pv,
+                );
+                if reduction > 0 && score > alpha {
+                    self.statistics.lmr_re_searches += 1;
+                    child_pv = PrincipalVariation::new();
+                    score = -self.search_child(
+                        m,
+                        &child,
+                        depth - 1,
+                        ply + 1,
+                        -alpha - Score::UNIT,
+                        -alpha,
+                        &mut child_pv,
+                    );
+                }
+                // The move is better than expected, so we need its exact score.
+                if score > alpha && score < beta {
+                    self.statistics.pvs_re_searches += 1;
+                    child_pv = PrincipalVariation::new();
+                    score = -self.search_child(
+                        m,
+                        &child,
+                        depth - 1,
+                        ply + 1,
+                        -beta,
+                        -alpha,
+                        &mut child_pv,
+                    );
+                }
+            } else {
+                score = -self.search_child(
+                    m,
+                    &child,
+                    depth - 1 - reduction,
+                    ply + 1,
+                    -beta,
+                    -alpha,
+                    &mut child_pv,
+                );
+                // The reduced search says the move is better than expected, so we have to
+                // verify that with the full depth.
+                if reduction > 0 && score > alpha {
+                    self.statistics.lmr_re_searches += 1;
+                    child_pv = PrincipalVariation::new();
+                    score = -self.search_child(
+                        m,
+                        &child,
+                        depth - 1,
+                        ply + 1,
+                        -beta,
+                        -alpha,
+                        &mut child_pv,
+                    );
+                }
+            }
+
+            if self.is_stopped() {
+                self.node_outcome = NodeOutcome::Stopped;
+                return Score::DRAW;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(m);
+                if score > alpha {
+                    alpha = score;
+                    *pv = PrincipalVariation::from_child(m, &child_pv);
+                    if alpha >= beta {
+                        if quiet {
+                            self.history[square_index(m.from)][square_index(m.get_to())] +=
+                                depth as u32 * depth as u32;
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        if legal_moves == 0 {
+            best_score = if in_check {
+                Score::mated_in(ply)
+            } else {
+                // Stalemate
+                Score::DRAW
+            };
+        } else if best_move.is_none() {
+            // Every move was pruned or excluded, so we know nothing about the board.
+            self.node_outcome = NodeOutcome::Pruned(if ply == 0 {
+                PruneReason::Excluded
+            } else {
+                PruneReason::Futility
+            });
+            return best_score;
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.node_outcome = match bound {
+            _ if legal_moves == 0 && in_check => NodeOutcome::Checkmate,
+            _ if legal_moves == 0 => NodeOutcome::Stalemate,
+            Bound::Exact => NodeOutcome::Exact,
+            Bound::Lower => NodeOutcome::Cutoff,
+            Bound::Upper => NodeOutcome::FailLow,
+        };
+        // The root isn't stored since its moves might be excluded by the multi pv search.
+        if ply > 0 {
+            self.table.store(
+                hash,
+                TableEntry {
+                    score: score_to_table(best_score, ply),
+                    depth,
+                    bound,
+                    best_move: best_move.map(|m| (m.from, m.get_to())),
+                },
+            );
+        }
+        best_score
+    }
+
+    /// Returns the evaluation of the board from the view of the team that has to move. It is
+    /// capped at [`Score::MAX_EVAL`], so it can't be mistaken for a mate.
+    fn evaluate(&mut self, board: &Board) -> Score {
+        let score = self
+            .evaluator
+            .evaluate(board)
+            .clamp(-Score::MAX_EVAL, Score::MAX_EVAL);
+        if board.get_light_to_move() {
+            score
+        } else {
+            -score
+        }
+    }
+
+    /// Returns true if the board is a draw by the fifty-move rule or because it repeats a board
+    /// of the current line or of the game.
+    fn is_draw(&self, board: &Board, hash: u64) -> bool {
+        let half_moves = board.get_half_move_amount() as usize;
+        // A mate on the last move still counts.
+        if half_moves >= 100
+            && (!board.is_in_check(board.to_move) || !legal_moves(board).is_empty())
+        {
+            return true;
+        }
+        // Boards can only repeat since the last capture or pawn move, and only every second
+        // ply when the same team has to move.
+        self.path
+            .iter()
+            .rev()
+            .skip(1)
+            .chain(self.game_history.iter().rev())
+            .take(half_moves)
+            .skip(1)
+            .step_by(2)
+            .any(|earlier| *earlier == hash)
+    }
+
+    /// Returns by how much a late move gets reduced. Moves that are sorted later get reduced more,
+    /// moves that often caused a cutoff get reduced less. At least one depth remains.
+    fn late_move_reduction(&self, m: &SearchMove, depth: u8, move_index: usize) -> u8 {
+        let mut reduction = 1;
+        if move_index > self.config.late_move_full_depth_moves * 2 {
+            reduction += 1;
+        }
+        let history = self.history[square_index(m.from)][square_index(m.get_to())];
+        if history > depth as u32 * depth as u32 {
+            reduction -= 1;
+        }
+        reduction.min(depth.saturating_sub(2))
+    }
+
+    /// Sorts the moves so that the most promising ones are searched first: The move of the
+    /// previous principal variation, then the move from the transposition table, then captures of
+    /// valuable pieces and then quiet moves by their history.
+    fn order_moves(
+        &self,
+        moves: &mut [SearchMove],
+        ply: u8,
+        table_move: Option<(Coordinate, Coordinate)>,
+    ) {
+        let pv_move = self.previous_pv.get_moves().get(ply as usize).copied();
+        moves.sort_by_key(|m| {
+            if Some(*m) == pv_move {
+                return i64::MIN;
+            }
+            if Some((m.from, m.get_to())) == table_move {
+                return i64::MIN + 1;
+            }
+            match m.basic_move.capture {
+                Some(capture) => {
+                    let value = self.evaluator.piece_value(capture.piece_type);
+                    i64::MIN / 2 - value.midgame as i64
+                }
+                None => {
+                    let from = square_index(m.from);
+                    let to = square_index(m.get_to());
+                    // The history decides first, the seed only shuffles moves with the same
+                    // history.
+                    -(self.history[from][to] as i64 * 64 + self.order_noise(from * 64 + to))
+                }
+            }
+        });
+    }
+
+    /// Returns a number from `0` to `63` that is the same for every move as long as the seed is
+    /// the same.
+    fn order_noise(&self, move_index: usize) -> i64 {
+        if self.order_seed == 0 {
+            return 0;
+        }
+        let mixed = (move_index as u64 ^ self.order_seed).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (mixed >> 58) as i64
+    }
+}
+
+impl<E: Evaluator> Searcher for AlphaBeta<E> {
+    /// Searches with iterative deepening up to the depth of the limits. If the node limit is
+    /// reached, the result of the last completed iteration is returned.
+    fn search_with_limits(&mut self, board: &Board, limits: SearchLimits) -> SearchResult {
+        self.max_nodes = limits.nodes;
+        let result = self.search(board, limits.depth);
+        self.max_nodes = None;
+        result
+    }
+}
+
+/// Searches the given board up to the given depth.
+pub fn search(board: &Board, depth: u8) -> SearchResult {
+    AlphaBeta::new().search(board, depth)
+}
+
+/// Mate scores are stored relative to the board instead of the root, so they stay correct when
+/// the board is reached at a different ply.
+fn score_to_table(score: Score, ply: u8) -> Score {
+    if score.is_mate() {
+        score + Score::new(score.get_value().signum() * ply as i32)
+    } else {
+        score
+    }
+}
+
+/// Reverses [`score_to_table`].
+fn score_from_table(score: Score, ply: u8) -> Score {
+    if score.is_mate() {
+        score - Score::new(score.get_value().signum() * ply as i32)
+    } else {
+        score
+    }
+}
+
+/// Returns true if the team has any piece besides the king and pawns.
+fn has_non_pawn_material(board: &Board, team: PieceColor) -> bool {
+    board.get_team_pieces(team).iter().any(|piece| {
+        let piece_type = piece.borrow().get_piece().get_type();
+        piece_type != PieceType::Pawn && piece_type != PieceType::King
+    })
+}
+
+/// Returns the index of a square from `0` to `63`.
+fn square_index(square: Coordinate) -> usize {
+    square.get_x() as usize * 8 + square.get_y() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use crate::eval::evaluator::MaterialEvaluator;
+    use crate::eval::params::EvalParams;
+    use crate::eval::TaperedScore;
+    use crate::pieces::move_gen::BasicMove;
+    use crate::search::tablebase::TablebaseResult;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_search_mate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let result = search(&board, 2);
+        assert_eq!(Score::mate_in(1), result.score);
+        assert_eq!(vec!["h1h8"], result.pv.to_uci(&board));
+        assert_eq!(vec!["Rh8#"], result.pv.to_san(&board));
+        assert_eq!(2, result.depth);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn test_search_pv() {
+        // Light can capture the queen.
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let result = search(&board, 3);
+        assert_eq!(3, result.pv.len());
+        assert_eq!("b1c3", result.get_best_move().unwrap().to_string());
+        assert_eq!("Nxc3", result.pv.to_san(&board)[0]);
+    }
+
+    #[test]
+    fn test_order_captures_by_material() {
+        // The queen can capture a knight or a rook.
+        let board = board_from_fen("4k3/8/8/2n1r3/3Q4/8/8/K7 w - - 0 1");
+        let first_capture = |search: &AlphaBeta| {
+            let mut moves = legal_moves(&board);
+            search.order_moves(&mut moves, 0, None);
+            moves[0].get_to().to_string()
+        };
+        assert_eq!("e5", first_capture(&AlphaBeta::new()));
+        // The captures follow the material of the evaluator.
+        let mut params = EvalParams::DEFAULT;
+        params.material[1] = TaperedScore::new(600, 600);
+        let search = AlphaBeta::with_evaluator(
+            SearchConfig::default(),
+            Arc::new(TranspositionTable::default()),
+            ClassicEvaluator::with_params(params),
+        );
+        assert_eq!("c5", first_capture(&search));
+    }
+
+    /// Counts how deep the search is and checks that every board it evaluates was entered.
+    #[derive(Debug, Default)]
+    struct DepthEvaluator {
+        boards: Vec<u64>,
+        max_depth: usize,
+    }
+
+    impl Evaluator for DepthEvaluator {
+        fn evaluate(&mut self, board: &Board) -> Score {
+            assert_eq!(self.boards.last(), Some(&board.get_zobrist_hash()));
+            Score::DRAW
+        }
+
+        fn reset(&mut self, board: &Board) {
+            self.boards = vec![board.get_zobrist_hash()];
+        }
+
+        fn make(&mut self, board: &Board) {
+            self.boards.push(board.get_zobrist_hash());
+            self.max_depth = self.max_depth.max(self.boards.len());
+        }
+
+        fn unmake(&mut self) {
+            self.boards.pop();
+        }
+    }
+
+    #[test]
+    fn test_search_with_evaluator() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let table = Arc::new(TranspositionTable::default());
+        let mut search =
+            AlphaBeta::with_evaluator(SearchConfig::default(), table, MaterialEvaluator);
+        assert_eq!(
+            "b1c3",
+            search
+                .search(&board, 3)
+                .get_best_move()
+                .unwrap()
+                .to_string()
+        );
+
+        let table = Arc::new(TranspositionTable::default());
+        let mut search =
+            AlphaBeta::with_evaluator(SearchConfig::none(), table, DepthEvaluator::default());
+        search.search(&board, 3);
+        assert_eq!(1, search.get_evaluator().boards.len());
+        assert_eq!(4, search.get_evaluator().max_depth);
+    }
+
+    /// Scores every board as far better for light than any real evaluation.
+    #[derive(Debug)]
+    struct HugeEvaluator;
+
+    impl Evaluator for HugeEvaluator {
+        fn evaluate(&mut self, _board: &Board) -> Score {
+            Score::INFINITY
+        }
+    }
+
+    #[test]
+    fn test_search_caps_evaluation() {
+        let board = board_from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        let table = Arc::new(TranspositionTable::default());
+        let mut search = AlphaBeta::with_evaluator(SearchConfig::default(), table, HugeEvaluator);
+        let result = search.search(&board, 3);
+        assert_eq!(Score::MAX_EVAL, result.score);
+        assert!(!result.score.is_mate());
+    }
+
+    #[test]
+    fn test_search_with_tablebases() {
+        let mut tablebases = Tablebases::new();
+        tablebases.generate("KQvK".parse().unwrap()).unwrap();
+        let tablebases = Arc::new(tablebases);
+        // The mate is too deep for the search alone.
+        let board = board_from_fen("8/8/8/3k4/8/8/8/KQ6 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        search.set_tablebases(Some(Arc::clone(&tablebases)));
+        let result = search.search(&board, 2);
+        let (_, expected) = tablebases.probe_root(&board).unwrap();
+        assert!(matches!(expected, TablebaseResult::Win(plies) if plies > 4));
+        assert_eq!(expected.to_score(0), result.score);
+        let best = result.get_best_move().unwrap();
+        assert_eq!(
+            Some(expected),
+            tablebases
+                .probe(&best.apply(&board))
+                .map(|result| result.before_move())
+        );
+    }
+
+    #[test]
+    fn test_search_probes_root() {
+        let mut tablebases = Tablebases::new();
+        tablebases.generate("KQvK".parse().unwrap()).unwrap();
+        let tablebases = Arc::new(tablebases);
+        let board = board_from_fen("8/8/8/3k4/8/8/8/KQ6 w - - 0 1");
+        let (expected_move, expected) = tablebases.probe_root(&board).unwrap();
+        let mut search = AlphaBeta::new();
+        search.set_tablebases(Some(Arc::clone(&tablebases)));
+        let result = search.search_with_limits(&board, SearchLimits::depth(6));
+        assert_eq!(0, result.nodes);
+        assert_eq!(expected.to_score(0), result.score);
+        assert_eq!(Some(expected_move), result.get_best_move());
+        assert_eq!(1, result.pv.len());
+        assert_eq!(0, result.depth);
+
+        // Every line is ranked by the tablebases.
+        let results = search.search_multi_pv(&board, 6, 3);
+        assert_eq!(3, results.len());
+        assert_eq!(Some(expected_move), results[0].get_best_move());
+        for (first, second) in results.iter().zip(results.iter().skip(1)) {
+            assert!(first.score >= second.score);
+            assert_ne!(first.get_best_move(), second.get_best_move());
+        }
+        for result in results.iter() {
+            assert_eq!(0, result.nodes);
+            assert_eq!(0, result.depth);
+            let after = result.get_best_move().unwrap().apply(&board);
+            assert_eq!(
+                result.score,
+                tablebases.probe(&after).unwrap().before_move().to_score(0)
+            );
+        }
+
+        // Boards that aren't in the tablebases are still searched.
+        let board = Board::default();
+        assert_ne!(0, search.search(&board, 1).nodes);
+    }
+
+    #[test]
+    fn test_search_kpk_draw() {
+        // Every move draws, even though the evaluator only sees the extra pawn.
+        let board = board_from_fen("4k3/8/4P3/4K3/8/8/8/8 w - - 0 1");
+        let table = Arc::new(TranspositionTable::default());
+        let mut search =
+            AlphaBeta::with_evaluator(SearchConfig::default(), table, MaterialEvaluator);
+        assert_eq!(Score::DRAW, search.search(&board, 3).score);
+    }
+
+    #[test]
+    fn test_search_config() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let configs = vec![
+            SearchConfig::none(),
+            SearchConfig {
+                null_move_pruning: true,
+                ..SearchConfig::none()
+            },
+            SearchConfig {
+                late_move_reductions: true,
+                ..SearchConfig::none()
+            },
+            SearchConfig {
+                reverse_futility_pruning: true,
+                ..SearchConfig::none()
+            },
+            SearchConfig {
+                futility_pruning: true,
+                ..SearchConfig::none()
+            },
+            SearchConfig::default(),
+        ];
+        for config in configs {
+            let result = AlphaBeta::with_config(config).search(&board, 3);
+            assert_eq!(Score::mate_in(1), result.score);
+            assert_eq!("h1h8", result.get_best_move().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_search_selectivity_saves_nodes() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let plain = AlphaBeta::with_config(SearchConfig::none()).search(&board, 4);
+        let selective = AlphaBeta::with_config(SearchConfig {
+            principal_variation_search: false,
+            aspiration_windows: false,
+            ..SearchConfig::default()
+        })
+        .search(&board, 4);
+        assert!(selective.nodes < plain.nodes);
+    }
+
+    #[test]
+    fn test_has_non_pawn_material() {
+        let board = board_from_fen("4k3/4p3/8/8/8/8/4P3/2B1K3 w - - 0 1");
+        assert!(has_non_pawn_material(&board, PieceColor::Light));
+        assert!(!has_non_pawn_material(&board, PieceColor::Dark));
+    }
+
+    #[test]
+    fn test_late_move_reduction() {
+        let mut search = AlphaBeta::new();
+        let m = SearchMove::new((1, 0).into(), BasicMove::new_move((2, 2).into()));
+        assert_eq!(1, search.late_move_reduction(&m, 5, 4));
+        assert_eq!(2, search.late_move_reduction(&m, 5, 10));
+        // At least one depth has to remain.
+        assert_eq!(0, search.late_move_reduction(&m, 2, 10));
+        // Moves with a good history get reduced less.
+        search.history[square_index(m.from)][square_index(m.get_to())] = 100;
+        assert_eq!(1, search.late_move_reduction(&m, 5, 10));
+    }
+
+    #[test]
+    fn test_principal_variation_search() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let plain = AlphaBeta::with_config(SearchConfig::none()).search(&board, 3);
+        let pvs = AlphaBeta::with_config(SearchConfig {
+            principal_variation_search: true,
+            ..SearchConfig::none()
+        })
+        .search(&board, 3);
+        assert_eq!(plain.score, pvs.score);
+        assert!(pvs.statistics.pvs_re_searches > 0);
+        assert_eq!(0, plain.statistics.pvs_re_searches);
+    }
+
+    #[test]
+    fn test_aspiration_windows() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let plain = AlphaBeta::with_config(SearchConfig::none()).search(&board, 3);
+        // A tiny window makes sure that the search fails at least once.
+        let aspiration = AlphaBeta::with_config(SearchConfig {
+            aspiration_windows: true,
+            aspiration_window: Score::new(10),
+            ..SearchConfig::none()
+        })
+        .search(&board, 3);
+        assert_eq!(plain.score, aspiration.score);
+        let statistics = aspiration.statistics;
+        assert!(statistics.aspiration_fail_highs + statistics.aspiration_fail_lows > 0);
+    }
+
+    #[test]
+    fn test_search_multi_pv() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let single = search(&board, 2);
+        let lines = AlphaBeta::new().search_multi_pv(&board, 2, 3);
+        assert_eq!(3, lines.len());
+        assert_eq!(single.score, lines[0].score);
+        assert_eq!(single.get_best_move(), lines[0].get_best_move());
+        for i in 1..lines.len() {
+            assert!(lines[i - 1].score >= lines[i].score);
+            for j in 0..i {
+                assert_ne!(lines[i].get_best_move(), lines[j].get_best_move());
+            }
+        }
+        for line in &lines {
+            assert_eq!(2, line.pv.len());
+            assert_eq!(lines[0].nodes, line.nodes);
+        }
+    }
+
+    #[test]
+    fn test_search_multi_pv_more_lines_than_moves() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let lines = AlphaBeta::new().search_multi_pv(&board, 2, 100);
+        assert_eq!(legal_moves(&board).len(), lines.len());
+        assert_eq!("h1h8", lines[0].get_best_move().unwrap().to_string());
+
+        // Without a legal move there is still a single line.
+        let board = board_from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        let lines = AlphaBeta::new().search_multi_pv(&board, 2, 3);
+        assert_eq!(1, lines.len());
+        assert!(lines[0].pv.is_empty());
+    }
+
+    #[test]
+    fn test_search_transposition_table() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        let first = search.search(&board, 3);
+        assert!(search.get_table().get_hashfull() > 0);
+        // The second search can reuse the results of the first one.
+        let second = search.search(&board, 3);
+        assert_eq!(first.score, second.score);
+        assert!(second.nodes < first.nodes);
+    }
+
+    #[test]
+    fn test_score_table_conversion() {
+        assert_eq!(Score::new(35), score_to_table(Score::new(35), 4));
+        assert_eq!(Score::mate_in(1), score_to_table(Score::mate_in(5), 4));
+        assert_eq!(Score::mated_in(1), score_to_table(Score::mated_in(5), 4));
+        for score in [Score::new(-35), Score::mate_in(7), Score::mated_in(3)].iter() {
+            assert_eq!(*score, score_from_table(score_to_table(*score, 3), 3));
+        }
+    }
+
+    #[test]
+    fn test_search_stop_flag() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        search.set_stop_flag(Arc::new(AtomicBool::new(true)));
+        // The first iteration is always finished.
+        let result = search.search(&board, 4);
+        assert_eq!(1, result.depth);
+        assert!(result.get_best_move().is_some());
+    }
+
+    #[test]
+    fn test_search_depth_limit() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        search.set_depth_limit(Arc::new(AtomicU8::new(2)));
+        let result = search.search(&board, u8::MAX);
+        assert_eq!(2, result.depth);
+        assert_eq!(result, AlphaBeta::new().search(&board, 2));
+    }
+
+    #[test]
+    fn test_search_observer() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let (sender, receiver) = channel();
+        let mut search = AlphaBeta::new();
+        search.set_observer(Box::new(sender));
+        let result = search.search_multi_pv(&board, 3, 2);
+        drop(search);
+        let events: Vec<SearchEvent> = receiver.iter().collect();
+        let infos: Vec<&SearchInfo> = events
+            .iter()
+            .filter_map(|event| match event {
+                SearchEvent::Iteration(info) => Some(info),
+                _ => None,
+            })
+            .collect();
+        // One info for every line of every iteration.
+        assert_eq!(6, infos.len());
+        for (i, info) in infos.iter().enumerate() {
+            assert_eq!(i as u8 / 2 + 1, info.depth);
+            assert_eq!(i % 2 + 1, info.multi_pv);
+            assert!(info.seldepth >= info.depth);
+        }
+        let last = infos[4];
+        assert_eq!(result[0].pv, last.pv);
+        assert_eq!(result[0].score, last.score);
+        assert!(infos[5].nodes > infos[0].nodes);
+
+        let legal = legal_moves(&board).len();
+        let current_moves = events
+            .iter()
+            .filter(|event| match event {
+                SearchEvent::CurrentMove { depth, number, .. } => {
+                    assert!(*number <= legal);
+                    *depth == 1
+                }
+                _ => false,
+            })
+            .count();
+        // The second line skips the best move of the first one.
+        assert_eq!(legal * 2 - 1, current_moves);
+    }
+
+    #[test]
+    fn test_search_recorder() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        search.set_recorder(SearchRecorder::new(1));
+        let result = search.search(&board, 3);
+        let tree = search.get_recorder().unwrap().to_tree().unwrap();
+        let root = tree.root();
+        assert_eq!(Some(result.score), root.data().score);
+        assert_eq!(3, root.data().depth);
+        // Only the root and its children are recorded.
+        assert!(root.iter().all(|child| child.has_no_child()));
+        let best_move = result.get_best_move().unwrap();
+        let best = root
+            .iter()
+            .filter(|child| child.data().r#move == Some(best_move))
+            .last()
+            .unwrap();
+        assert_eq!(Some(-result.score), best.data().score);
+        assert_eq!(NodeOutcome::Exact, best.data().outcome);
+        // Every legal move shows up at the root.
+        for m in legal_moves(&board) {
+            assert!(root.iter().any(|child| child.data().r#move == Some(m)));
+        }
+    }
+
+    #[test]
+    fn test_mate_distance() {
+        let board = board_from_fen("3r2k1/5ppp/8/8/8/8/4R3/4R1K1 w - - 0 1");
+        assert_eq!(Some(2), search(&board, 4).get_mate_distance());
+    }
+
+    #[test]
+    fn test_search_with_limits() {
+        let board = board_from_fen("r3k3/1pp5/8/8/8/2N5/PP3B2/2K1R3 w - - 0 1");
+        let mut search = AlphaBeta::new();
+        let full = search.search_with_limits(&board, SearchLimits::depth(3));
+        assert_eq!(3, full.depth);
+        // The first iteration is always finished, even if it needs more nodes.
+        let limited = search.search_with_limits(&board, SearchLimits::depth(3).with_nodes(1));
+        assert_eq!(1, limited.depth);
+        assert!(limited.get_best_move().is_some());
+    }
+
+    #[test]
+    fn test_search_fifty_move_rule() {
+        // Light is a rook ahead, but every move ends the game in a draw.
+        let board = board_from_fen("k7/8/2K5/8/8/8/8/6R1 w - - 99 80");
+        assert_eq!(Score::DRAW, AlphaBeta::new().search(&board, 3).score);
+        // Without the fifty-move rule it is a mate in two.
+        let board = board_from_fen("k7/8/2K5/8/8/8/8/6R1 w - - 0 80");
+        let result = AlphaBeta::with_config(SearchConfig::none()).search(&board, 4);
+        assert_eq!(Score::mate_in(3), result.score);
+        // A mate on the last move counts.
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/6R1 w - - 99 80");
+        assert_eq!(Score::mate_in(1), AlphaBeta::new().search(&board, 3).score);
+    }
+
+    #[test]
+    fn test_search_repetition() {
+        // Light can only move the king to b1 and is lost.
+        let board = board_from_fen("4k3/8/8/8/8/8/4q3/K7 w - - 10 60");
+        assert!(AlphaBeta::new().search(&board, 2).score < Score::new(-500));
+
+        // The game went Kd8 Ka1 Ke8 from the board after Kb1, so Kb1 repeats it.
+        let history = vec![
+            board_from_fen("4k3/8/8/8/8/8/4q3/1K6 b - - 6 58").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/1K6 w - - 7 59").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/K7 b - - 8 59").get_zobrist_hash(),
+        ];
+        let mut search = AlphaBeta::new();
+        search.set_game_history(history);
+        let result = search.search(&board, 2);
+        assert_eq!(Score::DRAW, result.score);
+        assert_eq!("a1b1", result.get_best_move().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_is_draw() {
+        let board = board_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 4 40");
+        let hash = board.get_zobrist_hash();
+        let mut search = AlphaBeta::new();
+        search.path = vec![hash];
+        assert!(!search.is_draw(&board, hash));
+        // The same board four plies ago.
+        search.set_game_history(vec![hash, 1, 2, 3]);
+        assert!(search.is_draw(&board, hash));
+        // A board with the other team to move can't be a repetition.
+        search.set_game_history(vec![1, 2, 3, hash]);
+        assert!(!search.is_draw(&board, hash));
+        // A capture or pawn move was done in between.
+        let board = board_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 2 40");
+        search.set_game_history(vec![hash, 1, 2, 3]);
+        assert!(!search.is_draw(&board, hash));
+        let board = board_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 100 40");
+        search.set_game_history(vec![]);
+        assert!(search.is_draw(&board, hash));
+        let board = board_from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 100 40");
+        assert!(!search.is_draw(&board, board.get_zobrist_hash()));
+    }
+
+    #[test]
+    fn test_search_no_legal_moves() {
+        // Stalemate
+        let board = board_from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        let result = search(&board, 2);
+        assert_eq!(Score::DRAW, result.score);
+        assert!(result.pv.is_empty());
+        assert_eq!(None, result.get_best_move());
+    }
+}
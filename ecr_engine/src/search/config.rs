@@ -0,0 +1,101 @@
+//! Configuration of the search.
+
+use crate::score::Score;
+
+/// Configures the techniques used by the search. Every technique can be toggled on its own so they
+/// can be compared against each other.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SearchConfig {
+    /// Give the opponent a free move and skip the board if the reduced search still fails high.
+    pub null_move_pruning: bool,
+    /// How much shallower the search after the null move is.
+    pub null_move_reduction: u8,
+
+    /// Search quiet moves that are sorted late with a reduced depth.
+    pub late_move_reductions: bool,
+    /// The amount of legal moves that are always searched with the full depth.
+    pub late_move_full_depth_moves: usize,
+    /// The minimum depth at which moves are reduced.
+    pub late_move_min_depth: u8,
+
+    /// Return the static evaluation if it is above beta by a margin that grows with the depth.
+    pub reverse_futility_pruning: bool,
+    /// The margin per remaining depth used by the reverse futility pruning.
+    pub reverse_futility_margin: Score,
+    /// The maximum remaining depth at which the reverse futility pruning is used.
+    pub reverse_futility_max_depth: u8,
+
+    /// Skip quiet moves near the leaves if the static evaluation can't reach alpha anymore.
+    pub futility_pruning: bool,
+    /// The margin per remaining depth used by the futility pruning.
+    pub futility_margin: Score,
+    /// The maximum remaining depth at which the futility pruning is used.
+    pub futility_max_depth: u8,
+
+    /// Search every move besides the first one with a null window and only search it again if it
+    /// turns out to be better.
+    pub principal_variation_search: bool,
+
+    /// Search the root with a window around the score of the previous iteration.
+    pub aspiration_windows: bool,
+    /// Half of the width of the first aspiration window. Doubles after every failed search.
+    pub aspiration_window: Score,
+}
+
+impl SearchConfig {
+    /// Returns a configuration with all selectivity techniques and window tricks disabled, which
+    /// results in a plain alpha-beta search.
+    pub fn none() -> SearchConfig {
+        SearchConfig {
+            null_move_pruning: false,
+            late_move_reductions: false,
+            reverse_futility_pruning: false,
+            futility_pruning: false,
+            principal_variation_search: false,
+            aspiration_windows: false,
+            ..SearchConfig::default()
+        }
+    }
+}
+
+impl Default for SearchConfig {
+    /// By default, every selectivity technique is enabled.
+    fn default() -> Self {
+        SearchConfig {
+            null_move_pruning: true,
+            null_move_reduction: 2,
+            late_move_reductions: true,
+            late_move_full_depth_moves: 3,
+            late_move_min_depth: 3,
+            reverse_futility_pruning: true,
+            reverse_futility_margin: Score::new(200),
+            reverse_futility_max_depth: 3,
+            futility_pruning: true,
+            futility_margin: Score::new(200),
+            futility_max_depth: 2,
+            principal_variation_search: true,
+            aspiration_windows: true,
+            aspiration_window: Score::new(50),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_none() {
+        let config = SearchConfig::none();
+        assert!(!config.null_move_pruning);
+        assert!(!config.late_move_reductions);
+        assert!(!config.reverse_futility_pruning);
+        assert!(!config.futility_pruning);
+        assert!(!config.principal_variation_search);
+        assert!(!config.aspiration_windows);
+        assert_eq!(
+            SearchConfig::default().null_move_reduction,
+            config.null_move_reduction
+        );
+    }
+}
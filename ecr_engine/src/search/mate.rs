@@ -0,0 +1,90 @@
+//! A search that proves or disproves a mate in a given amount of moves. The attacking team only
+//! tries moves that give check, the defending team tries every legal move. This is much faster
+//! than a regular search and finds every mate that consists of checks only, which is enough for
+//! most composed problems.
+
+use crate::board::Board;
+use crate::search::legal_moves;
+use crate::search::pv::PrincipalVariation;
+
+/// Searches for a mate of the team that has to move in at most `n` of its moves. Returns the
+/// mating line if there is one. The line is the shortest mate and the defender delays it as long
+/// as possible.
+pub fn search_mate(board: &Board, n: u8) -> Option<PrincipalVariation> {
+    (1..=n).find_map(|moves| attack(board, moves))
+}
+
+/// Returns the line of a mate in exactly `n` moves of the team that has to move.
+fn attack(board: &Board, n: u8) -> Option<PrincipalVariation> {
+    if n == 0 {
+        return None;
+    }
+    for m in legal_moves(board) {
+        let child = m.apply(board);
+        if !child.is_in_check(child.to_move) {
+            continue;
+        }
+        if let Some(line) = defend(&child, n - 1) {
+            return Some(PrincipalVariation::from_child(m, &line));
+        }
+    }
+    None
+}
+
+/// Returns the line of the longest defense if the team that has to move gets mated after at most
+/// `n` more moves of the attacker.
+fn defend(board: &Board, n: u8) -> Option<PrincipalVariation> {
+    let replies = legal_moves(board);
+    if replies.is_empty() {
+        // Stalemate isn't a mate.
+        return if board.is_in_check(board.to_move) {
+            Some(PrincipalVariation::new())
+        } else {
+            None
+        };
+    }
+    let mut longest: Option<(u8, PrincipalVariation)> = None;
+    for m in replies {
+        let child = m.apply(board);
+        // A reply that isn't mated in `n` moves refutes the whole line.
+        let (moves, line) = (1..=n).find_map(|moves| attack(&child, moves).map(|l| (moves, l)))?;
+        if longest.as_ref().is_none_or(|(longest, _)| moves > *longest) {
+            longest = Some((moves, PrincipalVariation::from_child(m, &line)));
+        }
+    }
+    longest.map(|(_, line)| line)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_search_mate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let line = search_mate(&board, 1).unwrap();
+        assert_eq!(vec!["Rh8#"], line.to_san(&board));
+        // A longer search still finds the shortest mate.
+        assert_eq!(line, search_mate(&board, 3).unwrap());
+    }
+
+    #[test]
+    fn test_search_mate_in_two() {
+        let board = board_from_fen("3r2k1/5ppp/8/8/8/8/4R3/4R1K1 w - - 0 1");
+        assert_eq!(None, search_mate(&board, 1));
+        let line = search_mate(&board, 2).unwrap();
+        assert_eq!(vec!["Re8+", "Rxe8", "Rxe8#"], line.to_san(&board));
+    }
+
+    #[test]
+    fn test_search_mate_none() {
+        // Stalemate after the only check isn't a mate.
+        let board = board_from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        assert_eq!(None, search_mate(&board, 2));
+        // A lone king can't mate.
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/8 w - - 0 1");
+        assert_eq!(None, search_mate(&board, 3));
+    }
+}
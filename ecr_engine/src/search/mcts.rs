@@ -0,0 +1,424 @@
+//! A Monte Carlo tree search using UCT. Instead of searching every move up to a fixed depth, the
+//! search grows a tree towards the moves that scored best so far and scores new nodes either with
+//! the evaluation or with a random playout.
+
+use crate::board::Board;
+use crate::eval::evaluator::{ClassicEvaluator, Evaluator};
+use crate::pieces::PieceType;
+use crate::score::Score;
+use crate::search::alpha_beta::{SearchResult, SearchStatistics};
+use crate::search::pv::PrincipalVariation;
+use crate::search::{legal_moves, pseudo_legal_moves, SearchLimits, SearchMove, Searcher};
+
+/// How a new node of the tree gets scored.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Simulation {
+    /// Uses the evaluation of the board.
+    Evaluation,
+    /// Plays random moves up to the given amount of plies and evaluates the board at the end.
+    RandomPlayout(u8),
+}
+
+/// Configures the Monte Carlo tree search.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MctsConfig {
+    /// How much the search prefers moves that were visited rarely over moves that scored well.
+    pub exploration: f32,
+    /// The amount of iterations if the limits have no node limit.
+    pub iterations: u64,
+    pub simulation: Simulation,
+    /// The evaluation difference in centipawns that changes the winning chance from 50% to about
+    /// 91%.
+    pub eval_scale: f32,
+    /// The seed of the random playouts. The same seed always gives the same result.
+    pub seed: u64,
+}
+
+impl Default for MctsConfig {
+    fn default() -> Self {
+        MctsConfig {
+            exploration: std::f32::consts::SQRT_2,
+            iterations: 1_000,
+            simulation: Simulation::Evaluation,
+            eval_scale: 400.0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+/// A node of the search tree.
+#[derive(Debug, Clone)]
+struct MctsNode {
+    board: Board,
+    /// The move that leads to the node. Is `None` for the root.
+    r#move: Option<SearchMove>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The moves that don't have a child yet.
+    untried: Vec<SearchMove>,
+    /// Is true if the team that has to move has no legal moves.
+    terminal: bool,
+    visits: u32,
+    /// The sum of the results from the view of the team that did the move to this node.
+    reward: f32,
+}
+
+impl MctsNode {
+    fn new(board: Board, r#move: Option<SearchMove>, parent: Option<usize>) -> MctsNode {
+        let untried = legal_moves(&board);
+        MctsNode {
+            terminal: untried.is_empty(),
+            board,
+            r#move,
+            parent,
+            children: vec![],
+            untried,
+            visits: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+/// Searches the board with a Monte Carlo tree search. The tree is built from scratch for every
+/// search. The boards are evaluated by the [`Evaluator`] `E`.
+#[derive(Debug)]
+pub struct Mcts<E: Evaluator = ClassicEvaluator> {
+    config: MctsConfig,
+    nodes: Vec<MctsNode>,
+    random: Random,
+    evaluator: E,
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Mcts::with_config(MctsConfig::default())
+    }
+}
+
+impl Mcts {
+    pub fn new() -> Mcts {
+        Mcts::default()
+    }
+
+    /// Returns a search that uses the supplied configuration.
+    pub fn with_config(config: MctsConfig) -> Mcts {
+        Mcts::with_evaluator(config, ClassicEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> Mcts<E> {
+    /// Returns a search that uses the supplied configuration and evaluator.
+    pub fn with_evaluator(config: MctsConfig, evaluator: E) -> Mcts<E> {
+        Mcts {
+            config,
+            nodes: vec![],
+            random: Random::new(config.seed),
+            evaluator,
+        }
+    }
+
+    /// Returns the evaluator of the search.
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Returns the configuration of the search.
+    pub fn get_config(&self) -> &MctsConfig {
+        &self.config
+    }
+
+    /// Runs the given amount of iterations. The tree doesn't get deeper than `max_depth` plies.
+    pub fn search(&mut self, board: &Board, iterations: u64, max_depth: u8) -> SearchResult {
+        self.nodes = vec![MctsNode::new(board.clone(), None, None)];
+        self.random = Random::new(self.config.seed);
+        for _ in 0..iterations {
+            let (leaf, depth) = self.select();
+            let leaf = if depth < max_depth {
+                self.expand(leaf)
+            } else {
+                leaf
+            };
+            let result = self.simulate(leaf);
+            self.backpropagate(leaf, result);
+        }
+        self.get_result(iterations)
+    }
+
+    /// Walks down the tree along the children with the best UCT value until it reaches a node
+    /// that isn't fully expanded. Returns the node and its depth.
+    fn select(&self) -> (usize, u8) {
+        let mut index = 0;
+        let mut depth = 0;
+        loop {
+            let node = &self.nodes[index];
+            if node.terminal || !node.untried.is_empty() || node.children.is_empty() {
+                return (index, depth);
+            }
+            let parent_visits = (node.visits.max(1) as f32).ln();
+            let best = node.children.iter().copied().max_by(|a, b| {
+                let a = self.uct(*a, parent_visits);
+                let b = self.uct(*b, parent_visits);
+                // UCT values are never NaN, since every child was visited at least once.
+                a.partial_cmp(&b).unwrap()
+            });
+            match best {
+                Some(best) => {
+                    index = best;
+                    depth += 1;
+                }
+                None => return (index, depth),
+            }
+        }
+    }
+
+    /// Returns the UCT value of the node: The average result plus a bonus for rarely visited
+    /// nodes.
+    fn uct(&self, index: usize, parent_visits_ln: f32) -> f32 {
+        let node = &self.nodes[index];
+        let visits = node.visits.max(1) as f32;
+        node.reward / visits + self.config.exploration * (parent_visits_ln / visits).sqrt()
+    }
+
+    /// Adds a child for a random untried move and returns it. Returns the node itself if it has no
+    /// untried moves.
+    fn expand(&mut self, index: usize) -> usize {
+        if self.nodes[index].untried.is_empty() {
+            return index;
+        }
+        let choice = self.random.next_below(self.nodes[index].untried.len());
+        let m = self.nodes[index].untried.swap_remove(choice);
+        let board = m.apply(&self.nodes[index].board);
+        let child = self.nodes.len();
+        self.nodes.push(MctsNode::new(board, Some(m), Some(index)));
+        self.nodes[index].children.push(child);
+        child
+    }
+
+    /// Returns the result of the node from the view of the team that has to move there, from `0`
+    /// for a loss to `1` for a win.
+    fn simulate(&mut self, index: usize) -> f32 {
+        let node = &self.nodes[index];
+        if node.terminal {
+            return if node.board.is_in_check(node.board.to_move) {
+                0.0
+            } else {
+                // Stalemate
+                0.5
+            };
+        }
+        match self.config.simulation {
+            Simulation::Evaluation => {
+                win_chance(&mut self.evaluator, self.config.eval_scale, &node.board)
+            }
+            Simulation::RandomPlayout(plies) => {
+                let mut board = node.board.clone();
+                let mut flipped = false;
+                for _ in 0..plies {
+                    // Pseudo legal moves are much faster to generate. A playout that captures the
+                    // king is won by the team that captured it.
+                    let moves = pseudo_legal_moves(&board);
+                    if moves.is_empty() {
+                        break;
+                    }
+                    let m = moves[self.random.next_below(moves.len())];
+                    if let Some(capture) = m.basic_move.capture {
+                        if capture.piece_type == PieceType::King {
+                            return if flipped { 0.0 } else { 1.0 };
+                        }
+                    }
+                    board = m.apply(&board);
+                    flipped = !flipped;
+                }
+                let result = win_chance(&mut self.evaluator, self.config.eval_scale, &board);
+                if flipped {
+                    1.0 - result
+                } else {
+                    result
+                }
+            }
+        }
+    }
+
+    /// Adds the result to every node from the given one up to the root.
+    fn backpropagate(&mut self, mut index: usize, mut result: f32) {
+        loop {
+            let node = &mut self.nodes[index];
+            node.visits += 1;
+            // The result is from the view of the team that has to move at the node, but the
+            // reward is from the view of the team that did the move to it.
+            node.reward += 1.0 - result;
+            result = 1.0 - result;
+            match node.parent {
+                Some(parent) => index = parent,
+                None => return,
+            }
+        }
+    }
+
+    /// Builds the result from the tree. The line follows the most visited children.
+    fn get_result(&self, iterations: u64) -> SearchResult {
+        let mut pv = PrincipalVariation::new();
+        let mut moves = vec![];
+        let mut index = 0;
+        while let Some(best) = self.nodes[index]
+            .children
+            .iter()
+            .copied()
+            .max_by_key(|child| self.nodes[*child].visits)
+        {
+            // Moves are only part of the line if they were visited more than once.
+            if self.nodes[best].visits < 2 && !moves.is_empty() {
+                break;
+            }
+            if let Some(m) = self.nodes[best].r#move {
+                moves.push(m);
+            }
+            index = best;
+        }
+        for m in moves.into_iter().rev() {
+            pv = PrincipalVariation::from_child(m, &pv);
+        }
+        let score = match self.nodes[0]
+            .children
+            .iter()
+            .max_by_key(|c| self.nodes[**c].visits)
+        {
+            Some(best) => {
+                let node = &self.nodes[*best];
+                self.to_score(node.reward / node.visits.max(1) as f32)
+            }
+            None if self.nodes[0].board.is_in_check(self.nodes[0].board.to_move) => {
+                Score::mated_in(0)
+            }
+            None => Score::DRAW,
+        };
+        SearchResult {
+            score,
+            depth: pv.len() as u8,
+            pv,
+            nodes: iterations,
+            statistics: SearchStatistics::default(),
+        }
+    }
+
+    /// Turns the chance to win back into an evaluation.
+    fn to_score(&self, win_chance: f32) -> Score {
+        let chance = win_chance.clamp(0.001, 0.999);
+        Score::new((-self.config.eval_scale * (1.0 / chance - 1.0).log10()).round() as i32)
+    }
+}
+
+impl<E: Evaluator> Searcher for Mcts<E> {
+    /// Runs as many iterations as the node limit allows, or the amount of the configuration if
+    /// there is none. The depth limits how deep the tree grows.
+    fn search_with_limits(&mut self, board: &Board, limits: SearchLimits) -> SearchResult {
+        let iterations = limits.nodes.unwrap_or(self.config.iterations);
+        self.search(board, iterations, limits.depth)
+    }
+}
+
+/// Turns the evaluation of the board into the chance of the team that has to move to win.
+fn win_chance<E: Evaluator>(evaluator: &mut E, eval_scale: f32, board: &Board) -> f32 {
+    // The boards of the tree aren't visited in the order of the moves, so every board is
+    // evaluated from scratch.
+    evaluator.reset(board);
+    let eval = evaluator.evaluate(board);
+    let eval = if board.get_light_to_move() {
+        eval
+    } else {
+        -eval
+    };
+    1.0 / (1.0 + 10f32.powf(-eval.get_value() as f32 / eval_scale))
+}
+
+/// A small xorshift random number generator, good enough to pick random moves.
+#[derive(Debug, Clone)]
+struct Random(u64);
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        // Xorshift gets stuck at zero.
+        Random(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number from `0` to `max - 1`.
+    fn next_below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::evaluator::MaterialEvaluator;
+    use crate::search::alpha_beta::AlphaBeta;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_search_mate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let result = Mcts::new().search(&board, 300, 4);
+        assert_eq!("h1h8", result.get_best_move().unwrap().to_string());
+        assert!(result.score > Score::DRAW);
+        assert_eq!(300, result.nodes);
+    }
+
+    #[test]
+    fn test_random_playout() {
+        // Light can capture the queen.
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let config = MctsConfig {
+            simulation: Simulation::RandomPlayout(4),
+            ..MctsConfig::default()
+        };
+        let result = Mcts::with_config(config).search(&board, 300, 4);
+        assert_eq!("b1c3", result.get_best_move().unwrap().to_string());
+        // The same seed gives the same result.
+        assert_eq!(result, Mcts::with_config(config).search(&board, 300, 4));
+    }
+
+    #[test]
+    fn test_evaluator() {
+        // Every move keeps the material equal, so only counting it finds no difference.
+        let board = Board::default();
+        let mut search = Mcts::with_evaluator(MctsConfig::default(), MaterialEvaluator);
+        assert_eq!(Score::DRAW, search.search(&board, 40, 1).score);
+        assert_ne!(Score::DRAW, Mcts::new().search(&board, 40, 1).score);
+    }
+
+    #[test]
+    fn test_no_legal_moves() {
+        let board = board_from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        let result = Mcts::new().search(&board, 10, 4);
+        assert_eq!(None, result.get_best_move());
+        assert_eq!(Score::DRAW, result.score);
+    }
+
+    #[test]
+    fn test_searcher() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let mut searchers: Vec<Box<dyn Searcher>> =
+            vec![Box::new(AlphaBeta::new()), Box::new(Mcts::new())];
+        for searcher in searchers.iter_mut() {
+            let result =
+                searcher.search_with_limits(&board, SearchLimits::depth(2).with_nodes(300));
+            assert_eq!("h1h8", result.get_best_move().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_random() {
+        let mut random = Random::new(0);
+        for _ in 0..100 {
+            assert!(random.next_below(7) < 7);
+        }
+    }
+}
@@ -1 +1,260 @@
+//! Everything needed to search for the best move on a [`Board`].
+
+use std::fmt::{self, Debug, Display, Formatter};
+
+use ecr_shared::coordinate::Coordinate;
+
+use crate::board::Board;
+use crate::pieces::move_gen::BasicMove;
+use crate::pieces::PieceType;
+
+pub mod alpha_beta;
+pub mod config;
+pub mod mate;
+pub mod mcts;
+pub mod observer;
+pub mod ponder;
+pub mod proof_number;
+pub mod pv;
+pub mod recorder;
 pub mod search_utils;
+pub mod smp;
+pub mod tablebase;
+pub mod transposition;
+
+pub use alpha_beta::search;
+pub use mate::search_mate;
+
+use crate::search::alpha_beta::SearchResult;
+
+/// Limits how much work a [`Searcher`] does.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SearchLimits {
+    /// The maximum depth in plies. How exactly it is used depends on the searcher.
+    pub depth: u8,
+    /// The maximum amount of nodes. Is unlimited if it is `None`.
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    /// Returns limits with the given depth and no node limit.
+    pub fn depth(depth: u8) -> SearchLimits {
+        SearchLimits { depth, nodes: None }
+    }
+
+    /// Returns the limits with the given node limit.
+    pub fn with_nodes(self, nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..self
+        }
+    }
+}
+
+/// Something that searches for the best move on a board. Every search backend implements this, so
+/// they can be swapped and compared with each other.
+pub trait Searcher: Debug + Send {
+    /// Searches the board within the limits and returns the best line that was found.
+    fn search_with_limits(&mut self, board: &Board, limits: SearchLimits) -> SearchResult;
+}
+
+/// A single move as it is done by the search. Consists of the starting square of the piece and the
+/// [`BasicMove`] that is done from there.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SearchMove {
+    pub from: Coordinate,
+    pub basic_move: BasicMove,
+}
+
+impl SearchMove {
+    pub fn new(from: Coordinate, basic_move: BasicMove) -> SearchMove {
+        SearchMove { from, basic_move }
+    }
+
+    /// Returns the square the piece moves to.
+    pub fn get_to(&self) -> Coordinate {
+        self.basic_move.get_target_square()
+    }
+
+    /// Returns true if the move captures a piece.
+    pub fn is_capture(&self) -> bool {
+        self.basic_move.capture.is_some()
+    }
+
+    /// Returns true if the move is a pawn reaching the last row. Promotions are always done to a
+    /// queen for now.
+    pub fn is_promotion(&self, board: &Board) -> bool {
+        self.get_piece_type(board) == Some(PieceType::Pawn)
+            && (self.get_to().get_y() == 7 || self.get_to().get_y() == 0)
+    }
+
+    /// Returns the type of the piece that does the move.
+    pub fn get_piece_type(&self, board: &Board) -> Option<PieceType> {
+        board
+            .get_at(self.from)
+            .map(|inner| inner.as_ref().borrow().get_piece().get_type())
+    }
+
+    /// Executes the move on a copy of the given board and returns it.
+    pub fn apply(&self, board: &Board) -> Board {
+        let mut result = board.clone();
+        result.r#move(self.from, &self.basic_move);
+        result
+    }
+
+    /// Returns the move in the long algebraic notation used by the UCI protocol, e.g. `e2e4` or
+    /// `a7a8q`.
+    pub fn to_uci(&self, board: &Board) -> String {
+        let mut result = format!("{}{}", self.from, self.get_to());
+        if self.is_promotion(board) {
+            result.push(PieceType::Queen.get_fen_piece_code());
+        }
+        result
+    }
+
+    /// Returns the move in the standard algebraic notation (SAN), e.g. `Nf3`, `exd5` or `Qh4#`.
+    /// The supplied board has to be the board before the move is done.
+    pub fn to_san(&self, board: &Board) -> String {
+        let piece_type = match self.get_piece_type(board) {
+            Some(piece_type) => piece_type,
+            None => return self.to_uci(board),
+        };
+        let mut result = String::from(piece_type.get_shortcode_algebraic());
+
+        if piece_type == PieceType::Pawn {
+            // Pawn captures are always disambiguated by the file they come from.
+            if self.is_capture() {
+                result.push(self.from.get_x_as_char());
+            }
+        } else {
+            result.push_str(&self.get_disambiguation(board, piece_type));
+        }
+        if self.is_capture() {
+            result.push('x');
+        }
+        result.push_str(&self.get_to().to_string());
+        if self.is_promotion(board) {
+            result.push('=');
+            result.push_str(PieceType::Queen.get_shortcode_algebraic());
+        }
+
+        // Mark checks and check mates.
+        let future_board = self.apply(board);
+        if future_board.is_in_check(future_board.to_move) {
+            if legal_moves(&future_board).is_empty() {
+                result.push('#');
+            } else {
+                result.push('+');
+            }
+        }
+        result
+    }
+
+    /// Returns the part of the SAN that is needed when multiple pieces of the same type can move
+    /// to the target square.
+    fn get_disambiguation(&self, board: &Board, piece_type: PieceType) -> String {
+        let others: Vec<SearchMove> = legal_moves(board)
+            .into_iter()
+            .filter(|m| {
+                m.from != self.from
+                    && m.get_to() == self.get_to()
+                    && m.get_piece_type(board) == Some(piece_type)
+            })
+            .collect();
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|m| m.from.get_x() != self.from.get_x()) {
+            self.from.get_x_as_char().to_string()
+        } else if others.iter().all(|m| m.from.get_y() != self.from.get_y()) {
+            (self.from.get_y() + 1).to_string()
+        } else {
+            self.from.to_string()
+        }
+    }
+}
+
+impl Display for SearchMove {
+    /// Formats the move using the format `fromto`, e.g. `e2e4`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.get_to())
+    }
+}
+
+/// Returns all pseudo legal moves of the team that has to move on the given board.
+pub(crate) fn pseudo_legal_moves(board: &Board) -> Vec<SearchMove> {
+    let mut result = vec![];
+    for moves in board.get_pseudo_legal_moves(board.to_move) {
+        for basic_move in moves.basic_move {
+            result.push(SearchMove::new(moves.from, basic_move));
+        }
+    }
+    result
+}
+
+/// Returns all legal moves of the team that has to move on the given board.
+pub fn legal_moves(board: &Board) -> Vec<SearchMove> {
+    let mut result = vec![];
+    for moves in board.get_legal_moves(board.to_move) {
+        for basic_move in moves.basic_move {
+            result.push(SearchMove::new(moves.from, basic_move));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_to_uci() {
+        let board = Board::default();
+        let m = SearchMove::new((4, 1).into(), BasicMove::new_move((4, 3).into()));
+        assert_eq!("e2e4", m.to_uci(&board));
+        assert_eq!("e2e4", m.to_string());
+
+        let board = board_from_fen("8/P7/8/8/8/8/8/k1K5 w - - 0 1");
+        let m = SearchMove::new((0, 6).into(), BasicMove::new_move((0, 7).into()));
+        assert_eq!("a7a8q", m.to_uci(&board));
+    }
+
+    #[test]
+    fn test_to_san() {
+        let board = Board::default();
+        let m = SearchMove::new((6, 0).into(), BasicMove::new_move((5, 2).into()));
+        assert_eq!("Nf3", m.to_san(&board));
+        let m = SearchMove::new((4, 1).into(), BasicMove::new_move((4, 3).into()));
+        assert_eq!("e4", m.to_san(&board));
+
+        let board = board_from_fen("k7/8/8/3p4/4P3/8/8/K7 w - - 0 1");
+        let m = SearchMove::new(
+            (4, 3).into(),
+            BasicMove::new_capture((3, 4).into(), PieceType::Pawn),
+        );
+        assert_eq!("exd5", m.to_san(&board));
+
+        // Both rooks can go to d1.
+        let board = board_from_fen("k7/8/8/8/8/8/8/R4R1K w - - 0 1");
+        let m = SearchMove::new((0, 0).into(), BasicMove::new_move((3, 0).into()));
+        assert_eq!("Rad1", m.to_san(&board));
+
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let m = SearchMove::new((7, 0).into(), BasicMove::new_move((7, 7).into()));
+        assert_eq!("Rh8#", m.to_san(&board));
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        assert_eq!(20, legal_moves(&Board::default()).len());
+
+        // The king is in check and can only move out of it.
+        let board = board_from_fen("k7/8/8/8/8/8/8/r6K w - - 0 1");
+        let moves = legal_moves(&board);
+        assert!(!moves.is_empty());
+        for m in moves {
+            assert_ne!(0, m.get_to().get_y());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Live feedback from a running search. A [`SearchObserver`] gets notified about the progress of
+//! the search, which can be used by user interfaces, protocol front-ends or for logging.
+
+use std::fmt::Debug;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use crate::score::Score;
+use crate::search::pv::PrincipalVariation;
+use crate::search::SearchMove;
+
+/// The state of the search after a line of an iteration was finished.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo {
+    /// The depth of the finished iteration.
+    pub depth: u8,
+    /// The highest ply that was reached so far.
+    pub seldepth: u8,
+    /// The amount of visited nodes so far.
+    pub nodes: u64,
+    /// The visited nodes per second.
+    pub nps: u64,
+    /// How full the transposition table is in permille.
+    pub hashfull: u16,
+    /// The time since the search was started.
+    pub time: Duration,
+    /// The number of the line, starting at `1`. Is always `1` if only a single line is searched.
+    pub multi_pv: usize,
+    /// The score of the line from the view of the team that has to move.
+    pub score: Score,
+    pub pv: PrincipalVariation,
+}
+
+/// Something that happened during the search.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SearchEvent {
+    /// The search started searching a move at the root.
+    CurrentMove {
+        depth: u8,
+        current_move: SearchMove,
+        /// The number of the move in the order it is searched, starting at `1`.
+        number: usize,
+    },
+    /// A line of an iteration was finished.
+    Iteration(SearchInfo),
+}
+
+/// Receives the events of a search. The observer is called from the thread that runs the search.
+pub trait SearchObserver: Debug + Send {
+    fn notify(&mut self, event: &SearchEvent);
+}
+
+/// Sends every event through the channel, so they can be received on another thread. Events that
+/// can't be sent because the receiver is gone are dropped.
+impl SearchObserver for Sender<SearchEvent> {
+    fn notify(&mut self, event: &SearchEvent) {
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Returns the visited nodes per second. Is `0` if no time has passed yet.
+pub(crate) fn nodes_per_second(nodes: u64, time: Duration) -> u64 {
+    match time.as_micros() {
+        0 => 0,
+        micros => (nodes as u128 * 1_000_000 / micros) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use crate::pieces::move_gen::BasicMove;
+
+    use super::*;
+
+    #[test]
+    fn test_nodes_per_second() {
+        assert_eq!(0, nodes_per_second(100, Duration::from_secs(0)));
+        assert_eq!(2_000, nodes_per_second(1_000, Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn test_channel_observer() {
+        let (mut sender, receiver) = channel();
+        let event = SearchEvent::CurrentMove {
+            depth: 1,
+            current_move: SearchMove::new((4, 1).into(), BasicMove::new_move((4, 3).into())),
+            number: 1,
+        };
+        sender.notify(&event);
+        assert_eq!(event, receiver.recv().unwrap());
+        // Sending without a receiver doesn't panic.
+        drop(receiver);
+        sender.notify(&event);
+    }
+}
@@ -0,0 +1,308 @@
+//! Thinking on the time of the opponent. After a search, the expected reply of the opponent is
+//! taken from the principal variation and the board after it gets searched on another thread. If
+//! the opponent plays the expected move, the pondering continues as the normal search, otherwise
+//! it is thrown away.
+
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::board::Board;
+use crate::eval::evaluator::{ClassicEvaluator, Evaluator};
+use crate::search::alpha_beta::{AlphaBeta, SearchResult};
+use crate::search::config::SearchConfig;
+use crate::search::transposition::TranspositionTable;
+use crate::search::SearchMove;
+
+/// The search that runs on the time of the opponent.
+#[derive(Debug)]
+struct PonderSearch {
+    /// The board after the expected move of the opponent.
+    board: Board,
+    expected_move: SearchMove,
+    stop: Arc<AtomicBool>,
+    /// The depth the search stops at. Is unlimited while pondering.
+    depth: Arc<AtomicU8>,
+    handle: JoinHandle<SearchResult>,
+}
+
+impl PonderSearch {
+    /// Waits until the search ends and returns its result.
+    fn join(self) -> SearchResult {
+        // The thread only panics if a regular search would have panicked as well.
+        self.handle.join().expect("ponder thread panicked")
+    }
+}
+
+/// A search that can ponder between its own searches. Every search shares the same transposition
+/// table, so even a search after a ponder miss profits from the pondering a bit. Every search
+/// evaluates the boards with its own copy of the [`Evaluator`] `E`.
+#[derive(Debug)]
+pub struct Ponder<E: Evaluator = ClassicEvaluator> {
+    config: SearchConfig,
+    table: Arc<TranspositionTable>,
+    pondering: Option<PonderSearch>,
+    /// The hashes of the boards of the game before the board of the next search, the oldest one
+    /// first.
+    game_history: Vec<u64>,
+    evaluator: E,
+}
+
+impl Ponder {
+    pub fn new(config: SearchConfig) -> Ponder {
+        Ponder::with_table(config, Arc::new(TranspositionTable::default()))
+    }
+
+    /// Returns a search that uses the given transposition table.
+    pub fn with_table(config: SearchConfig, table: Arc<TranspositionTable>) -> Ponder {
+        Ponder::with_evaluator(config, table, ClassicEvaluator::default())
+    }
+}
+
+impl<E: Evaluator> Ponder<E> {
+    /// Returns a search that uses the given transposition table and evaluator.
+    pub fn with_evaluator(
+        config: SearchConfig,
+        table: Arc<TranspositionTable>,
+        evaluator: E,
+    ) -> Ponder<E> {
+        Ponder {
+            config,
+            table,
+            pondering: None,
+            game_history: vec![],
+            evaluator,
+        }
+    }
+
+    /// Returns the evaluator every search gets a copy of.
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Sets the hashes of the boards that occurred in the game before the board of the next
+    /// search or pondering, the oldest one first. Repeating one of them is scored as a draw.
+    pub fn set_game_history(&mut self, history: Vec<u64>) {
+        self.game_history = history;
+    }
+
+    /// Searches the board up to the given depth. Stops the pondering if it is still running.
+    pub fn search(&mut self, board: &Board, depth: u8) -> SearchResult
+    where
+        E: Clone,
+    {
+        self.stop();
+        let mut search =
+            AlphaBeta::with_evaluator(self.config, Arc::clone(&self.table), self.evaluator.clone());
+        search.set_game_history(self.game_history.clone());
+        search.search(board, depth)
+    }
+
+    /// Starts pondering after our best move from the result was played on the board. The expected
+    /// reply of the opponent is the second move of the principal variation. Returns the expected
+    /// reply or `None` if the line is too short to ponder. The pondering sees the game history with
+    /// the board and the board after our move added, so it fits the board after the reply of the
+    /// opponent. The game history of the following searches stays as it was set.
+    pub fn start(&mut self, board: &Board, result: &SearchResult) -> Option<SearchMove>
+    where
+        E: Clone + 'static,
+    {
+        self.stop();
+        let moves = result.pv.get_moves();
+        if moves.len() < 2 {
+            return None;
+        }
+        let expected_move = moves[1];
+        let after_move = moves[0].apply(board);
+        let ponder_board = expected_move.apply(&after_move);
+        let mut history = self.game_history.clone();
+        history.push(board.get_zobrist_hash());
+        history.push(after_move.get_zobrist_hash());
+        let stop = Arc::new(AtomicBool::new(false));
+        let depth = Arc::new(AtomicU8::new(u8::MAX));
+        let mut search =
+            AlphaBeta::with_evaluator(self.config, Arc::clone(&self.table), self.evaluator.clone());
+        search.set_stop_flag(Arc::clone(&stop));
+        search.set_depth_limit(Arc::clone(&depth));
+        search.set_game_history(history);
+        let board = ponder_board.clone();
+        // The pondering only ends when it gets stopped or a ponder hit sets its depth.
+        let handle = thread::spawn(move || search.search(&board, u8::MAX));
+        self.pondering = Some(PonderSearch {
+            board: ponder_board,
+            expected_move,
+            stop,
+            depth,
+            handle,
+        });
+        Some(expected_move)
+    }
+
+    /// Returns true if the search is currently pondering.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// Returns the reply of the opponent the search is pondering on.
+    pub fn get_expected_move(&self) -> Option<SearchMove> {
+        self.pondering.as_ref().map(|p| p.expected_move)
+    }
+
+    /// The opponent played the expected move. The running pondering becomes the normal search: it
+    /// finishes the iterations up to the depth without starting over. If it is already deeper,
+    /// it stops at once and the result of its last completed iteration is returned.
+    pub fn ponderhit(&mut self, depth: u8) -> Option<SearchResult> {
+        let pondering = self.pondering.take()?;
+        pondering.depth.store(depth, Ordering::Relaxed);
+        Some(pondering.join())
+    }
+
+    /// The opponent did a move which resulted in the given board. Continues the pondering if it
+    /// was the expected move and restarts the search otherwise.
+    pub fn opponent_moved(&mut self, board: &Board, depth: u8) -> SearchResult
+    where
+        E: Clone,
+    {
+        let hit = self.pondering.as_ref().is_some_and(|pondering| {
+            pondering.board.get_zobrist_hash() == board.get_zobrist_hash()
+        });
+        if hit {
+            if let Some(result) = self.ponderhit(depth) {
+                return result;
+            }
+        }
+        // `search` stops the pondering on a miss.
+        self.search(board, depth)
+    }
+
+    /// Stops the pondering and throws its result away.
+    pub fn stop(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            finish(pondering);
+        }
+    }
+}
+
+impl<E: Evaluator> Drop for Ponder<E> {
+    /// Makes sure the pondering thread doesn't run forever.
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Stops the pondering and returns the result of the last completed iteration.
+fn finish(pondering: PonderSearch) -> SearchResult {
+    pondering.stop.store(true, Ordering::Relaxed);
+    pondering.join()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::score::Score;
+    use crate::search::legal_moves;
+    use crate::search::pv::PrincipalVariation;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    /// Returns the board with the queen that can be captured, the ponder search and its result.
+    fn get_ponder() -> (Board, Ponder, SearchResult) {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let mut ponder = Ponder::new(SearchConfig::default());
+        let result = ponder.search(&board, 3);
+        (board, ponder, result)
+    }
+
+    #[test]
+    fn test_start() {
+        let (board, mut ponder, result) = get_ponder();
+        assert!(!ponder.is_pondering());
+        let expected = ponder.start(&board, &result);
+        assert_eq!(Some(result.pv.get_moves()[1]), expected);
+        assert_eq!(expected, ponder.get_expected_move());
+        assert!(ponder.is_pondering());
+        ponder.stop();
+        assert!(!ponder.is_pondering());
+
+        // There is nothing to ponder on without an expected reply.
+        let mut short = result.clone();
+        short.pv = PrincipalVariation::from_child(result.pv.get_moves()[0], &Default::default());
+        assert_eq!(None, ponder.start(&board, &short));
+    }
+
+    #[test]
+    fn test_ponderhit() {
+        let (board, mut ponder, result) = get_ponder();
+        ponder.start(&board, &result);
+        let moves = result.pv.get_moves();
+        let next = moves[1].apply(&moves[0].apply(&board));
+        let hit = ponder.opponent_moved(&next, 2);
+        assert!(!ponder.is_pondering());
+        assert!(hit.depth >= 2);
+        assert!(legal_moves(&next).contains(&hit.get_best_move().unwrap()));
+        assert_eq!(None, ponder.ponderhit(2));
+    }
+
+    #[test]
+    fn test_game_history() {
+        let (board, mut ponder, result) = get_ponder();
+        ponder.set_game_history(vec![1, 2]);
+        ponder.start(&board, &result);
+        // The boards of the pondered line don't stay in the history of the next searches.
+        assert_eq!(vec![1, 2], ponder.game_history);
+        ponder.stop();
+        assert_eq!(vec![1, 2], ponder.game_history);
+
+        // Light is lost unless Kb1 repeats the board after Kb1 Kd8 Ka1 Ke8.
+        let board = board_from_fen("4k3/8/8/8/8/8/4q3/K7 w - - 10 60");
+        ponder.set_game_history(vec![
+            board_from_fen("4k3/8/8/8/8/8/4q3/1K6 b - - 6 58").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/1K6 w - - 7 59").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/K7 b - - 8 59").get_zobrist_hash(),
+        ]);
+        let result = ponder.search(&board, 2);
+        assert_eq!(Score::DRAW, result.score);
+        assert_eq!("a1b1", result.get_best_move().unwrap().to_string());
+    }
+
+    /// Scores every board as a draw.
+    #[derive(Debug, Clone)]
+    struct DrawEvaluator;
+
+    impl Evaluator for DrawEvaluator {
+        fn evaluate(&mut self, _board: &Board) -> Score {
+            Score::DRAW
+        }
+    }
+
+    #[test]
+    fn test_evaluator() {
+        let board = board_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        let table = Arc::new(TranspositionTable::default());
+        let mut ponder = Ponder::with_evaluator(SearchConfig::default(), table, DrawEvaluator);
+        let result = ponder.search(&board, 2);
+        assert_eq!(Score::DRAW, result.score);
+        assert_ne!(Score::DRAW, AlphaBeta::new().search(&board, 2).score);
+        // The pondering uses the evaluator as well.
+        ponder.start(&board, &result);
+        assert_eq!(Score::DRAW, ponder.ponderhit(2).unwrap().score);
+    }
+
+    #[test]
+    fn test_ponder_miss() {
+        let (board, mut ponder, result) = get_ponder();
+        ponder.start(&board, &result);
+        let moves = result.pv.get_moves();
+        let after = moves[0].apply(&board);
+        // The opponent plays another move than the expected one.
+        let other = legal_moves(&after)
+            .into_iter()
+            .find(|m| *m != moves[1])
+            .unwrap();
+        let next = other.apply(&after);
+        let miss = ponder.opponent_moved(&next, 2);
+        assert!(!ponder.is_pondering());
+        assert_eq!(2, miss.depth);
+        assert!(legal_moves(&next).contains(&miss.get_best_move().unwrap()));
+    }
+}
@@ -0,0 +1,275 @@
+//! A proof-number search that tries to prove that the team that has to move can force a mate. In
+//! contrast to the alpha-beta search it has no depth limit: It keeps the whole searched tree in
+//! memory and always expands the node that is most likely to decide the result. This way it can
+//! prove mates that are too deep for the depth-limited search, as long as the defender has only a
+//! few replies.
+
+use trees::{tr, Node, Tree};
+
+use crate::board::Board;
+use crate::pieces::PieceColor;
+use crate::search::pv::PrincipalVariation;
+use crate::search::{legal_moves, SearchMove};
+
+/// The proof or disproof number of a node that can't be proven or disproven anymore.
+pub const INFINITE: u32 = u32::MAX;
+
+/// The result of a proof-number search.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ProofResult {
+    /// The attacker can force a mate.
+    Proven,
+    /// The attacker can't force a mate.
+    Disproven,
+    /// The node limit was reached before the result was found.
+    Unknown,
+}
+
+/// A node of the searched tree.
+#[derive(Debug, Clone)]
+pub struct ProofNode {
+    pub board: Board,
+    /// The move that leads to this node. Is `None` for the root.
+    pub r#move: Option<SearchMove>,
+    /// The minimum amount of leaves that have to be proven to prove this node.
+    pub proof: u32,
+    /// The minimum amount of leaves that have to be disproven to disprove this node.
+    pub disproof: u32,
+}
+
+/// Holds the tree of a proof-number search. The team that has to move at the root is the attacker.
+#[derive(Debug)]
+pub struct ProofNumberSearch {
+    tree: Tree<ProofNode>,
+    attacker: PieceColor,
+    max_nodes: usize,
+    nodes: usize,
+}
+
+impl ProofNumberSearch {
+    /// Returns a search of the board that stops after `max_nodes` nodes were added to the tree.
+    pub fn new(board: &Board, max_nodes: usize) -> ProofNumberSearch {
+        let attacker = board.to_move;
+        let (proof, disproof) = initial_numbers(board, attacker);
+        ProofNumberSearch {
+            tree: tr(ProofNode {
+                board: board.clone(),
+                r#move: None,
+                proof,
+                disproof,
+            }),
+            attacker,
+            max_nodes,
+            nodes: 1,
+        }
+    }
+
+    /// Expands the tree until the root is proven, disproven or the node limit is reached. Can be
+    /// called again with a higher limit to continue the search.
+    pub fn search(&mut self) -> ProofResult {
+        while self.get_result() == ProofResult::Unknown && self.nodes < self.max_nodes {
+            let attacker = self.attacker;
+            let mut nodes = self.nodes;
+            expand_most_proving(&mut self.tree.root_mut(), attacker, &mut nodes);
+            self.nodes = nodes;
+        }
+        self.get_result()
+    }
+
+    /// Returns the result of the root with the current tree.
+    pub fn get_result(&self) -> ProofResult {
+        let root = self.tree.root().data();
+        if root.proof == 0 {
+            ProofResult::Proven
+        } else if root.disproof == 0 {
+            ProofResult::Disproven
+        } else {
+            ProofResult::Unknown
+        }
+    }
+
+    /// Sets the maximum amount of nodes in the tree.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Returns the amount of nodes in the tree.
+    pub fn get_nodes(&self) -> usize {
+        self.nodes
+    }
+
+    /// Returns the searched tree.
+    pub fn get_tree(&self) -> &Tree<ProofNode> {
+        &self.tree
+    }
+
+    /// Returns a line that ends in a mate if the root is proven. The attacker plays a proven move
+    /// and the defender the reply with the largest proof tree, which is usually the longest
+    /// defense.
+    pub fn get_proof_line(&self) -> Option<PrincipalVariation> {
+        if self.get_result() != ProofResult::Proven {
+            return None;
+        }
+        let mut moves = vec![];
+        let mut node = self.tree.root();
+        loop {
+            let next = if node.data().board.to_move == self.attacker {
+                node.iter().find(|child| child.data().proof == 0)
+            } else {
+                node.iter().max_by_key(|child| child.node_count())
+            };
+            match next.and_then(|child| child.data().r#move.map(|m| (child, m))) {
+                Some((child, m)) => {
+                    moves.push(m);
+                    node = child;
+                }
+                None => break,
+            }
+        }
+        Some(
+            moves
+                .into_iter()
+                .rev()
+                .fold(PrincipalVariation::new(), |line, m| {
+                    PrincipalVariation::from_child(m, &line)
+                }),
+        )
+    }
+}
+
+/// Tries to prove a mate of the team that has to move with at most `max_nodes` nodes. Returns the
+/// mating line if the mate was proven.
+pub fn prove_mate(board: &Board, max_nodes: usize) -> Option<PrincipalVariation> {
+    let mut search = ProofNumberSearch::new(board, max_nodes);
+    search.search();
+    search.get_proof_line()
+}
+
+/// Returns the proof and the disproof number of a new leaf. Leaves with many moves are harder to
+/// prove for the defender and harder to disprove for the attacker.
+fn initial_numbers(board: &Board, attacker: PieceColor) -> (u32, u32) {
+    let moves = legal_moves(board).len() as u32;
+    let attacking = board.to_move == attacker;
+    if moves == 0 {
+        // Only a mate of the defender counts, stalemate is a draw.
+        return if !attacking && board.is_in_check(board.to_move) {
+            (0, INFINITE)
+        } else {
+            (INFINITE, 0)
+        };
+    }
+    if attacking {
+        (1, moves)
+    } else {
+        (moves, 1)
+    }
+}
+
+/// Walks down to the most proving leaf, expands it and updates the numbers of every node on the
+/// way back up.
+fn expand_most_proving(node: &mut Node<ProofNode>, attacker: PieceColor, nodes: &mut usize) {
+    let attacking = node.data().board.to_move == attacker;
+    if node.has_no_child() {
+        let board = node.data().board.clone();
+        for m in legal_moves(&board) {
+            let child = m.apply(&board);
+            let (proof, disproof) = initial_numbers(&child, attacker);
+            node.push_back(tr(ProofNode {
+                board: child,
+                r#move: Some(m),
+                proof,
+                disproof,
+            }));
+            *nodes += 1;
+        }
+    } else {
+        // The attacker needs a single proven child, so we follow the one that is easiest to
+        // prove. The defender needs a single disproven child.
+        let index = node
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, child)| {
+                if attacking {
+                    child.data().proof
+                } else {
+                    child.data().disproof
+                }
+            })
+            .map(|(index, _)| index);
+        if let Some(mut child) = index.and_then(|index| node.iter_mut().nth(index)) {
+            expand_most_proving(&mut child, attacker, nodes);
+        }
+    }
+    update_numbers(node, attacking);
+}
+
+/// Calculates the numbers of an expanded node from its children.
+fn update_numbers(node: &mut Node<ProofNode>, attacking: bool) {
+    if node.has_no_child() {
+        return;
+    }
+    let min_proof = node.iter().map(|child| child.data().proof).min();
+    let min_disproof = node.iter().map(|child| child.data().disproof).min();
+    let sum_proof = node
+        .iter()
+        .fold(0u32, |sum, child| sum.saturating_add(child.data().proof));
+    let sum_disproof = node
+        .iter()
+        .fold(0u32, |sum, child| sum.saturating_add(child.data().disproof));
+    let data = node.data_mut();
+    if attacking {
+        data.proof = min_proof.unwrap_or(INFINITE);
+        data.disproof = sum_disproof;
+    } else {
+        data.proof = sum_proof;
+        data.disproof = min_disproof.unwrap_or(INFINITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_prove_mate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        let line = prove_mate(&board, 1_000).unwrap();
+        assert_eq!(vec!["Rh8#"], line.to_san(&board));
+    }
+
+    #[test]
+    fn test_prove_mate_in_two() {
+        let board = board_from_fen("3r2k1/5ppp/8/8/8/8/4R3/4R1K1 w - - 0 1");
+        let mut search = ProofNumberSearch::new(&board, 10_000);
+        assert_eq!(ProofResult::Proven, search.search());
+        assert_eq!(search.get_nodes(), search.get_tree().root().node_count());
+        let line = search.get_proof_line().unwrap();
+        // The attacker does the first and the last move.
+        assert_eq!(1, line.len() % 2);
+        assert!(line.to_san(&board).last().unwrap().ends_with('#'));
+    }
+
+    #[test]
+    fn test_disproven() {
+        // The attacker is stalemated.
+        let board = board_from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+        let mut search = ProofNumberSearch::new(&board, 1_000);
+        assert_eq!(ProofResult::Disproven, search.search());
+        assert_eq!(None, search.get_proof_line());
+    }
+
+    #[test]
+    fn test_node_limit() {
+        // Two kings can't mate, but proving that needs an endless tree.
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/8 w - - 0 1");
+        let mut search = ProofNumberSearch::new(&board, 50);
+        assert_eq!(ProofResult::Unknown, search.search());
+        assert!(search.get_nodes() >= 50);
+        // The search can be continued with a higher limit.
+        search.set_max_nodes(100);
+        assert_eq!(ProofResult::Unknown, search.search());
+        assert!(search.get_nodes() >= 100);
+    }
+}
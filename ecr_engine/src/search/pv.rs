@@ -0,0 +1,117 @@
+//! The principal variation, i.e. the line of moves the search expects to be played.
+
+use std::fmt::{self, Display, Formatter};
+
+use crate::board::Board;
+use crate::search::SearchMove;
+
+/// Holds the moves of the principal variation, starting with the move that should be done on the
+/// searched board.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PrincipalVariation {
+    moves: Vec<SearchMove>,
+}
+
+impl PrincipalVariation {
+    pub fn new() -> PrincipalVariation {
+        PrincipalVariation { moves: vec![] }
+    }
+
+    /// Returns a principal variation that starts with the given move followed by the moves of the
+    /// supplied child variation.
+    pub fn from_child(first: SearchMove, child: &PrincipalVariation) -> PrincipalVariation {
+        let mut moves = Vec::with_capacity(child.moves.len() + 1);
+        moves.push(first);
+        moves.extend_from_slice(&child.moves);
+        PrincipalVariation { moves }
+    }
+
+    /// Returns the moves of the principal variation.
+    pub fn get_moves(&self) -> &Vec<SearchMove> {
+        &self.moves
+    }
+
+    /// Returns the first move of the principal variation, which is the best move.
+    pub fn first(&self) -> Option<&SearchMove> {
+        self.moves.first()
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Returns every move of the principal variation in the UCI notation. The supplied board has
+    /// to be the board the variation starts on.
+    pub fn to_uci(&self, board: &Board) -> Vec<String> {
+        self.map_moves(board, |m, b| m.to_uci(b))
+    }
+
+    /// Returns every move of the principal variation in the standard algebraic notation. The
+    /// supplied board has to be the board the variation starts on.
+    pub fn to_san(&self, board: &Board) -> Vec<String> {
+        self.map_moves(board, |m, b| m.to_san(b))
+    }
+
+    /// Plays through the variation and calls the supplied function with every move and the board
+    /// before that move.
+    fn map_moves<F: Fn(&SearchMove, &Board) -> String>(&self, board: &Board, f: F) -> Vec<String> {
+        let mut current = board.clone();
+        let mut result = Vec::with_capacity(self.moves.len());
+        for m in &self.moves {
+            result.push(f(m, &current));
+            current = m.apply(&current);
+        }
+        result
+    }
+}
+
+impl Display for PrincipalVariation {
+    /// Formats the variation as the moves separated by spaces, e.g. `e2e4 e7e5 g1f3`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let moves: Vec<String> = self.moves.iter().map(|m| m.to_string()).collect();
+        write!(f, "{}", moves.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ecr_shared::coordinate::Coordinate;
+
+    use crate::pieces::move_gen::BasicMove;
+
+    use super::*;
+
+    fn get_pv() -> PrincipalVariation {
+        let child = PrincipalVariation::from_child(
+            SearchMove::new((4, 6).into(), BasicMove::new_move((4, 4).into())),
+            &PrincipalVariation::new(),
+        );
+        PrincipalVariation::from_child(
+            SearchMove::new((4, 1).into(), BasicMove::new_move((4, 3).into())),
+            &child,
+        )
+    }
+
+    #[test]
+    fn test_from_child() {
+        let pv = get_pv();
+        assert_eq!(2, pv.len());
+        assert_eq!(Coordinate::new(4, 1), pv.first().unwrap().from);
+        assert_eq!(Coordinate::new(4, 6), pv.get_moves()[1].from);
+    }
+
+    #[test]
+    fn test_to_uci() {
+        assert_eq!(vec!["e2e4", "e7e5"], get_pv().to_uci(&Board::default()));
+        assert_eq!("e2e4 e7e5", get_pv().to_string());
+    }
+
+    #[test]
+    fn test_to_san() {
+        assert_eq!(vec!["e4", "e5"], get_pv().to_san(&Board::default()));
+    }
+}
@@ -0,0 +1,394 @@
+//! Records the tree of the alpha-beta search for debugging. The recorded tree contains the window,
+//! the score and the outcome of every node, so it shows exactly why the search preferred a move.
+//! It can be exported to Graphviz DOT and JSON.
+
+use std::fmt::{self, Display, Formatter};
+
+use trees::{tr, Node, Tree};
+
+use crate::score::Score;
+use crate::search::search_utils::tree_to_string;
+use crate::search::SearchMove;
+
+/// The reason why a node or a move was skipped.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PruneReason {
+    ReverseFutility,
+    NullMove,
+    Futility,
+    /// The move was already reported as a line of the multi pv search.
+    Excluded,
+}
+
+impl Display for PruneReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PruneReason::ReverseFutility => "reverse futility",
+            PruneReason::NullMove => "null move",
+            PruneReason::Futility => "futility",
+            PruneReason::Excluded => "excluded",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the search of a node ended.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeOutcome {
+    /// The node wasn't finished yet.
+    Open,
+    /// The static evaluation was returned because there is no depth left.
+    Leaf,
+    /// The score is exact.
+    Exact,
+    /// No move reached alpha, the score is an upper bound.
+    FailLow,
+    /// A move reached beta, the score is a lower bound.
+    Cutoff,
+    /// The score was taken from the transposition table.
+    TableHit,
+    Pruned(PruneReason),
+    Checkmate,
+    Stalemate,
+    /// The board is a draw by repetition or by the fifty-move rule.
+    Draw,
+    /// The exact score was taken from a tablebase.
+    Tablebase,
+    /// The search was stopped, the score is meaningless.
+    Stopped,
+}
+
+impl Display for NodeOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeOutcome::Open => write!(f, "open"),
+            NodeOutcome::Leaf => write!(f, "leaf"),
+            NodeOutcome::Exact => write!(f, "exact"),
+            NodeOutcome::FailLow => write!(f, "fail low"),
+            NodeOutcome::Cutoff => write!(f, "cutoff"),
+            NodeOutcome::TableHit => write!(f, "table hit"),
+            NodeOutcome::Pruned(reason) => write!(f, "pruned ({})", reason),
+            NodeOutcome::Checkmate => write!(f, "checkmate"),
+            NodeOutcome::Stalemate => write!(f, "stalemate"),
+            NodeOutcome::Draw => write!(f, "draw"),
+            NodeOutcome::Tablebase => write!(f, "tablebase"),
+            NodeOutcome::Stopped => write!(f, "stopped"),
+        }
+    }
+}
+
+/// A single node of the recorded tree.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecordedNode {
+    /// The move that leads to the node. Is `None` for the root and after a null move.
+    pub r#move: Option<SearchMove>,
+    pub ply: u8,
+    /// The remaining depth.
+    pub depth: u8,
+    pub alpha: Score,
+    pub beta: Score,
+    /// The score from the view of the team that has to move. Is `None` if the node was skipped
+    /// without being searched.
+    pub score: Option<Score>,
+    pub outcome: NodeOutcome,
+}
+
+impl Display for RecordedNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.r#move {
+            Some(m) => write!(f, "{}", m)?,
+            None if self.ply == 0 => write!(f, "root")?,
+            None => write!(f, "null")?,
+        }
+        write!(f, " d={} [{}, {}]", self.depth, self.alpha, self.beta)?;
+        if let Some(score) = self.score {
+            write!(f, " {}", score)?;
+        }
+        write!(f, " {}", self.outcome)
+    }
+}
+
+/// Records the nodes of the last search of the root up to a maximum ply. Every new search of the
+/// root replaces the previously recorded tree.
+#[derive(Debug, Clone)]
+pub struct SearchRecorder {
+    max_ply: u8,
+    /// The recorded nodes with the indices of their children. The root is the first node.
+    nodes: Vec<(RecordedNode, Vec<usize>)>,
+    /// The indices of the nodes that are currently searched.
+    path: Vec<usize>,
+    /// The move that leads to the next node that is entered.
+    next_move: Option<SearchMove>,
+}
+
+impl SearchRecorder {
+    /// Returns a recorder that records every node up to the given ply.
+    pub fn new(max_ply: u8) -> SearchRecorder {
+        SearchRecorder {
+            max_ply,
+            nodes: vec![],
+            path: vec![],
+            next_move: None,
+        }
+    }
+
+    /// Returns true if nodes at the given ply get recorded.
+    pub(crate) fn records(&self, ply: u8) -> bool {
+        ply <= self.max_ply
+    }
+
+    /// Sets the move that leads to the next node. `None` stands for a null move.
+    pub(crate) fn set_next_move(&mut self, m: Option<SearchMove>) {
+        self.next_move = m;
+    }
+
+    /// Adds a node as a child of the current node and makes it the current node.
+    pub(crate) fn enter(&mut self, ply: u8, depth: u8, alpha: Score, beta: Score) {
+        if ply == 0 {
+            self.nodes.clear();
+            self.path.clear();
+        }
+        let node = RecordedNode {
+            r#move: self.next_move.take(),
+            ply,
+            depth,
+            alpha,
+            beta,
+            score: None,
+            outcome: NodeOutcome::Open,
+        };
+        let index = self.add(node);
+        self.path.push(index);
+    }
+
+    /// Finishes the current node and makes its parent the current node.
+    pub(crate) fn exit(&mut self, score: Score, outcome: NodeOutcome) {
+        if let Some(index) = self.path.pop() {
+            let node = &mut self.nodes[index].0;
+            node.score = Some(score);
+            node.outcome = outcome;
+        }
+    }
+
+    /// Adds a move that was skipped without being searched as a child of the current node.
+    pub(crate) fn skip(
+        &mut self,
+        m: SearchMove,
+        depth: u8,
+        alpha: Score,
+        beta: Score,
+        reason: PruneReason,
+    ) {
+        let ply = self.path.len() as u8;
+        if !self.records(ply) {
+            return;
+        }
+        let node = RecordedNode {
+            r#move: Some(m),
+            ply,
+            depth,
+            alpha,
+            beta,
+            score: None,
+            outcome: NodeOutcome::Pruned(reason),
+        };
+        self.add(node);
+    }
+
+    fn add(&mut self, node: RecordedNode) -> usize {
+        let index = self.nodes.len();
+        if let Some(parent) = self.path.last() {
+            self.nodes[*parent].1.push(index);
+        }
+        self.nodes.push((node, vec![]));
+        index
+    }
+
+    /// Returns the amount of recorded nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the recorded tree. Is `None` if nothing was recorded yet.
+    pub fn to_tree(&self) -> Option<Tree<RecordedNode>> {
+        if self.nodes.is_empty() {
+            None
+        } else {
+            Some(self.subtree(0))
+        }
+    }
+
+    fn subtree(&self, index: usize) -> Tree<RecordedNode> {
+        let (node, children) = &self.nodes[index];
+        let mut tree = tr(node.clone());
+        for child in children {
+            tree.push_back(self.subtree(*child));
+        }
+        tree
+    }
+
+    /// Exports the recorded tree in the DOT language of Graphviz.
+    pub fn to_dot(&self) -> String {
+        let mut result = String::from("digraph search {\n    node [shape=box];\n");
+        for (index, (node, children)) in self.nodes.iter().enumerate() {
+            let mut label = format!("d={} [{}, {}]", node.depth, node.alpha, node.beta);
+            if let Some(score) = node.score {
+                label += &format!("\\n{}", score);
+            }
+            label += &format!("\\n{}", node.outcome);
+            result += &format!("    n{} [label=\"{}\"];\n", index, label);
+            for child in children {
+                let edge = match self.nodes[*child].0.r#move {
+                    Some(m) => m.to_string(),
+                    None => String::from("null"),
+                };
+                result += &format!("    n{} -> n{} [label=\"{}\"];\n", index, child, edge);
+            }
+        }
+        result + "}\n"
+    }
+
+    /// Exports the recorded tree as JSON. Every node is an object with its children in the
+    /// `children` array and the scores are in centipawns. Is `null` if nothing was recorded yet.
+    pub fn to_json(&self) -> String {
+        if self.nodes.is_empty() {
+            String::from("null")
+        } else {
+            self.node_to_json(0)
+        }
+    }
+
+    fn node_to_json(&self, index: usize) -> String {
+        let (node, children) = &self.nodes[index];
+        let r#move = match node.r#move {
+            Some(m) => format!("\"{}\"", m),
+            None => String::from("null"),
+        };
+        let score = match node.score {
+            Some(score) => score.get_value().to_string(),
+            None => String::from("null"),
+        };
+        let children: Vec<String> = children.iter().map(|c| self.node_to_json(*c)).collect();
+        format!(
+            "{{\"move\":{},\"ply\":{},\"depth\":{},\"alpha\":{},\"beta\":{},\"score\":{},\"outcome\":\"{}\",\"children\":[{}]}}",
+            r#move,
+            node.ply,
+            node.depth,
+            node.alpha.get_value(),
+            node.beta.get_value(),
+            score,
+            node.outcome,
+            children.join(",")
+        )
+    }
+}
+
+impl Display for SearchRecorder {
+    /// Writes the recorded tree in the format of [`tree_to_string`].
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.to_tree() {
+            Some(tree) => write!(f, "{}", tree_to_string::<RecordedNode>(tree.root())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Returns the child of the node that is reached with the given move.
+pub fn find_child<'a>(
+    node: &'a Node<RecordedNode>,
+    m: &SearchMove,
+) -> Option<&'a Node<RecordedNode>> {
+    node.iter()
+        .find(|child| child.data().r#move.as_ref() == Some(m))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pieces::move_gen::BasicMove;
+
+    use super::*;
+
+    fn get_move() -> SearchMove {
+        SearchMove::new((4, 1).into(), BasicMove::new_move((4, 3).into()))
+    }
+
+    fn get_recorder() -> SearchRecorder {
+        let mut recorder = SearchRecorder::new(1);
+        recorder.enter(0, 2, Score::new(-1000), Score::new(1000));
+        recorder.set_next_move(Some(get_move()));
+        recorder.enter(1, 1, Score::new(-1000), Score::new(1000));
+        recorder.exit(Score::new(-30), NodeOutcome::Exact);
+        recorder.skip(
+            get_move(),
+            1,
+            Score::new(-1000),
+            Score::new(1000),
+            PruneReason::Futility,
+        );
+        recorder.exit(Score::new(30), NodeOutcome::Exact);
+        recorder
+    }
+
+    #[test]
+    fn test_record() {
+        let recorder = get_recorder();
+        assert_eq!(3, recorder.len());
+        assert!(recorder.records(1));
+        assert!(!recorder.records(2));
+        let tree = recorder.to_tree().unwrap();
+        let root = tree.root();
+        assert_eq!(None, root.data().r#move);
+        assert_eq!(Some(Score::new(30)), root.data().score);
+        assert_eq!(2, root.degree());
+        let child = find_child(root, &get_move()).unwrap();
+        assert_eq!(Some(Score::new(-30)), child.data().score);
+        assert_eq!(
+            NodeOutcome::Pruned(PruneReason::Futility),
+            root.back().unwrap().data().outcome
+        );
+        assert!(SearchRecorder::new(3).to_tree().is_none());
+    }
+
+    #[test]
+    fn test_new_root_replaces_tree() {
+        let mut recorder = get_recorder();
+        recorder.enter(0, 3, Score::new(-1000), Score::new(1000));
+        recorder.exit(Score::new(10), NodeOutcome::FailLow);
+        assert_eq!(1, recorder.len());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let expected = "digraph search {\n    node [shape=box];\n    \
+        n0 [label=\"d=2 [-10.00, +10.00]\\n+0.30\\nexact\"];\n    \
+        n0 -> n1 [label=\"e2e4\"];\n    \
+        n0 -> n2 [label=\"e2e4\"];\n    \
+        n1 [label=\"d=1 [-10.00, +10.00]\\n-0.30\\nexact\"];\n    \
+        n2 [label=\"d=1 [-10.00, +10.00]\\npruned (futility)\"];\n}\n";
+        assert_eq!(expected, get_recorder().to_dot());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let expected = "{\"move\":null,\"ply\":0,\"depth\":2,\"alpha\":-1000,\"beta\":1000,\
+        \"score\":30,\"outcome\":\"exact\",\"children\":[\
+        {\"move\":\"e2e4\",\"ply\":1,\"depth\":1,\"alpha\":-1000,\"beta\":1000,\"score\":-30,\
+        \"outcome\":\"exact\",\"children\":[]},\
+        {\"move\":\"e2e4\",\"ply\":1,\"depth\":1,\"alpha\":-1000,\"beta\":1000,\"score\":null,\
+        \"outcome\":\"pruned (futility)\",\"children\":[]}]}";
+        assert_eq!(expected, get_recorder().to_json());
+        assert_eq!("null", SearchRecorder::new(1).to_json());
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            "root d=2 [-10.00, +10.00] +0.30 exact, (e2e4 d=1 [-10.00, +10.00] -0.30 exact e2e4 d=1 [-10.00, +10.00] pruned (futility) )",
+            get_recorder().to_string()
+        );
+    }
+}
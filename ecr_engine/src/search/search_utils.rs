@@ -1,12 +1,12 @@
-use crate::board::Board;
 use std::fmt::Display;
-use trees::{tr, Node, Tree};
+use trees::Node;
 
 pub fn search() {
     //TODO: Implement a Graph containing either the full board oor a minimal version of the board(or the move).
 }
 
 /// Prints the tree fens from a given nodet o a string.
+#[allow(clippy::needless_borrow, clippy::op_ref)]
 pub fn tree_to_string<T: Display>(node: &Node<T>) -> String {
     if node.has_no_child() {
         node.data().to_string()
@@ -24,6 +24,7 @@ pub fn tree_to_string<T: Display>(node: &Node<T>) -> String {
 mod tests {
     use super::*;
     use crate::board::Board;
+    use trees::tr;
 
     #[test]
     fn test_search() {
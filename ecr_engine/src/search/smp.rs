@@ -0,0 +1,219 @@
+//! A multi-threaded search using lazy SMP. Every thread searches the same root on its own and the
+//! threads only help each other through the shared transposition table.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use crate::board::Board;
+use crate::eval::evaluator::{ClassicEvaluator, Evaluator};
+use crate::search::alpha_beta::{AlphaBeta, SearchResult};
+use crate::search::config::SearchConfig;
+use crate::search::observer::SearchObserver;
+use crate::search::transposition::TranspositionTable;
+
+/// Searches a board with multiple threads. The main thread searches like a single-threaded
+/// search would, the helper threads fill the transposition table by searching with a slightly
+/// different depth and move order. Every thread evaluates the boards with its own copy of the
+/// [`Evaluator`] `E`.
+#[derive(Debug)]
+pub struct LazySmp<E: Evaluator = ClassicEvaluator> {
+    config: SearchConfig,
+    threads: usize,
+    table: Arc<TranspositionTable>,
+    /// Gets notified about the progress of the main thread.
+    observer: Option<Box<dyn SearchObserver>>,
+    /// The hashes of the boards of the game before the searched board, the oldest one first.
+    game_history: Vec<u64>,
+    evaluator: E,
+}
+
+impl LazySmp {
+    /// Returns a search that uses the given amount of threads including the main thread. Uses at
+    /// least one thread.
+    pub fn new(config: SearchConfig, threads: usize) -> LazySmp {
+        LazySmp::with_table(config, threads, Arc::new(TranspositionTable::default()))
+    }
+
+    /// Returns a search that uses the given amount of threads and transposition table.
+    pub fn with_table(
+        config: SearchConfig,
+        threads: usize,
+        table: Arc<TranspositionTable>,
+    ) -> LazySmp {
+        LazySmp::with_evaluator(config, threads, table, ClassicEvaluator::default())
+    }
+}
+
+impl<E: Evaluator + Clone + 'static> LazySmp<E> {
+    /// Returns a search that uses the given amount of threads, transposition table and evaluator.
+    pub fn with_evaluator(
+        config: SearchConfig,
+        threads: usize,
+        table: Arc<TranspositionTable>,
+        evaluator: E,
+    ) -> LazySmp<E> {
+        LazySmp {
+            config,
+            threads: threads.max(1),
+            table,
+            observer: None,
+            game_history: vec![],
+            evaluator,
+        }
+    }
+
+    /// Returns the evaluator the threads get a copy of.
+    pub fn get_evaluator(&self) -> &E {
+        &self.evaluator
+    }
+
+    /// Returns the amount of threads used by the search.
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Returns the transposition table shared by all threads.
+    pub fn get_table(&self) -> &Arc<TranspositionTable> {
+        &self.table
+    }
+
+    /// Sets the observer that gets notified about the progress of the main thread.
+    pub fn set_observer(&mut self, observer: Box<dyn SearchObserver>) {
+        self.observer = Some(observer);
+    }
+
+    /// Sets the hashes of the boards that occurred in the game before the board that gets
+    /// searched, the oldest one first. Every thread scores repeating one of them as a draw.
+    pub fn set_game_history(&mut self, history: Vec<u64>) {
+        self.game_history = history;
+    }
+
+    /// Searches the board up to the given depth. The result is the one of the main thread, only
+    /// the nodes are counted over all threads.
+    pub fn search(&mut self, board: &Board, depth: u8) -> SearchResult {
+        let stop = Arc::new(AtomicBool::new(false));
+        let helpers: Vec<_> = (1..self.threads)
+            .map(|index| {
+                let board = board.clone();
+                let mut search = AlphaBeta::with_evaluator(
+                    self.config,
+                    Arc::clone(&self.table),
+                    self.evaluator.clone(),
+                );
+                search.set_stop_flag(Arc::clone(&stop));
+                search.set_order_seed(index as u64);
+                search.set_game_history(self.game_history.clone());
+                // Every second helper searches one ply deeper, so the main thread finds more
+                // results of the next iteration in the table.
+                let helper_depth = depth.saturating_add((index % 2) as u8);
+                thread::spawn(move || {
+                    search.search(&board, helper_depth);
+                    search.get_nodes()
+                })
+            })
+            .collect();
+
+        let mut main =
+            AlphaBeta::with_evaluator(self.config, Arc::clone(&self.table), self.evaluator.clone());
+        main.set_game_history(self.game_history.clone());
+        if let Some(observer) = self.observer.take() {
+            main.set_observer(observer);
+        }
+        let mut result = main.search(board, depth);
+        self.observer = main.take_observer();
+        stop.store(true, Ordering::Relaxed);
+        for helper in helpers {
+            // A helper only panics if the main thread would have panicked as well.
+            result.nodes += helper.join().expect("helper thread panicked");
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval::evaluator::MaterialEvaluator;
+    use crate::score::Score;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    #[test]
+    fn test_new() {
+        assert_eq!(1, LazySmp::new(SearchConfig::default(), 0).get_threads());
+        assert_eq!(4, LazySmp::new(SearchConfig::default(), 4).get_threads());
+    }
+
+    #[test]
+    fn test_board_is_send() {
+        let board = Board::default();
+        let moved = thread::spawn(move || board).join().unwrap();
+        assert_eq!(
+            Board::default().get_zobrist_hash(),
+            moved.get_zobrist_hash()
+        );
+    }
+
+    #[test]
+    fn test_search_mate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1");
+        for threads in 1..=4 {
+            let result = LazySmp::new(SearchConfig::default(), threads).search(&board, 3);
+            assert_eq!(Score::mate_in(1), result.score);
+            assert_eq!("h1h8", result.get_best_move().unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn test_search_repetition() {
+        // Light is lost unless Kb1 repeats the board after Kb1 Kd8 Ka1 Ke8.
+        let board = board_from_fen("4k3/8/8/8/8/8/4q3/K7 w - - 10 60");
+        let history = vec![
+            board_from_fen("4k3/8/8/8/8/8/4q3/1K6 b - - 6 58").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/1K6 w - - 7 59").get_zobrist_hash(),
+            board_from_fen("3k4/8/8/8/8/8/4q3/K7 b - - 8 59").get_zobrist_hash(),
+        ];
+        let mut smp = LazySmp::new(SearchConfig::default(), 3);
+        assert!(smp.search(&board, 2).score < Score::new(-500));
+        let mut smp = LazySmp::new(SearchConfig::default(), 3);
+        smp.set_game_history(history);
+        let result = smp.search(&board, 2);
+        assert_eq!(Score::DRAW, result.score);
+        assert_eq!("a1b1", result.get_best_move().unwrap().to_string());
+    }
+
+    #[test]
+    fn test_search_with_evaluator() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let single = AlphaBeta::with_evaluator(
+            SearchConfig::default(),
+            Arc::new(TranspositionTable::default()),
+            MaterialEvaluator,
+        )
+        .search(&board, 2);
+        assert_ne!(AlphaBeta::new().search(&board, 2).score, single.score);
+        for threads in 1..=3 {
+            let table = Arc::new(TranspositionTable::default());
+            let mut smp =
+                LazySmp::with_evaluator(SearchConfig::default(), threads, table, MaterialEvaluator);
+            let result = smp.search(&board, 2);
+            assert_eq!(single.get_best_move(), result.get_best_move());
+            // The helpers can change the score of the main thread through the table.
+            if threads == 1 {
+                assert_eq!(single.score, result.score);
+            }
+        }
+    }
+
+    #[test]
+    fn test_search_same_score() {
+        let board = board_from_fen("4k3/8/8/8/8/2q5/8/1N2K3 w - - 0 1");
+        let single = AlphaBeta::new().search(&board, 3);
+        let table = Arc::new(TranspositionTable::new(1024));
+        let mut smp = LazySmp::with_table(SearchConfig::default(), 3, table);
+        let result = smp.search(&board, 3);
+        assert_eq!(single.get_best_move(), result.get_best_move());
+        assert!(smp.get_table().get_hashfull() > 0);
+    }
+}
@@ -0,0 +1,1352 @@
+//! Distance-to-mate tablebases for endgames with few pieces. They are generated by retrograde
+//! analysis: starting from the mates, the positions are resolved backwards one ply at a time until
+//! every win and loss is known, and everything that is left is a draw.
+//!
+//! Like everywhere else in the engine, pawns always promote to a queen. Castling and en passant
+//! are ignored, which doesn't matter for endgames this small.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ecr_shared::coordinate::Coordinate;
+use thiserror::Error;
+
+use crate::board::Board;
+use crate::eval::endgame::{MaterialSignature, SignatureError};
+use crate::pieces::{PieceColor, PieceType};
+use crate::score::Score;
+use crate::search::{legal_moves, SearchMove};
+
+/// The most pieces a tablebase can have, including the kings.
+pub const MAX_PIECES: usize = 5;
+/// The extension of the files written by [`Tablebases::save_dir`].
+pub const EXTENSION: &str = "ecrtb";
+
+const MAGIC: &[u8; 8] = b"ECRTB\0\0\0";
+const VERSION: u32 = 1;
+
+/// The entries store the plies until the mate plus one, so they fit into a byte together with
+/// these markers.
+const DRAW: u8 = 0;
+const UNKNOWN: u8 = 254;
+const INVALID: u8 = 255;
+const MAX_PLIES: u8 = 252;
+
+/// The squares a1 to d1, b2 to d2, c3, d3 and d4. Every square can be moved into this triangle by
+/// rotating and mirroring the board.
+const TRIANGLE: [u8; 10] = [0, 1, 2, 3, 9, 10, 11, 18, 19, 27];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+
+/// An error that occurred while generating, reading or writing a tablebase.
+#[derive(Debug, Error)]
+pub enum TablebaseError {
+    #[error("{0} can't have a tablebase, it has more than 5 pieces")]
+    TooManyPieces(MaterialSignature),
+
+    #[error("the file is not a tablebase")]
+    InvalidMagic,
+
+    #[error("unsupported tablebase version {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("the tablebase is truncated or has the wrong amount of entries")]
+    WrongSize,
+
+    #[error("a mate of {0} takes more than 252 plies")]
+    DistanceTooLong(MaterialSignature),
+
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+
+    #[error("cannot access the file: {0}")]
+    Io(#[from] io::Error),
+}
+
+/// The result of a board from the view of the team that has to move, with the amount of plies
+/// until the mate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TablebaseResult {
+    Win(u8),
+    Loss(u8),
+    Draw,
+}
+
+impl TablebaseResult {
+    /// Returns the score of the result when the board is the given amount of plies away from the
+    /// root of the search.
+    pub fn to_score(self, ply: u8) -> Score {
+        match self {
+            TablebaseResult::Win(plies) => Score::mate_in(ply.saturating_add(plies)),
+            TablebaseResult::Loss(plies) => Score::mated_in(ply.saturating_add(plies)),
+            TablebaseResult::Draw => Score::DRAW,
+        }
+    }
+
+    /// Returns the result for the team that plays the move to a board with this result.
+    pub fn before_move(self) -> TablebaseResult {
+        match self {
+            TablebaseResult::Win(plies) => TablebaseResult::Loss(plies.saturating_add(1)),
+            TablebaseResult::Loss(plies) => TablebaseResult::Win(plies.saturating_add(1)),
+            TablebaseResult::Draw => TablebaseResult::Draw,
+        }
+    }
+
+    fn is_better_than(self, other: TablebaseResult) -> bool {
+        self.to_score(0) > other.to_score(0)
+    }
+
+    fn to_entry(self) -> u8 {
+        match self {
+            TablebaseResult::Win(plies) | TablebaseResult::Loss(plies) => plies + 1,
+            TablebaseResult::Draw => DRAW,
+        }
+    }
+
+    fn from_entry(entry: u8) -> Option<TablebaseResult> {
+        match entry {
+            DRAW => Some(TablebaseResult::Draw),
+            UNKNOWN | INVALID => None,
+            // The team that has to move mates after an odd amount of plies.
+            _ if entry.is_multiple_of(2) => Some(TablebaseResult::Win(entry - 1)),
+            _ => Some(TablebaseResult::Loss(entry - 1)),
+        }
+    }
+}
+
+/// A piece of a tablebase with its square numbered `y * 8 + x`.
+type Piece = (PieceColor, PieceType, u8);
+
+/// A move of the piece with the index in [`Layout::pieces`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TableMove {
+    piece: usize,
+    to: u8,
+    capture: Option<usize>,
+    promotion: bool,
+}
+
+/// Which pieces a tablebase has and how their positions are turned into indices. The squares of
+/// the pieces are stored in a fixed order: first light and then dark, each team with the king
+/// first and then from the strongest to the weakest piece.
+///
+/// The king of light is always moved to a part of the board by mirroring, and if there are no
+/// pawns also by rotating the board, so every position is only stored once.
+#[derive(Debug, Clone, PartialEq)]
+struct Layout {
+    pieces: Vec<(PieceColor, PieceType)>,
+    pawns: bool,
+}
+
+impl Layout {
+    fn new(signature: &MaterialSignature) -> Layout {
+        let mut pieces = Vec::new();
+        for color in [PieceColor::Light, PieceColor::Dark].iter() {
+            for piece_type in [
+                PieceType::King,
+                PieceType::Queen,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Knight,
+                PieceType::Pawn,
+            ]
+            .iter()
+            {
+                for _ in 0..signature.get(*color, *piece_type) {
+                    pieces.push((*color, *piece_type));
+                }
+            }
+        }
+        let pawns = pieces
+            .iter()
+            .any(|(_, piece_type)| *piece_type == PieceType::Pawn);
+        Layout { pieces, pawns }
+    }
+
+    fn king_squares(&self) -> usize {
+        if self.pawns {
+            32
+        } else {
+            TRIANGLE.len()
+        }
+    }
+
+    /// Returns the amount of entries of the tablebase.
+    fn size(&self) -> usize {
+        self.king_squares() * 64usize.pow(self.pieces.len() as u32 - 1) * 2
+    }
+
+    /// Returns the index of the position. Positions that are the same after mirroring, rotating or
+    /// swapping pieces of the same type get the same index, which is the smallest one.
+    fn index(&self, squares: &[u8], light_to_move: bool) -> usize {
+        let transforms = if self.pawns { 2 } else { 8 };
+        let mut best = usize::MAX;
+        for transform in 0..transforms {
+            let mut transformed = [0; MAX_PIECES];
+            for (target, square) in transformed.iter_mut().zip(squares) {
+                *target = transform_square(*square, transform);
+            }
+            let transformed = &mut transformed[..squares.len()];
+            let slot = match self.king_slot(transformed[0]) {
+                Some(slot) => slot,
+                None => continue,
+            };
+            self.sort_same_pieces(transformed);
+            let index = transformed[1..]
+                .iter()
+                .fold(slot, |index, square| index * 64 + *square as usize);
+            best = best.min(index * 2 + light_to_move as usize);
+        }
+        best
+    }
+
+    /// Returns the squares of the pieces and if light has to move.
+    fn position(&self, index: usize) -> ([u8; MAX_PIECES], bool) {
+        let mut squares = [0; MAX_PIECES];
+        let mut rest = index / 2;
+        for square in squares[1..self.pieces.len()].iter_mut().rev() {
+            *square = (rest % 64) as u8;
+            rest /= 64;
+        }
+        squares[0] = if self.pawns {
+            (rest / 4 * 8 + rest % 4) as u8
+        } else {
+            TRIANGLE[rest]
+        };
+        (squares, index % 2 == 1)
+    }
+
+    fn king_slot(&self, square: u8) -> Option<usize> {
+        let (x, y) = (square % 8, square / 8);
+        if self.pawns {
+            if x < 4 {
+                return Some((y * 4 + x) as usize);
+            }
+            return None;
+        }
+        TRIANGLE.iter().position(|triangle| *triangle == square)
+    }
+
+    fn sort_same_pieces(&self, squares: &mut [u8]) {
+        let mut start = 0;
+        while start < squares.len() {
+            let mut end = start + 1;
+            while end < squares.len() && self.pieces[end] == self.pieces[start] {
+                end += 1;
+            }
+            squares[start..end].sort_unstable();
+            start = end;
+        }
+    }
+
+    /// Returns true if the position can occur in a game and its index is the one that is stored.
+    fn is_valid(&self, index: usize, squares: &[u8], light_to_move: bool) -> bool {
+        let occupied = occupied(squares);
+        if occupied.count_ones() as usize != squares.len() {
+            return false;
+        }
+        let pawn_on_last_rank = self
+            .pieces
+            .iter()
+            .zip(squares)
+            .any(|((_, piece_type), square)| {
+                *piece_type == PieceType::Pawn && (square / 8 == 0 || square / 8 == 7)
+            });
+        !pawn_on_last_rank && self.index(squares, light_to_move) == index
+    }
+
+    /// Returns true if a piece of the team attacks the square. The captured piece, if any, is
+    /// ignored.
+    fn is_attacked(
+        &self,
+        squares: &[u8],
+        target: u8,
+        by: PieceColor,
+        captured: Option<usize>,
+    ) -> bool {
+        let occupied = self.occupied_without(squares, captured);
+        self.pieces
+            .iter()
+            .zip(squares)
+            .enumerate()
+            .any(|(piece, ((color, piece_type), square))| {
+                *color == by
+                    && Some(piece) != captured
+                    && attacks(*piece_type, *color, *square, target, occupied)
+            })
+    }
+
+    fn is_in_check(&self, squares: &[u8], team: PieceColor, captured: Option<usize>) -> bool {
+        let king = self
+            .pieces
+            .iter()
+            .position(|piece| *piece == (team, PieceType::King))
+            .unwrap();
+        self.is_attacked(squares, squares[king], team.get_opponent(), captured)
+    }
+
+    fn occupied_without(&self, squares: &[u8], captured: Option<usize>) -> u64 {
+        squares
+            .iter()
+            .enumerate()
+            .filter(|(piece, _)| Some(*piece) != captured)
+            .fold(0, |occupied, (_, square)| occupied | 1 << square)
+    }
+
+    /// Returns the legal moves of the team that has to move. This is the same as
+    /// [`legal_moves`] of the [`Board`], but fast enough for the millions of positions of a
+    /// tablebase.
+    fn legal_moves(&self, squares: &[u8], light_to_move: bool) -> Vec<TableMove> {
+        let team = if light_to_move {
+            PieceColor::Light
+        } else {
+            PieceColor::Dark
+        };
+        let occupied = occupied(squares);
+        let owner = |target: u8| squares.iter().position(|square| *square == target);
+        let mut result = Vec::new();
+        for (piece, (color, piece_type)) in self.pieces.iter().enumerate() {
+            if *color != team {
+                continue;
+            }
+            let from = squares[piece];
+            let mut targets = Vec::new();
+            if *piece_type == PieceType::Pawn {
+                let (forward, start_rank) = match team {
+                    PieceColor::Light => (1, 1),
+                    PieceColor::Dark => (-1, 6),
+                };
+                if let Some(single) = step(from, (0, forward)).filter(|to| owner(*to).is_none()) {
+                    targets.push(single);
+                    if from / 8 == start_rank {
+                        targets
+                            .extend(step(single, (0, forward)).filter(|to| owner(*to).is_none()));
+                    }
+                }
+                for side in [-1, 1].iter() {
+                    targets.extend(
+                        step(from, (*side, forward)).filter(|to| {
+                            owner(*to).is_some_and(|other| self.pieces[other].0 != team)
+                        }),
+                    );
+                }
+            } else {
+                targets = reach(*piece_type, from, occupied);
+            }
+            for to in targets {
+                let capture = owner(to);
+                if capture.is_some_and(|other| self.pieces[other].0 == team) {
+                    continue;
+                }
+                let mut next = [0; MAX_PIECES];
+                next[..squares.len()].copy_from_slice(squares);
+                next[piece] = to;
+                if self.is_in_check(&next[..squares.len()], team, capture) {
+                    continue;
+                }
+                result.push(TableMove {
+                    piece,
+                    to,
+                    capture,
+                    promotion: *piece_type == PieceType::Pawn && (to / 8 == 0 || to / 8 == 7),
+                });
+            }
+        }
+        result
+    }
+
+    /// Returns the indices of the positions that lead to the position with a move of the team
+    /// that doesn't have to move. Captures and promotions are left out since they lead to other
+    /// tablebases.
+    fn predecessors(&self, index: usize) -> Vec<usize> {
+        let (squares, light_to_move) = self.position(index);
+        let squares = &squares[..self.pieces.len()];
+        let mover = if light_to_move {
+            PieceColor::Dark
+        } else {
+            PieceColor::Light
+        };
+        let occupied = occupied(squares);
+        let mut result = Vec::new();
+        for (piece, (color, piece_type)) in self.pieces.iter().enumerate() {
+            if *color != mover {
+                continue;
+            }
+            for origin in origins(*piece_type, mover, squares[piece], occupied) {
+                let mut previous = [0; MAX_PIECES];
+                previous[..squares.len()].copy_from_slice(squares);
+                previous[piece] = origin;
+                result.push(self.index(&previous[..squares.len()], !light_to_move));
+            }
+        }
+        result.sort_unstable();
+        result.dedup();
+        result
+    }
+}
+
+/// Returns the square after rotating or mirroring the board. The bits of the transform mirror the
+/// files, mirror the ranks and swap files and ranks, in reverse order.
+fn transform_square(square: u8, transform: u8) -> u8 {
+    let (mut x, mut y) = (square % 8, square / 8);
+    if transform & 4 != 0 {
+        std::mem::swap(&mut x, &mut y);
+    }
+    if transform & 1 != 0 {
+        x = 7 - x;
+    }
+    if transform & 2 != 0 {
+        y = 7 - y;
+    }
+    y * 8 + x
+}
+
+fn occupied(squares: &[u8]) -> u64 {
+    squares
+        .iter()
+        .fold(0, |occupied, square| occupied | 1 << square)
+}
+
+fn to_square(coordinate: Coordinate) -> u8 {
+    coordinate.get_y() * 8 + coordinate.get_x()
+}
+
+/// Returns the square next to the square in the direction if it is on the board.
+fn step(square: u8, (dx, dy): (i8, i8)) -> Option<u8> {
+    let x = (square % 8) as i8 + dx;
+    let y = (square / 8) as i8 + dy;
+    if (0..8).contains(&x) && (0..8).contains(&y) {
+        Some((y * 8 + x) as u8)
+    } else {
+        None
+    }
+}
+
+/// Returns the squares a piece other than a pawn can move to if all of them were empty or
+/// occupied by the opponent.
+fn reach(piece_type: PieceType, square: u8, occupied: u64) -> Vec<u8> {
+    let jumps = |offsets: &[(i8, i8)]| {
+        offsets
+            .iter()
+            .filter_map(|offset| step(square, *offset))
+            .collect::<Vec<u8>>()
+    };
+    let slides = |directions: &[(i8, i8)]| {
+        let mut result = Vec::new();
+        for direction in directions {
+            let mut current = square;
+            while let Some(next) = step(current, *direction) {
+                result.push(next);
+                if occupied & 1 << next != 0 {
+                    break;
+                }
+                current = next;
+            }
+        }
+        result
+    };
+    match piece_type {
+        PieceType::King => jumps(&KING_OFFSETS),
+        PieceType::Knight => jumps(&KNIGHT_OFFSETS),
+        PieceType::Bishop => slides(&BISHOP_DIRECTIONS),
+        PieceType::Rook => slides(&ROOK_DIRECTIONS),
+        PieceType::Queen => [slides(&BISHOP_DIRECTIONS), slides(&ROOK_DIRECTIONS)].concat(),
+        PieceType::Pawn => vec![],
+    }
+}
+
+/// Returns the empty squares the piece can have come from without capturing or promoting.
+fn origins(piece_type: PieceType, color: PieceColor, square: u8, occupied: u64) -> Vec<u8> {
+    let is_empty = |square: &u8| occupied & 1 << square == 0;
+    if piece_type != PieceType::Pawn {
+        return reach(piece_type, square, occupied)
+            .into_iter()
+            .filter(is_empty)
+            .collect();
+    }
+    let (back, first_rank, double_rank) = match color {
+        PieceColor::Light => (-1, 0, 3),
+        PieceColor::Dark => (1, 7, 4),
+    };
+    let mut result = Vec::new();
+    if let Some(single) = step(square, (0, back)).filter(is_empty) {
+        if single / 8 != first_rank {
+            result.push(single);
+        }
+        if square / 8 == double_rank {
+            result.extend(step(single, (0, back)).filter(is_empty));
+        }
+    }
+    result
+}
+
+/// Returns true if the piece on the square attacks the target.
+fn attacks(
+    piece_type: PieceType,
+    color: PieceColor,
+    square: u8,
+    target: u8,
+    occupied: u64,
+) -> bool {
+    let dx = (target % 8) as i8 - (square % 8) as i8;
+    let dy = (target / 8) as i8 - (square / 8) as i8;
+    let is_clear = || {
+        let direction = (dx.signum(), dy.signum());
+        let mut current = step(square, direction);
+        while let Some(next) = current.filter(|next| *next != target) {
+            if occupied & 1 << next != 0 {
+                return false;
+            }
+            current = step(next, direction);
+        }
+        true
+    };
+    let diagonal = dx != 0 && dx.abs() == dy.abs();
+    let straight = (dx == 0) != (dy == 0);
+    match piece_type {
+        PieceType::King => dx.abs().max(dy.abs()) == 1,
+        PieceType::Knight => dx.abs() * dy.abs() == 2,
+        PieceType::Bishop => diagonal && is_clear(),
+        PieceType::Rook => straight && is_clear(),
+        PieceType::Queen => (diagonal || straight) && is_clear(),
+        PieceType::Pawn => {
+            let forward = match color {
+                PieceColor::Light => 1,
+                PieceColor::Dark => -1,
+            };
+            dx.abs() == 1 && dy == forward
+        }
+    }
+}
+
+/// Returns the signature of the pieces and if it had to be flipped so that light is the team with
+/// the stronger pieces, which is how tablebases are stored.
+fn normalize(signature: MaterialSignature) -> (MaterialSignature, bool) {
+    let strength = |team: PieceColor| {
+        [
+            PieceType::Queen,
+            PieceType::Rook,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Pawn,
+        ]
+        .iter()
+        .map(|piece_type| signature.get(team, *piece_type))
+        .collect::<Vec<u8>>()
+    };
+    if strength(PieceColor::Light) >= strength(PieceColor::Dark) {
+        (signature, false)
+    } else {
+        (signature.flipped(), true)
+    }
+}
+
+fn signature_of(pieces: &[Piece]) -> MaterialSignature {
+    pieces.iter().fold(
+        MaterialSignature::default(),
+        |signature, (color, piece_type, _)| signature.with_piece(*color, *piece_type),
+    )
+}
+
+/// The results of every position with the same material.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tablebase {
+    signature: MaterialSignature,
+    layout: Layout,
+    entries: Vec<u8>,
+}
+
+impl Tablebase {
+    /// Returns the material of the tablebase. Light has the stronger pieces, boards where dark has
+    /// them are probed with the colors swapped.
+    pub fn get_signature(&self) -> MaterialSignature {
+        self.signature
+    }
+
+    /// Returns the amount of positions, including the ones that can't occur in a game.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the amount of plies of the longest mate, or `None` if no position is won.
+    pub fn get_longest_mate(&self) -> Option<u8> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match TablebaseResult::from_entry(*entry) {
+                Some(TablebaseResult::Win(plies)) => Some(plies),
+                _ => None,
+            })
+            .max()
+    }
+
+    /// Reads a tablebase from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Tablebase, TablebaseError> {
+        Tablebase::from_bytes(&fs::read(path)?)
+    }
+
+    /// Writes the tablebase to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), TablebaseError> {
+        fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Reads a tablebase. Every number is stored in little endian:
+    ///
+    /// - the magic bytes `ECRTB\0\0\0` and the version `1` as `u32`
+    /// - the length of the signature as `u32` and the signature like `KRvK`
+    /// - the amount of entries as `u64`
+    /// - the entries as `u8`, packed in blocks: a byte `n` below 128 is followed by `n + 1`
+    ///   entries, a byte `n` from 128 on is followed by one entry that repeats `n - 126` times
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tablebase, TablebaseError> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(8)? != MAGIC {
+            return Err(TablebaseError::InvalidMagic);
+        }
+        let version = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(TablebaseError::UnsupportedVersion(version));
+        }
+        let length = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+        let signature = String::from_utf8_lossy(reader.take(length)?).parse()?;
+        let mut table = Tablebase::new(signature)?;
+        let amount = u64::from_le_bytes(reader.take(8)?.try_into().unwrap()) as usize;
+        if amount != table.len() {
+            return Err(TablebaseError::WrongSize);
+        }
+        table.entries.clear();
+        while table.entries.len() < amount {
+            let header = reader.take(1)?[0] as usize;
+            if header < 128 {
+                table.entries.extend_from_slice(reader.take(header + 1)?);
+            } else {
+                let entry = reader.take(1)?[0];
+                table
+                    .entries
+                    .resize(table.entries.len() + header - 126, entry);
+            }
+        }
+        if table.entries.len() != amount {
+            return Err(TablebaseError::WrongSize);
+        }
+        if reader.offset != bytes.len() {
+            return Err(TablebaseError::WrongSize);
+        }
+        Ok(table)
+    }
+
+    /// Returns the tablebase in the format of [`Tablebase::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let signature = self.signature.to_string();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(signature.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(signature.as_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        let entries = &self.entries;
+        let mut start = 0;
+        while start < entries.len() {
+            let run = entries[start..]
+                .iter()
+                .take(129)
+                .take_while(|entry| **entry == entries[start])
+                .count();
+            if run > 1 {
+                bytes.push((run + 126) as u8);
+                bytes.push(entries[start]);
+                start += run;
+                continue;
+            }
+            // Everything up to the next run.
+            let mut end = start + 1;
+            while end < entries.len()
+                && end - start < 128
+                && entries.get(end + 1) != Some(&entries[end])
+            {
+                end += 1;
+            }
+            bytes.push((end - start - 1) as u8);
+            bytes.extend_from_slice(&entries[start..end]);
+            start = end;
+        }
+        bytes
+    }
+
+    /// Returns a tablebase for the signature where every position is invalid.
+    fn new(signature: MaterialSignature) -> Result<Tablebase, TablebaseError> {
+        let (normalized, _) = normalize(signature);
+        let layout = Layout::new(&normalized);
+        if layout.pieces.len() > MAX_PIECES {
+            return Err(TablebaseError::TooManyPieces(signature));
+        }
+        Ok(Tablebase {
+            signature: normalized,
+            entries: vec![INVALID; layout.size()],
+            layout,
+        })
+    }
+
+    /// Returns the result of the position with the pieces, which have to match the signature of
+    /// the tablebase with or without swapped colors.
+    fn probe_pieces(&self, pieces: &[Piece], light_to_move: bool) -> Option<TablebaseResult> {
+        let flipped = signature_of(pieces) != self.signature;
+        let mut squares = [0; MAX_PIECES];
+        let mut used = [false; MAX_PIECES];
+        for (square, (color, piece_type)) in squares.iter_mut().zip(&self.layout.pieces) {
+            let found = pieces.iter().enumerate().position(|(i, piece)| {
+                let color = if flipped {
+                    color.get_opponent()
+                } else {
+                    *color
+                };
+                !used[i] && piece.0 == color && piece.1 == *piece_type
+            })?;
+            used[found] = true;
+            let original = pieces[found].2;
+            *square = if flipped {
+                (7 - original / 8) * 8 + original % 8
+            } else {
+                original
+            };
+        }
+        let squares = &squares[..self.layout.pieces.len()];
+        let index = self.layout.index(squares, light_to_move != flipped);
+        TablebaseResult::from_entry(self.entries[index])
+    }
+}
+
+/// Reads the parts of [`Tablebase::from_bytes`].
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, amount: usize) -> Result<&'a [u8], TablebaseError> {
+        let bytes = self
+            .bytes
+            .get(self.offset..self.offset + amount)
+            .ok_or(TablebaseError::WrongSize)?;
+        self.offset += amount;
+        Ok(bytes)
+    }
+}
+
+/// A collection of tablebases that can be probed with any board that has the material of one of
+/// them.
+#[derive(Debug, Clone, Default)]
+pub struct Tablebases {
+    tables: HashMap<MaterialSignature, Tablebase>,
+}
+
+impl Tablebases {
+    pub fn new() -> Tablebases {
+        Tablebases::default()
+    }
+
+    /// Returns the tablebase for the material, no matter which team has the stronger pieces.
+    pub fn get(&self, signature: MaterialSignature) -> Option<&Tablebase> {
+        self.tables.get(&normalize(signature).0)
+    }
+
+    /// Returns the tablebases sorted by their material.
+    pub fn get_tablebases(&self) -> Vec<&Tablebase> {
+        let mut tables: Vec<&Tablebase> = self.tables.values().collect();
+        tables.sort_by_key(|table| table.signature.to_string());
+        tables
+    }
+
+    /// Adds a tablebase, replacing the one with the same material.
+    pub fn insert(&mut self, table: Tablebase) {
+        self.tables.insert(table.signature, table);
+    }
+
+    /// Generates the tablebase for the material and all tablebases it can turn into by captures
+    /// and promotions, unless they are already there. A pawn only ever promotes to a queen, since
+    /// that is the only promotion a move of the board can do, so the results are exact for the
+    /// engine but not for positions that need an underpromotion. The time this takes grows quickly
+    /// with the amount of pieces: three pieces take moments, four pieces take seconds to minutes
+    /// and five pieces take hours.
+    pub fn generate(&mut self, signature: MaterialSignature) -> Result<(), TablebaseError> {
+        let (signature, _) = normalize(signature);
+        if self.tables.contains_key(&signature) {
+            return Ok(());
+        }
+        let table = Tablebase::new(signature)?;
+        for team in [PieceColor::Light, PieceColor::Dark].iter() {
+            let opponent = team.get_opponent();
+            for piece_type in [
+                PieceType::Queen,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Knight,
+                PieceType::Pawn,
+            ]
+            .iter()
+            {
+                if signature.get(opponent, *piece_type) > 0 {
+                    self.generate(signature.without_piece(opponent, *piece_type))?;
+                }
+            }
+            if signature.get(*team, PieceType::Pawn) > 0 {
+                let promoted = signature
+                    .without_piece(*team, PieceType::Pawn)
+                    .with_piece(*team, PieceType::Queen);
+                self.generate(promoted)?;
+            }
+        }
+        let table = self.retrograde(table)?;
+        self.insert(table);
+        Ok(())
+    }
+
+    /// Reads every tablebase in the directory.
+    pub fn load_dir<P: AsRef<Path>>(path: P) -> Result<Tablebases, TablebaseError> {
+        let mut tablebases = Tablebases::new();
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+            {
+                tablebases.insert(Tablebase::load(path)?);
+            }
+        }
+        Ok(tablebases)
+    }
+
+    /// Writes every tablebase into the directory, named after its material like `KRvK.ecrtb`.
+    pub fn save_dir<P: AsRef<Path>>(&self, path: P) -> Result<(), TablebaseError> {
+        fs::create_dir_all(&path)?;
+        for table in self.tables.values() {
+            let name = format!("{}.{}", table.signature, EXTENSION);
+            table.save(path.as_ref().join(name))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the result of the board from the view of the team that has to move, or `None` if
+    /// there is no tablebase for its material. Meant for the leaves of a search. Like the search,
+    /// the result assumes that pawns promote to a queen.
+    pub fn probe(&self, board: &Board) -> Option<TablebaseResult> {
+        if board.get_pieces().len() > MAX_PIECES {
+            return None;
+        }
+        let pieces: Vec<Piece> = board
+            .get_pieces()
+            .iter()
+            .map(|inner| {
+                let piece = inner.borrow();
+                (
+                    piece.get_color(),
+                    piece.get_piece().get_type(),
+                    to_square(piece.get_coordinate()),
+                )
+            })
+            .collect();
+        self.probe_pieces(&pieces, board.get_light_to_move())
+    }
+
+    /// Returns the move that mates the fastest, holds the draw or delays the mate the longest,
+    /// together with the result of the board. Returns `None` if there is no legal move or if one
+    /// of the moves leads to a board without a tablebase. Meant for the root of a search.
+    pub fn probe_root(&self, board: &Board) -> Option<(SearchMove, TablebaseResult)> {
+        self.probe_root_moves(board)?.into_iter().next()
+    }
+
+    /// Returns every legal move together with the result for the team that plays it, sorted from
+    /// the best to the worst move. Returns `None` if one of the moves leads to a board without a
+    /// tablebase.
+    pub fn probe_root_moves(&self, board: &Board) -> Option<Vec<(SearchMove, TablebaseResult)>> {
+        let mut moves = legal_moves(board)
+            .into_iter()
+            .map(|m| Some((m, self.probe(&m.apply(board))?.before_move())))
+            .collect::<Option<Vec<_>>>()?;
+        moves.sort_by_key(|(_, result)| Reverse(result.to_score(0)));
+        Some(moves)
+    }
+
+    fn probe_pieces(&self, pieces: &[Piece], light_to_move: bool) -> Option<TablebaseResult> {
+        self.get(signature_of(pieces))?
+            .probe_pieces(pieces, light_to_move)
+    }
+
+    /// Fills the tablebase, whose captures and promotions lead to tablebases that are already
+    /// there. The positions are resolved ply by ply: a position is won one ply after one of its
+    /// moves leads to a lost position, and lost one ply after the last of its moves leads to a
+    /// won position.
+    fn retrograde(&self, mut table: Tablebase) -> Result<Tablebase, TablebaseError> {
+        let layout = table.layout.clone();
+        let size = layout.size();
+        let entries = &mut table.entries;
+        // The amount of moves that stay in the tablebase and don't lead to a won position yet.
+        let mut remaining = vec![0u8; size];
+        // The best result of the captures and promotions.
+        let mut conversions = vec![INVALID; size];
+        // The positions whose result gets known through a capture or a promotion at the ply.
+        let mut pending = vec![Vec::new(); MAX_PLIES as usize + 1];
+        let mut current = Vec::new();
+        let signature = table.signature;
+        let too_long = || TablebaseError::DistanceTooLong(signature);
+
+        for index in 0..size {
+            let (squares, light_to_move) = layout.position(index);
+            let squares = &squares[..layout.pieces.len()];
+            if !layout.is_valid(index, squares, light_to_move) {
+                continue;
+            }
+            let team = if light_to_move {
+                PieceColor::Light
+            } else {
+                PieceColor::Dark
+            };
+            if layout.is_in_check(squares, team.get_opponent(), None) {
+                continue;
+            }
+            let moves = layout.legal_moves(squares, light_to_move);
+            if moves.is_empty() {
+                entries[index] = if layout.is_in_check(squares, team, None) {
+                    current.push(index);
+                    TablebaseResult::Loss(0).to_entry()
+                } else {
+                    DRAW
+                };
+                continue;
+            }
+            let mut successors = Vec::new();
+            let mut conversion: Option<TablebaseResult> = None;
+            for m in moves {
+                if m.capture.is_some() || m.promotion {
+                    let pieces: Vec<Piece> = layout
+                        .pieces
+                        .iter()
+                        .zip(squares)
+                        .enumerate()
+                        .filter(|(piece, _)| Some(*piece) != m.capture)
+                        .map(
+                            |(piece, ((color, piece_type), square))| match piece == m.piece {
+                                true if m.promotion => (*color, PieceType::Queen, m.to),
+                                true => (*color, *piece_type, m.to),
+                                false => (*color, *piece_type, *square),
+                            },
+                        )
+                        .collect();
+                    let result = self
+                        .probe_pieces(&pieces, !light_to_move)
+                        .expect("the tablebases of captures and promotions are generated first")
+                        .before_move();
+                    if conversion.is_none_or(|best| result.is_better_than(best)) {
+                        conversion = Some(result);
+                    }
+                } else {
+                    let mut next = [0; MAX_PIECES];
+                    next[..squares.len()].copy_from_slice(squares);
+                    next[m.piece] = m.to;
+                    successors.push(layout.index(&next[..squares.len()], !light_to_move));
+                }
+            }
+            successors.sort_unstable();
+            successors.dedup();
+            remaining[index] = successors.len() as u8;
+            entries[index] = UNKNOWN;
+            match conversion {
+                Some(TablebaseResult::Win(plies)) | Some(TablebaseResult::Loss(plies))
+                    if plies > MAX_PLIES =>
+                {
+                    return Err(too_long());
+                }
+                Some(TablebaseResult::Draw) if successors.is_empty() => entries[index] = DRAW,
+                Some(TablebaseResult::Win(plies)) => pending[plies as usize].push(index),
+                Some(TablebaseResult::Loss(plies)) if successors.is_empty() => {
+                    pending[plies as usize].push(index)
+                }
+                _ => {}
+            }
+            if let Some(conversion) = conversion {
+                conversions[index] = conversion.to_entry();
+            }
+        }
+
+        for ply in 0..=MAX_PLIES as usize {
+            for index in std::mem::take(&mut pending[ply]) {
+                if entries[index] == UNKNOWN {
+                    entries[index] = conversions[index];
+                    current.push(index);
+                }
+            }
+            if current.is_empty() {
+                continue;
+            }
+            if ply == MAX_PLIES as usize {
+                return Err(too_long());
+            }
+            let next_ply = ply as u8 + 1;
+            let mut next = Vec::new();
+            for index in current.drain(..) {
+                let lost = matches!(
+                    TablebaseResult::from_entry(entries[index]),
+                    Some(TablebaseResult::Loss(_))
+                );
+                for previous in layout.predecessors(index) {
+                    if entries[previous] != UNKNOWN {
+                        continue;
+                    }
+                    if lost {
+                        entries[previous] = TablebaseResult::Win(next_ply).to_entry();
+                        next.push(previous);
+                        continue;
+                    }
+                    remaining[previous] -= 1;
+                    if remaining[previous] > 0 {
+                        continue;
+                    }
+                    match TablebaseResult::from_entry(conversions[previous]) {
+                        Some(TablebaseResult::Draw) => entries[previous] = DRAW,
+                        // The position waits for its capture or promotion.
+                        Some(TablebaseResult::Win(_)) => {}
+                        Some(TablebaseResult::Loss(plies)) if plies > next_ply => {
+                            pending[plies as usize].push(previous)
+                        }
+                        _ => {
+                            entries[previous] = TablebaseResult::Loss(next_ply).to_entry();
+                            next.push(previous);
+                        }
+                    }
+                }
+            }
+            current = next;
+        }
+        for entry in entries.iter_mut() {
+            if *entry == UNKNOWN {
+                *entry = DRAW;
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::str::FromStr;
+    use std::sync::OnceLock;
+
+    use ecr_shared::board::BoardCastleState;
+
+    use crate::eval::kpk::{self, KpkResult};
+    use crate::pieces::BoardPiece;
+    use crate::utils::board_from_fen;
+
+    use super::*;
+
+    fn board_from_position(layout: &Layout, squares: &[u8], light_to_move: bool) -> Board {
+        let mut board = Board::empty();
+        board.castle_state = BoardCastleState::empty();
+        board.to_move = if light_to_move {
+            PieceColor::Light
+        } else {
+            PieceColor::Dark
+        };
+        for ((color, piece_type), square) in layout.pieces.iter().zip(squares) {
+            let coordinate = Coordinate::new(square % 8, square / 8);
+            board.add_piece(BoardPiece::new_from_type(*piece_type, coordinate, *color));
+        }
+        board.calculate_threatened_states();
+        board
+    }
+
+    fn signature(text: &str) -> MaterialSignature {
+        MaterialSignature::from_str(text).unwrap()
+    }
+
+    /// Returns the tablebases of KPK, KQK and KK, which are only generated once.
+    fn generated() -> &'static Tablebases {
+        static TABLEBASES: OnceLock<Tablebases> = OnceLock::new();
+        TABLEBASES.get_or_init(|| {
+            let mut tablebases = Tablebases::new();
+            tablebases.generate(signature("KPvK")).unwrap();
+            tablebases
+        })
+    }
+
+    #[test]
+    fn test_index() {
+        let layout = Layout::new(&signature("KRvKR"));
+        for index in [0, 1, 12_345, layout.size() - 1].iter() {
+            let (squares, light_to_move) = layout.position(*index);
+            assert_eq!(light_to_move, index % 2 == 1);
+            let squares = &squares[..layout.pieces.len()];
+            let canonical = layout.index(squares, light_to_move);
+            assert!(canonical <= *index);
+            assert_eq!(
+                canonical,
+                layout.index(&layout.position(canonical).0[..4], light_to_move)
+            );
+        }
+        // Mirrored and rotated boards have the same index.
+        let squares = [12, 45, 60, 3];
+        for transform in 0..8 {
+            let transformed: Vec<u8> = squares
+                .iter()
+                .map(|square| transform_square(*square, transform))
+                .collect();
+            assert_eq!(
+                layout.index(&squares, true),
+                layout.index(&transformed, true)
+            );
+        }
+        // Pawns are only mirrored from left to right.
+        let layout = Layout::new(&signature("KPvK"));
+        assert_eq!(
+            layout.index(&[4, 12, 60], true),
+            layout.index(&[3, 11, 59], true)
+        );
+        assert_ne!(
+            layout.index(&[4, 12, 60], true),
+            layout.index(&[60, 52, 4], true)
+        );
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        // The move generation of the tablebases has to match the one of the board.
+        // Pawns are left out, the board doesn't let a king move in front of a pawn of the opponent.
+        for name in ["KQvKR", "KBNvK", "KRvKN"].iter() {
+            let layout = Layout::new(&signature(name));
+            for index in (0..layout.size()).step_by(7919) {
+                let (squares, light_to_move) = layout.position(index);
+                let squares = &squares[..layout.pieces.len()];
+                let team = if light_to_move {
+                    PieceColor::Light
+                } else {
+                    PieceColor::Dark
+                };
+                if !layout.is_valid(index, squares, light_to_move)
+                    || layout.is_in_check(squares, team.get_opponent(), None)
+                {
+                    continue;
+                }
+                let board = board_from_position(&layout, squares, light_to_move);
+                let mut expected: Vec<(u8, u8)> = legal_moves(&board)
+                    .iter()
+                    .map(|m| (to_square(m.from), to_square(m.get_to())))
+                    .collect();
+                let mut actual: Vec<(u8, u8)> = layout
+                    .legal_moves(squares, light_to_move)
+                    .iter()
+                    .map(|m| (squares[m.piece], m.to))
+                    .collect();
+                expected.sort_unstable();
+                actual.sort_unstable();
+                assert_eq!(expected, actual, "{}", board);
+                assert_eq!(
+                    board.is_in_check(team),
+                    layout.is_in_check(squares, team, None)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        for result in [
+            TablebaseResult::Draw,
+            TablebaseResult::Loss(0),
+            TablebaseResult::Win(1),
+            TablebaseResult::Win(MAX_PLIES - 1),
+            TablebaseResult::Loss(MAX_PLIES),
+        ]
+        .iter()
+        {
+            assert_eq!(
+                Some(*result),
+                TablebaseResult::from_entry(result.to_entry())
+            );
+        }
+        assert_eq!(None, TablebaseResult::from_entry(UNKNOWN));
+        assert_eq!(None, TablebaseResult::from_entry(INVALID));
+        assert_eq!(
+            TablebaseResult::Loss(4),
+            TablebaseResult::Win(3).before_move()
+        );
+        assert!(TablebaseResult::Win(1).is_better_than(TablebaseResult::Win(3)));
+        assert!(TablebaseResult::Loss(6).is_better_than(TablebaseResult::Loss(2)));
+        assert!(TablebaseResult::Draw.is_better_than(TablebaseResult::Loss(20)));
+    }
+
+    #[test]
+    fn test_generate_kqk() {
+        let tablebases = generated();
+        let table = tablebases.get(signature("KvKQ")).unwrap();
+        assert_eq!("KQvK", table.get_signature().to_string());
+        // The longest mate with a queen takes ten moves.
+        assert_eq!(Some(19), table.get_longest_mate());
+        assert_eq!(
+            None,
+            tablebases.get(signature("KvK")).unwrap().get_longest_mate()
+        );
+
+        let probe = |fen: &str| tablebases.probe(&board_from_fen(fen));
+        assert_eq!(
+            Some(TablebaseResult::Win(1)),
+            probe("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1")
+        );
+        assert_eq!(
+            Some(TablebaseResult::Loss(0)),
+            probe("k6Q/8/1K6/8/8/8/8/8 b - - 0 1")
+        );
+        // The same mate with swapped colors.
+        assert_eq!(
+            Some(TablebaseResult::Win(1)),
+            probe("6q1/8/8/8/8/1k6/8/K7 b - - 0 1")
+        );
+        // The queen gets captured.
+        assert_eq!(
+            Some(TablebaseResult::Draw),
+            probe("k7/1Q6/8/8/8/8/8/7K b - - 0 1")
+        );
+        // Stalemate.
+        assert_eq!(
+            Some(TablebaseResult::Draw),
+            probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")
+        );
+        assert_eq!(None, probe("k7/8/1K6/8/8/8/8/5RQ1 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_generate_kpk() {
+        let tablebases = generated();
+        let names: Vec<String> = tablebases
+            .get_tablebases()
+            .iter()
+            .map(|table| table.get_signature().to_string())
+            .collect();
+        assert_eq!(vec!["KPvK", "KQvK", "KvK"], names);
+        let probe = |fen: &str| tablebases.probe(&board_from_fen(fen));
+        // Matches the bitbase for wins and draws.
+        assert!(matches!(
+            probe("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(TablebaseResult::Win(_))
+        ));
+        assert_eq!(
+            Some(TablebaseResult::Draw),
+            probe("4k3/8/4P3/4K3/8/8/8/8 w - - 0 1")
+        );
+        assert_eq!(
+            Some(TablebaseResult::Draw),
+            probe("k7/8/1K6/P7/8/8/8/8 w - - 0 1")
+        );
+        assert!(matches!(
+            probe("8/8/8/3p4/8/3k4/8/3K4 w - - 0 1"),
+            Some(TablebaseResult::Loss(_))
+        ));
+
+        // Every position agrees with the bitbase.
+        let table = tablebases.get(signature("KPvK")).unwrap();
+        for (index, entry) in table.entries.iter().enumerate() {
+            let (squares, light_to_move) = table.layout.position(index);
+            let coordinate = |square: u8| Coordinate::new(square % 8, square / 8);
+            // The entries are from the view of the team that has to move.
+            let expected = match (TablebaseResult::from_entry(*entry), light_to_move) {
+                (Some(TablebaseResult::Win(_)), true) | (Some(TablebaseResult::Loss(_)), false) => {
+                    KpkResult::Win
+                }
+                (Some(TablebaseResult::Draw), _) => KpkResult::Draw,
+                (Some(result), _) => panic!("{:?} for {:?}", result, squares),
+                (None, _) => continue,
+            };
+            let actual = kpk::probe_squares(
+                PieceColor::Light,
+                coordinate(squares[0]),
+                coordinate(squares[1]),
+                coordinate(squares[2]),
+                light_to_move,
+            );
+            assert_eq!(Some(expected), actual, "{:?}", squares);
+        }
+    }
+
+    #[test]
+    fn test_promotion_matches_board() {
+        let tablebases = generated();
+        let board = board_from_fen("8/4P3/8/8/8/8/k7/4K3 w - - 0 1");
+        let (m, result) = tablebases.probe_root(&board).unwrap();
+        assert_eq!("e7e8", m.to_string());
+        // The tablebase of the pawn expects the same queen that the move of the board creates.
+        let after = m.apply(&board);
+        let queen = after.get_at(Coordinate::new(4, 7)).unwrap();
+        assert_eq!(PieceType::Queen, queen.borrow().get_piece().get_type());
+        assert_eq!(Some(result), tablebases.probe(&board));
+    }
+
+    #[test]
+    fn test_probe_root() {
+        let tablebases = generated();
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/6Q1 w - - 0 1");
+        let (m, result) = tablebases.probe_root(&board).unwrap();
+        assert_eq!(TablebaseResult::Win(1), result);
+        assert!(m.apply(&board).is_in_check(PieceColor::Dark));
+        assert!(legal_moves(&m.apply(&board)).is_empty());
+        // Without the tablebase of the capture.
+        let mut tablebases = Tablebases::new();
+        tablebases.insert(generated().get(signature("KQvK")).unwrap().clone());
+        let board = board_from_fen("k7/1Q6/8/8/8/8/8/7K b - - 0 1");
+        assert_eq!(None, tablebases.probe_root(&board));
+    }
+
+    #[test]
+    fn test_bytes() {
+        let table = generated().get(signature("KQvK")).unwrap();
+        let bytes = table.to_bytes();
+        assert!(bytes.len() < table.len());
+        assert_eq!(table, &Tablebase::from_bytes(&bytes).unwrap());
+        assert!(matches!(
+            Tablebase::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(TablebaseError::WrongSize)
+        ));
+        let mut wrong = bytes.clone();
+        wrong[0] = b'X';
+        assert!(matches!(
+            Tablebase::from_bytes(&wrong),
+            Err(TablebaseError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Tablebases::new().generate(signature("KQRvKRN")),
+            Err(TablebaseError::TooManyPieces(_))
+        ));
+
+        let path = env::temp_dir().join(format!("ecr_tablebases_{}", std::process::id()));
+        generated().save_dir(&path).unwrap();
+        let loaded = Tablebases::load_dir(&path);
+        fs::remove_dir_all(&path).unwrap();
+        let loaded = loaded.unwrap();
+        assert_eq!(generated().get_tablebases(), loaded.get_tablebases());
+    }
+}
@@ -0,0 +1,244 @@
+//! A transposition table that stores the results of already searched boards. It can be shared
+//! between threads without any locks.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use ecr_shared::coordinate::Coordinate;
+
+use crate::score::Score;
+
+/// Tells how the stored score relates to the real score of the board.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Bound {
+    /// The stored score is the real score.
+    Exact,
+    /// The real score is at least the stored score (the search failed high).
+    Lower,
+    /// The real score is at most the stored score (the search failed low).
+    Upper,
+}
+
+/// The information that is stored for a board.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TableEntry {
+    pub score: Score,
+    /// The remaining depth the board was searched with.
+    pub depth: u8,
+    pub bound: Bound,
+    /// The starting and the target square of the best move that was found.
+    pub best_move: Option<(Coordinate, Coordinate)>,
+}
+
+impl TableEntry {
+    /// Packs the entry into a single number. The bound is never stored as `0`, so a packed entry is
+    /// never `0`, which is the value of an empty slot.
+    fn pack(&self) -> u64 {
+        let mut data = self.score.get_value() as u32 as u64;
+        data |= (self.depth as u64) << 32;
+        data |= match self.bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        } << 40;
+        if let Some((from, to)) = self.best_move {
+            data |= 1 << 42;
+            data |= (square_index(from) as u64) << 43;
+            data |= (square_index(to) as u64) << 49;
+        }
+        data
+    }
+
+    fn unpack(data: u64) -> TableEntry {
+        let best_move = if data & (1 << 42) != 0 {
+            Some((
+                square_from_index((data >> 43) & 63),
+                square_from_index((data >> 49) & 63),
+            ))
+        } else {
+            None
+        };
+        TableEntry {
+            score: Score::new(data as u32 as i32),
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) & 3 {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            best_move,
+        }
+    }
+}
+
+fn square_index(square: Coordinate) -> u8 {
+    square.get_y() * 8 + square.get_x()
+}
+
+fn square_from_index(index: u64) -> Coordinate {
+    Coordinate::new((index % 8) as u8, (index / 8) as u8)
+}
+
+/// A single slot of the table. The key is stored xor-ed with the data, so a slot that was written
+/// by two threads at the same time doesn't match any hash anymore and is simply ignored.
+#[derive(Debug, Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A transposition table with a fixed size. Only one entry is stored per slot, newer entries
+/// always replace older ones.
+#[derive(Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Slot>,
+}
+
+impl Default for TranspositionTable {
+    /// Returns a table with `2^16` slots, which uses 1 MiB of memory.
+    fn default() -> Self {
+        TranspositionTable::new(1 << 16)
+    }
+}
+
+impl TranspositionTable {
+    /// Returns a table with at least the given amount of slots. The amount gets rounded up to the
+    /// next power of two.
+    pub fn new(slots: usize) -> TranspositionTable {
+        let amount = slots.max(1).next_power_of_two();
+        let mut result = Vec::with_capacity(amount);
+        result.resize_with(amount, Slot::default);
+        TranspositionTable { slots: result }
+    }
+
+    /// Returns the amount of slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    fn get_slot(&self, hash: u64) -> &Slot {
+        &self.slots[hash as usize & (self.slots.len() - 1)]
+    }
+
+    /// Returns the entry of the board with the given hash, if there is one.
+    pub fn probe(&self, hash: u64) -> Option<TableEntry> {
+        let slot = self.get_slot(hash);
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        if data != 0 && key ^ data == hash {
+            Some(TableEntry::unpack(data))
+        } else {
+            None
+        }
+    }
+
+    /// Stores the entry for the board with the given hash.
+    pub fn store(&self, hash: u64, entry: TableEntry) {
+        let slot = self.get_slot(hash);
+        let data = entry.pack();
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns how full the table is in permille, estimated from the first thousand slots.
+    pub fn get_hashfull(&self) -> u16 {
+        let sample = self.slots.len().min(1000);
+        let used = self.slots[..sample]
+            .iter()
+            .filter(|slot| slot.data.load(Ordering::Relaxed) != 0)
+            .count();
+        (used * 1000 / sample) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::*;
+
+    fn get_entry() -> TableEntry {
+        TableEntry {
+            score: Score::new(-35),
+            depth: 7,
+            bound: Bound::Lower,
+            best_move: Some(((4, 1).into(), (4, 3).into())),
+        }
+    }
+
+    #[test]
+    fn test_pack() {
+        let entry = get_entry();
+        assert_eq!(entry, TableEntry::unpack(entry.pack()));
+        let entry = TableEntry {
+            score: Score::mate_in(5),
+            depth: 0,
+            bound: Bound::Exact,
+            best_move: None,
+        };
+        assert_eq!(entry, TableEntry::unpack(entry.pack()));
+        assert_ne!(0, entry.pack());
+    }
+
+    #[test]
+    fn test_new() {
+        assert_eq!(1, TranspositionTable::new(0).len());
+        assert_eq!(1024, TranspositionTable::new(1000).len());
+        assert_eq!(1 << 16, TranspositionTable::default().len());
+    }
+
+    #[test]
+    fn test_probe_and_store() {
+        let table = TranspositionTable::new(16);
+        assert_eq!(None, table.probe(42));
+        table.store(42, get_entry());
+        assert_eq!(Some(get_entry()), table.probe(42));
+        // Another hash in the same slot doesn't match.
+        assert_eq!(None, table.probe(42 + 16));
+        table.clear();
+        assert_eq!(None, table.probe(42));
+    }
+
+    #[test]
+    fn test_get_hashfull() {
+        let table = TranspositionTable::new(2000);
+        assert_eq!(0, table.get_hashfull());
+        for hash in 0..512 {
+            table.store(hash, get_entry());
+        }
+        assert_eq!(512, table.get_hashfull());
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let table = Arc::new(TranspositionTable::new(1024));
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let table = Arc::clone(&table);
+                thread::spawn(move || {
+                    for hash in (i * 256)..((i + 1) * 256) {
+                        table.store(hash, get_entry());
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        for hash in 0..1024 {
+            assert_eq!(Some(get_entry()), table.probe(hash));
+        }
+    }
+}
@@ -1,10 +1,38 @@
 use ecr_shared::coordinate::Coordinate;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-/// Returns the supplied value wrapped inside a [`Rc`] that contains a [`RefCell`] with the value.
-pub fn new_rc_refcell<T>(value: T) -> Rc<RefCell<T>> {
-    Rc::new(RefCell::new(value))
+/// A replacement for a [`RefCell`](std::cell::RefCell) that can be shared between threads. Offers
+/// the same `borrow` and `borrow_mut` methods, but is backed by a [`RwLock`].
+#[derive(Debug, Default)]
+pub struct SharedCell<T>(RwLock<T>);
+
+impl<T> SharedCell<T> {
+    pub fn new(value: T) -> SharedCell<T> {
+        SharedCell(RwLock::new(value))
+    }
+
+    /// Immutably borrows the wrapped value. Multiple immutable borrows can exist at the same time.
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        // A poisoned lock only means that another thread panicked, the value itself is still fine.
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Mutably borrows the wrapped value.
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: PartialEq> PartialEq for SharedCell<T> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+
+/// Returns the supplied value wrapped inside an [`Arc`] that contains a [`SharedCell`] with the
+/// value.
+pub fn new_shared<T>(value: T) -> Arc<SharedCell<T>> {
+    Arc::new(SharedCell::new(value))
 }
 
 pub fn get_en_passant_actual(target_square: Coordinate) -> Coordinate {
@@ -16,22 +44,40 @@ pub fn get_en_passant_actual(target_square: Coordinate) -> Coordinate {
     }
 }
 
+/// Returns the board of a FEN string that is known to be valid. Only used by tests.
+#[cfg(test)]
+pub(crate) fn board_from_fen(fen: &str) -> crate::board::Board {
+    use std::str::FromStr;
+
+    ecr_formats::fen::Fen::from_str(fen).unwrap().into()
+}
+
 #[cfg(test)]
 mod tests {
     use std::any;
 
     use super::*;
 
+    #[allow(clippy::useless_format)]
     fn get_type_name<T>(_: &T) -> String {
         format!("{}", any::type_name::<T>())
     }
 
     #[test]
-    fn test_new_rc_refcell() {
-        let r = new_rc_refcell(String::from("Test"));
+    fn test_new_shared() {
+        let r = new_shared(String::from("Test"));
         assert_eq!(
-            "alloc::rc::Rc<core::cell::RefCell<alloc::string::String>>",
+            "alloc::sync::Arc<ecr_engine::utils::SharedCell<alloc::string::String>>",
             get_type_name(&r)
         );
     }
+
+    #[test]
+    fn test_shared_cell() {
+        let cell = SharedCell::new(1);
+        assert_eq!(1, *cell.borrow());
+        *cell.borrow_mut() += 1;
+        assert_eq!(2, *cell.borrow());
+        assert_eq!(SharedCell::new(2), cell);
+    }
 }
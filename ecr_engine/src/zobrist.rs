@@ -0,0 +1,184 @@
+//! Zobrist hashing of boards. Every property of a board gets a random key and the hash of a board
+//! is the xor of the keys of all properties it has.
+
+use std::ops::Deref;
+
+use ecr_shared::coordinate::Coordinate;
+
+use crate::board;
+use crate::pieces::{PieceColor, PieceType};
+
+/// One key for every piece type of every color on every square.
+const PIECE_KEYS: usize = 2 * 6 * 64;
+/// Index of the key that is used when dark has to move.
+const DARK_TO_MOVE: usize = PIECE_KEYS;
+/// Index of the first of the four castle keys.
+const CASTLE: usize = DARK_TO_MOVE + 1;
+/// Index of the first of the eight en passant file keys.
+const EN_PASSANT: usize = CASTLE + 4;
+const KEY_AMOUNT: usize = EN_PASSANT + 8;
+
+/// The random keys. They are generated at compile time, so every hash stays the same between runs.
+const KEYS: [u64; KEY_AMOUNT] = generate_keys();
+
+/// Generates the keys with the splitmix64 generator.
+const fn generate_keys() -> [u64; KEY_AMOUNT] {
+    let mut keys = [0; KEY_AMOUNT];
+    let mut state: u64 = 0x1dea_c0ff_ee15_600d;
+    let mut i = 0;
+    while i < KEY_AMOUNT {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+/// Returns the key of a piece of the given type and color on the given square.
+pub fn piece_key(piece_type: PieceType, color: PieceColor, square: Coordinate) -> u64 {
+    let type_index = match piece_type {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    let color_index = match color {
+        PieceColor::Light => 0,
+        PieceColor::Dark => 1,
+    };
+    let square_index = square.get_y() as usize * 8 + square.get_x() as usize;
+    KEYS[(color_index * 6 + type_index) * 64 + square_index]
+}
+
+impl board::Board {
+    /// Returns the zobrist hash of the board. Boards with the same pieces, the same team to move,
+    /// the same castle state and the same en passant target square have the same hash.
+    pub fn get_zobrist_hash(&self) -> u64 {
+        let mut hash = 0;
+        for inner in self.get_pieces() {
+            let piece = inner.deref().borrow();
+            hash ^= piece_key(
+                piece.get_piece().get_type(),
+                piece.get_color(),
+                piece.get_coordinate(),
+            );
+        }
+        if !self.get_light_to_move() {
+            hash ^= KEYS[DARK_TO_MOVE];
+        }
+        let castle_state = self.get_castle_state();
+        let castles = [
+            castle_state.light_king_side,
+            castle_state.light_queen_side,
+            castle_state.dark_king_side,
+            castle_state.dark_queen_side,
+        ];
+        for (i, castle) in castles.iter().enumerate() {
+            if *castle {
+                hash ^= KEYS[CASTLE + i];
+            }
+        }
+        if let Some(en_passant) = self.get_en_passant_target() {
+            hash ^= KEYS[EN_PASSANT + en_passant.target_square.get_x() as usize];
+        }
+        hash
+    }
+
+    /// Returns the zobrist hash of only the pawns on the board. Boards with the same pawn
+    /// structure have the same pawn hash, which is used to cache the pawn evaluation.
+    pub fn get_pawn_hash(&self) -> u64 {
+        let mut hash = 0;
+        for inner in self.get_pieces() {
+            let piece = inner.deref().borrow();
+            if piece.get_piece().get_type() == PieceType::Pawn {
+                hash ^= piece_key(PieceType::Pawn, piece.get_color(), piece.get_coordinate());
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ecr_formats::fen::Fen;
+
+    use crate::board::Board;
+    use crate::pieces::move_gen::BasicMove;
+
+    use super::*;
+
+    fn hash_of(fen: &str) -> u64 {
+        Board::from(Fen::from_str(fen).unwrap()).get_zobrist_hash()
+    }
+
+    #[test]
+    fn test_generate_keys() {
+        let keys = generate_keys();
+        for i in 0..KEY_AMOUNT {
+            assert_ne!(0, keys[i]);
+            for j in 0..i {
+                assert_ne!(keys[i], keys[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_zobrist_hash() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(Board::default().get_zobrist_hash(), hash_of(start));
+        // The move counters don't change the hash.
+        assert_eq!(
+            hash_of(start),
+            hash_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 12")
+        );
+        assert_ne!(
+            hash_of(start),
+            hash_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1")
+        );
+        assert_ne!(
+            hash_of(start),
+            hash_of("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1")
+        );
+        assert_ne!(
+            hash_of("4k3/8/8/8/8/8/8/4K2R w - - 0 1"),
+            hash_of("4k3/8/8/8/8/8/8/4K1R1 w - - 0 1")
+        );
+    }
+
+    #[test]
+    fn test_get_zobrist_hash_transposition() {
+        // The same board reached with different move orders has the same hash.
+        let mut one = Board::default();
+        one.r#move((6, 0).into(), &BasicMove::new_move((5, 2).into()));
+        one.r#move((6, 7).into(), &BasicMove::new_move((5, 5).into()));
+        one.r#move((1, 0).into(), &BasicMove::new_move((2, 2).into()));
+        let mut two = Board::default();
+        two.r#move((1, 0).into(), &BasicMove::new_move((2, 2).into()));
+        two.r#move((6, 7).into(), &BasicMove::new_move((5, 5).into()));
+        two.r#move((6, 0).into(), &BasicMove::new_move((5, 2).into()));
+        assert_eq!(one.get_zobrist_hash(), two.get_zobrist_hash());
+        assert_ne!(Board::default().get_zobrist_hash(), one.get_zobrist_hash());
+    }
+
+    #[test]
+    fn test_get_pawn_hash() {
+        let pawns = |fen: &str| Board::from(Fen::from_str(fen).unwrap()).get_pawn_hash();
+        // Only the pawns matter.
+        assert_eq!(
+            pawns("4k3/3p4/8/8/8/8/4P3/4K3 w - - 0 1"),
+            pawns("r3k3/3p4/8/8/8/2N5/4P3/3K4 b - - 0 1")
+        );
+        assert_ne!(
+            pawns("4k3/3p4/8/8/8/8/4P3/4K3 w - - 0 1"),
+            pawns("4k3/3p4/8/8/8/4P3/8/4K3 w - - 0 1")
+        );
+        assert_eq!(0, pawns("4k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+    }
+}
@@ -56,6 +56,7 @@ pub struct Fen {
 
 impl Display for Fen {
     /// Converts the [`Fen`] struct into the FEN string itself.
+    #[allow(clippy::to_string_in_format_args)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -98,6 +99,7 @@ impl Display for Fen {
 impl FromStr for Fen {
     type Err = FenError;
 
+    #[allow(clippy::needless_borrow)]
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         // First we split the string using regex
         let caps = match FEN_REGEX.captures(s) {
@@ -189,6 +191,7 @@ impl FromStr for FenPiecePlacements {
 
 impl Display for FenPiecePlacements {
     /// Turns the list of [`FenPiece`]s into the FEN format.
+    #[allow(clippy::needless_range_loop)]
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut pieces = self.pieces.clone();
         // Sorting by coordinate, so that the first coordinate in the Vec is the lowest one